@@ -0,0 +1,783 @@
+use std::{collections::HashMap, f64::consts::TAU};
+
+use ordered_float::OrderedFloat;
+
+use crate::{
+    diagram::Diagram,
+    geometry::{convex_hull, nearest_first, ClipRegion, Cut, Point, Scalar, WeightedSite},
+    metric::{sew, Ring},
+    Error,
+};
+
+/// Halving a stretch of an edge more often than this gains nothing in `f64`.
+const MAX_DEPTH: u32 = 40;
+
+/// Compute the additively weighted Voronoi diagram of `sites`, in which a
+/// point belongs to the site it has the smallest distance `|p - site| -
+/// weight` to, cut off at the boundary of `region`. This is how far a point
+/// is from the circle around the site with the weight as radius, for sensors
+/// of different ranges, say.
+///
+/// Between the cells of sites `s` and `t` an edge runs along the branch of
+/// the hyperbola with foci `s` and `t` where `|p - s| - |p - t|` is the
+/// weight of `s` minus that of `t`, a straight line when the weights are
+/// equal. The diagram follows it by a polyline straying no further than
+/// about `tolerance` from it, with a vertex wherever it bends. Fails with
+/// [`Error::DegenerateInput`] unless `tolerance` is positive.
+///
+/// A site whose circle lies inside that of another one is outweighed
+/// everywhere and gets a face without a cell; sites equal to an earlier one
+/// in position and weight are merged into it, see [`Diagram::site_map`].
+///
+/// The sweep has no way to order events by hyperbolas exactly. Instead each
+/// cell is traced on its own, as the part of the region its site reaches
+/// before any other, in floating point, and the cells are then sewn
+/// together, with every edge taken from the cell of the lower site. Far away
+/// sites are ruled out by how close they could get, which keeps tracing a
+/// cell short, but every cell still weighs every other site, as does finding
+/// the outweighed ones, so this takes `O(n^2)` time in all, which makes it
+/// slow beyond a few thousand sites.
+pub fn apollonius_diagram<S: Scalar, R: ClipRegion<S>>(
+    sites: &[WeightedSite<S>],
+    region: &R,
+    tolerance: S,
+) -> Result<Diagram<S>, Error> {
+    if region.is_empty() || !tolerance.is_finite() || tolerance <= S::zero() {
+        return Err(Error::DegenerateInput);
+    }
+    for (idx, site) in sites.iter().enumerate() {
+        if !site.point.x.is_finite() || !site.point.y.is_finite() || !site.weight.is_finite() {
+            return Err(Error::NonFiniteSite(idx));
+        }
+        if !region.contains(&site.point) {
+            return Err(Error::SiteOutsideBoundingBox(idx));
+        }
+    }
+
+    let mut first = HashMap::new();
+    let site_map = sites
+        .iter()
+        .enumerate()
+        .map(|(idx, site)| *first.entry(site).or_insert(idx))
+        .collect::<Vec<_>>();
+    let coords = sites.iter().map(coords).collect::<Vec<_>>();
+    let corners = region
+        .vertices()
+        .iter()
+        .map(|p| [p.x.to_f64(), p.y.to_f64()])
+        .collect::<Vec<_>>();
+
+    // t hides s when the circle of s lies inside that of t
+    let distinct = (0..sites.len())
+        .filter(|&idx| site_map[idx] == idx)
+        .collect::<Vec<_>>();
+    let hidden = |s: usize| {
+        distinct
+            .iter()
+            .any(|&t| t != s && closest_approach(coords[s], coords[t]) <= 0.0)
+    };
+    let visible = distinct
+        .iter()
+        .copied()
+        .filter(|&s| !hidden(s))
+        .collect::<Vec<_>>();
+
+    let points = sites
+        .iter()
+        .map(|site| site.point.clone())
+        .collect::<Vec<_>>();
+    let mut diagram = Diagram::new(&points, site_map);
+    diagram.set_additive_weights(sites, tolerance.clone());
+    let visible_points = visible
+        .iter()
+        .map(|&s| points[s].clone())
+        .collect::<Vec<_>>();
+    diagram.set_convex_hull(
+        convex_hull(&visible_points)
+            .into_iter()
+            .map(|idx| visible[idx])
+            .collect(),
+    );
+    if visible.len() < 2 {
+        return Ok(diagram);
+    }
+
+    let tolerance = tolerance.to_f64();
+    let cells = visible
+        .iter()
+        .map(|&s| (s, trace_cell(s, &coords, &visible, &corners, tolerance)))
+        .collect::<Vec<_>>();
+    sew(&mut diagram, &cells, region)?;
+    diagram.find_chains();
+    Ok(diagram)
+}
+
+fn coords<S: Scalar>(site: &WeightedSite<S>) -> ([f64; 2], f64) {
+    (
+        [site.point.x.to_f64(), site.point.y.to_f64()],
+        site.weight.to_f64(),
+    )
+}
+
+/// Half of `|s - t| - (weight of t - weight of s)`, the closest the edge
+/// between the two can come to `s`. Not positive when `t` hides `s`.
+fn closest_approach((s, s_weight): ([f64; 2], f64), (t, t_weight): ([f64; 2], f64)) -> f64 {
+    ((t[0] - s[0]).hypot(t[1] - s[1]) - (t_weight - s_weight)) / 2.0
+}
+
+/// The cell of `s`, counter-clockwise, with the corners where what bounds it
+/// changes. Only the edges towards higher sites, which the diagram takes
+/// from this cell, are followed through their bends.
+fn trace_cell<S: Scalar>(
+    s: usize,
+    coords: &[([f64; 2], f64)],
+    visible: &[usize],
+    corners: &[[f64; 2]],
+    tolerance: f64,
+) -> Ring<S> {
+    let (center, weight) = coords[s];
+    let mut envelope = Envelope::new();
+    for (idx, a) in corners.iter().enumerate() {
+        let b = corners[(idx + 1) % corners.len()];
+        // outward normal of a counter-clockwise edge
+        let n = [b[1] - a[1], a[0] - b[0]];
+        let curve = Polar {
+            num: n[0] * (a[0] - center[0]) + n[1] * (a[1] - center[1]),
+            g: n,
+            h: 0.0,
+        };
+        envelope.insert(Owner::Region(idx), curve);
+    }
+
+    let mut candidates = visible
+        .iter()
+        .filter(|&&t| t != s)
+        .map(|&t| (OrderedFloat(closest_approach(coords[s], coords[t])), t))
+        .collect::<Vec<_>>();
+    nearest_first(&mut candidates, |approach, t| {
+        if approach.0 >= envelope.max_radius() {
+            return false;
+        }
+        let (other, other_weight) = coords[t];
+        let d = [other[0] - center[0], other[1] - center[1]];
+        let delta = other_weight - weight;
+        let curve = Polar {
+            num: d[0] * d[0] + d[1] * d[1] - delta * delta,
+            g: [2.0 * d[0], 2.0 * d[1]],
+            h: 2.0 * delta,
+        };
+        envelope.insert(Owner::Site(t), curve);
+        true
+    });
+
+    let at = |curve: &Polar, angle: f64| {
+        let [x, y] = curve.point(center, angle);
+        Point::new(S::from_f64(x), S::from_f64(y))
+    };
+    let pieces = envelope.edges();
+    let mut res = vec![];
+    for (idx, &(owner, curve, angles)) in pieces.iter().enumerate() {
+        let (start, end) = if curve.num > 0.0 {
+            (at(&curve, angles.0), at(&curve, angles.1))
+        } else {
+            // An edge of the region through the site leaves the cell nothing
+            // on its far side, so the cell runs along it, turning at the
+            // site where two of them meet.
+            let site = Point::new(S::from_f64(center[0]), S::from_f64(center[1]));
+            let (_, prev, (_, prev_end)) = pieces[(idx + pieces.len() - 1) % pieces.len()];
+            let (_, next, (next_start, _)) = pieces[(idx + 1) % pieces.len()];
+            (
+                if prev.num > 0.0 {
+                    at(&prev, prev_end)
+                } else {
+                    site.clone()
+                },
+                if next.num > 0.0 {
+                    at(&next, next_start)
+                } else {
+                    site
+                },
+            )
+        };
+        let across = match owner {
+            Owner::Site(t) => Some(t),
+            _ => None,
+        };
+        let bends = match across {
+            Some(t) if s < t => {
+                let bisector = AdditiveBisector::from_coords(coords[s], coords[t], tolerance);
+                bisector.corners(&bisector.along(&start), &bisector.along(&end))
+            }
+            _ => vec![],
+        };
+        res.push((start, across));
+        res.extend(bends.into_iter().map(|p| (p, across)));
+    }
+    res
+}
+
+/// The boundary of the points closer to `site` than to `other` by
+/// additively weighted distance, running with the site on its left, see
+/// [`apollonius_diagram`]. Worked out in `f64`, and in coordinates centred
+/// between the sites with the x axis pointing from `site` to `other`, where
+/// it is the graph of `x = c / 2 * sqrt(1 + y^2 / b2)`.
+pub(crate) struct AdditiveBisector {
+    center: [f64; 2],
+    /// Unit vector from the site to the other one
+    axis: [f64; 2],
+    /// The weight of the site less that of the other one
+    c: f64,
+    /// Square of the semi-minor axis of the hyperbola, positive as long as
+    /// neither site hides the other
+    b2: f64,
+    /// How far the corners may let the curve stray between them
+    tolerance: f64,
+}
+
+impl AdditiveBisector {
+    pub fn new<S: Scalar>(site: &WeightedSite<S>, other: &WeightedSite<S>, tolerance: &S) -> Self {
+        Self::from_coords(coords(site), coords(other), tolerance.to_f64())
+    }
+
+    fn from_coords(
+        (s, s_weight): ([f64; 2], f64),
+        (t, t_weight): ([f64; 2], f64),
+        tolerance: f64,
+    ) -> Self {
+        let d = [t[0] - s[0], t[1] - s[1]];
+        let len = d[0].hypot(d[1]);
+        let c = s_weight - t_weight;
+        Self {
+            center: [(s[0] + t[0]) / 2.0, (s[1] + t[1]) / 2.0],
+            axis: [d[0] / len, d[1] / len],
+            c,
+            b2: (len * len - c * c) / 4.0,
+            tolerance,
+        }
+    }
+
+    fn local<S: Scalar>(&self, p: &Point<S>) -> [f64; 2] {
+        let q = [p.x.to_f64() - self.center[0], p.y.to_f64() - self.center[1]];
+        [
+            q[0] * self.axis[0] + q[1] * self.axis[1],
+            q[1] * self.axis[0] - q[0] * self.axis[1],
+        ]
+    }
+
+    /// The x coordinate of the curve at height `y`.
+    fn height(&self, y: f64) -> f64 {
+        self.c / 2.0 * (1.0 + y * y / self.b2).sqrt()
+    }
+
+    /// How far the local point `[x, y]` lies beyond the curve along the x
+    /// axis, negative on the side of the site.
+    fn gap(&self, [x, y]: [f64; 2]) -> f64 {
+        x - self.height(y)
+    }
+
+    fn point<S: Scalar>(&self, y: f64) -> Point<S> {
+        let x = self.height(y);
+        Point::new(
+            S::from_f64(self.center[0] + x * self.axis[0] - y * self.axis[1]),
+            S::from_f64(self.center[1] + x * self.axis[1] + y * self.axis[0]),
+        )
+    }
+
+    /// Push the heights strictly between `a` and `b` at which to bend, the
+    /// stretch between them halved until the chord comes within half the
+    /// tolerance of the curve halfway, and so within the tolerance all
+    /// along, since the gap between them is concave.
+    fn subdivide(&self, a: f64, b: f64, depth: u32, res: &mut Vec<f64>) {
+        let mid = (a + b) / 2.0;
+        let chord = (self.height(a) + self.height(b)) / 2.0;
+        if depth < MAX_DEPTH && (chord - self.height(mid)).abs() > self.tolerance / 2.0 {
+            self.subdivide(a, mid, depth + 1, res);
+            res.push(mid);
+            self.subdivide(mid, b, depth + 1, res);
+        }
+    }
+}
+
+fn lerp<S: Scalar>(a: &Point<S>, b: &Point<S>, t: f64) -> Point<S> {
+    (b.clone() - a.clone()) * S::from_f64(t) + a
+}
+
+impl<S: Scalar> Cut<S> for AdditiveBisector {
+    fn kept(&self, p: &Point<S>) -> bool {
+        self.gap(self.local(p)) < 0.0
+    }
+
+    /// Splits a segment crossing the curve twice where it comes furthest
+    /// onto the other side. The curve bends one way, so the gap changes
+    /// along the segment in a single swing.
+    fn splits(&self, a: &Point<S>, b: &Point<S>) -> Vec<Point<S>> {
+        let (la, lb) = (self.local(a), self.local(b));
+        let (dx, dy) = (lb[0] - la[0], lb[1] - la[1]);
+        if self.c == 0.0 || dy == 0.0 {
+            return vec![];
+        }
+        // where the slope of the curve matches that of the segment
+        let semi = self.b2.sqrt();
+        let r = dx / dy * 2.0 * semi / self.c;
+        if r.abs() >= 1.0 {
+            return vec![];
+        }
+        let t = (r / (1.0 - r * r).sqrt() * semi - la[1]) / dy;
+        if !(0.0 < t && t < 1.0) {
+            return vec![];
+        }
+        let p = lerp(a, b, t);
+        let sides = [self.kept(a), self.kept(&p), self.kept(b)];
+        if sides[0] == sides[2] && sides[0] != sides[1] {
+            vec![p]
+        } else {
+            vec![]
+        }
+    }
+
+    fn crossing(&self, a: &Point<S>, b: &Point<S>) -> Point<S> {
+        let (la, lb) = (self.local(a), self.local(b));
+        let side = self.gap(la) < 0.0;
+        let (mut lo, mut hi) = (0.0, 1.0);
+        for _ in 0..64 {
+            let mid = (lo + hi) / 2.0;
+            let p = [la[0] + (lb[0] - la[0]) * mid, la[1] + (lb[1] - la[1]) * mid];
+            if (self.gap(p) < 0.0) == side {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lerp(a, b, (lo + hi) / 2.0)
+    }
+
+    fn along(&self, p: &Point<S>) -> S {
+        S::from_f64(self.local(p)[1])
+    }
+
+    fn corners(&self, from: &S, to: &S) -> Vec<Point<S>> {
+        if self.c == 0.0 {
+            return vec![];
+        }
+        let mut heights = vec![];
+        self.subdivide(from.to_f64(), to.to_f64(), 0, &mut heights);
+        heights.into_iter().map(|y| self.point(y)).collect()
+    }
+}
+
+/// A curve around a site in polar coordinates: along the unit vector `u` it
+/// lies `num / (g . u + h)` away, where that is positive, and infinitely
+/// far away elsewhere. Both the edge towards another site and an edge of the
+/// region take this form.
+#[derive(Clone, Copy, Debug)]
+struct Polar {
+    num: f64,
+    g: [f64; 2],
+    h: f64,
+}
+
+impl Polar {
+    fn denominator(&self, angle: f64) -> f64 {
+        let (sin, cos) = angle.sin_cos();
+        self.g[0] * cos + self.g[1] * sin + self.h
+    }
+
+    fn radius(&self, angle: f64) -> f64 {
+        let denominator = self.denominator(angle);
+        if denominator > 0.0 {
+            self.num / denominator
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    fn point(&self, [x, y]: [f64; 2], angle: f64) -> [f64; 2] {
+        let r = self.radius(angle);
+        let (sin, cos) = angle.sin_cos();
+        [x + r * cos, y + r * sin]
+    }
+
+    /// Whether this curve comes strictly closer to the site than `other`
+    /// in the direction `angle`.
+    fn beats(&self, other: &Polar, angle: f64) -> bool {
+        let (d, other_d) = (self.denominator(angle), other.denominator(angle));
+        d > 0.0 && (other_d <= 0.0 || self.num * other_d < other.num * d)
+    }
+
+    /// The directions in which this curve and `other` meet, or where this
+    /// one goes off to infinity.
+    fn crossings(&self, other: &Polar) -> impl Iterator<Item = f64> {
+        let v = [
+            self.num * other.g[0] - other.num * self.g[0],
+            self.num * other.g[1] - other.num * self.g[1],
+        ];
+        let c = other.num * self.h - self.num * other.h;
+        directions(v, c).chain(directions(self.g, -self.h))
+    }
+}
+
+/// The angles in `[0, TAU)` of the unit vectors `u` with `u . v = c`.
+fn directions(v: [f64; 2], c: f64) -> impl Iterator<Item = f64> {
+    let len = v[0].hypot(v[1]);
+    let res = if len > 0.0 && c.abs() <= len {
+        let phi = v[1].atan2(v[0]);
+        let alpha = (c / len).acos();
+        vec![phi - alpha, phi + alpha]
+    } else {
+        vec![]
+    };
+    res.into_iter().map(|angle| {
+        let angle = angle.rem_euclid(TAU);
+        if angle < TAU {
+            angle
+        } else {
+            0.0
+        }
+    })
+}
+
+/// What bounds a cell in some direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Owner {
+    Nothing,
+    Site(usize),
+    /// An edge of the region, by the index of its first corner
+    Region(usize),
+}
+
+/// The nearest of a set of curves around a site, by direction.
+struct Envelope {
+    /// The direction each stretch starts at and what bounds it there, up to
+    /// where the next one starts. The first starts at 0, the last runs up
+    /// to a full turn.
+    pieces: Vec<(f64, Owner, Polar)>,
+}
+
+impl Envelope {
+    fn new() -> Self {
+        Self {
+            pieces: vec![(
+                0.0,
+                Owner::Nothing,
+                Polar {
+                    num: 1.0,
+                    g: [0.0, 0.0],
+                    h: 0.0,
+                },
+            )],
+        }
+    }
+
+    /// Let `curve` take over wherever it comes closer than the envelope.
+    fn insert(&mut self, owner: Owner, curve: Polar) {
+        let mut res: Vec<(f64, Owner, Polar)> = vec![];
+        for (idx, &(start, current, current_curve)) in self.pieces.iter().enumerate() {
+            let end = self.pieces.get(idx + 1).map_or(TAU, |piece| piece.0);
+            let mut cuts = vec![start];
+            cuts.extend(
+                curve
+                    .crossings(&current_curve)
+                    .filter(|&angle| start < angle && angle < end),
+            );
+            cuts.push(end);
+            cuts.sort_unstable_by(f64::total_cmp);
+
+            for pair in cuts.windows(2) {
+                if pair[0] >= pair[1] {
+                    continue;
+                }
+                let winner = if curve.beats(&current_curve, (pair[0] + pair[1]) / 2.0) {
+                    (owner, curve)
+                } else {
+                    (current, current_curve)
+                };
+                if res.last().map_or(true, |last| last.1 != winner.0) {
+                    res.push((pair[0], winner.0, winner.1));
+                }
+            }
+        }
+        self.pieces = res;
+    }
+
+    /// The furthest the envelope gets from the site, which it does at the
+    /// end of some piece. Pieces along an edge of the region through the site
+    /// stay at the site.
+    fn max_radius(&self) -> f64 {
+        self.pieces
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, curve))| curve.num > 0.0)
+            .flat_map(|(idx, &(start, _, curve))| {
+                let end = self.pieces.get(idx + 1).map_or(TAU, |piece| piece.0);
+                [curve.radius(start), curve.radius(end)]
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Every stretch with the range of directions it covers, joining the
+    /// last to the first when they are the same.
+    fn edges(&self) -> Vec<(Owner, Polar, (f64, f64))> {
+        let mut res = self
+            .pieces
+            .iter()
+            .enumerate()
+            .map(|(idx, &(start, owner, curve))| {
+                let end = self.pieces.get(idx + 1).map_or(TAU, |piece| piece.0);
+                (owner, curve, (start, end))
+            })
+            .collect::<Vec<_>>();
+        if res.len() > 1 && res[0].0 == res[res.len() - 1].0 {
+            let (_, _, (_, end)) = res.remove(0);
+            res.last_mut().unwrap().2 .1 = end + TAU;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordered_float::OrderedFloat;
+
+    use crate::{
+        fortunes_algorithm,
+        geometry::{distance, polygon_area, ConvexPolygon, PolygonWithHoles},
+        locate::PointLocator,
+        test_utils::{bounding_box, check_diagram_by, point, random_sites},
+    };
+
+    use super::*;
+
+    fn site(x: f64, y: f64, weight: f64) -> WeightedSite {
+        WeightedSite::new(point(x, y), OrderedFloat(weight))
+    }
+
+    fn weighted_distance(p: &Point, site: &WeightedSite) -> f64 {
+        (distance(&site.point, p) - site.weight).into_inner()
+    }
+
+    fn close(a: &Point, b: &Point) -> bool {
+        distance(a, b).into_inner() < 1e-6
+    }
+
+    fn total_area(cells: &[Vec<Point>]) -> f64 {
+        cells
+            .iter()
+            .map(|cell| polygon_area(cell).into_inner())
+            .sum()
+    }
+
+    /// The points along the edges from `face` to `other`, in order.
+    fn polyline(diagram: &Diagram, face: usize, other: usize) -> Vec<Point> {
+        let edges = diagram
+            .neighbours(face)
+            .into_iter()
+            .filter(|n| n.face == other)
+            .map(|n| n.edge)
+            .collect::<Vec<_>>();
+        let at = |v: Option<usize>| diagram.vertex(v.unwrap()).point;
+        let mut res = vec![at(diagram.half_edge(edges[0]).origin)];
+        res.extend(edges.iter().map(|&edge| at(diagram.destination(edge))));
+        res
+    }
+
+    fn distance_to_polyline(p: &Point, polyline: &[Point]) -> f64 {
+        let coords = |p: &Point| [p.x.into_inner(), p.y.into_inner()];
+        let [x, y] = coords(p);
+        polyline
+            .windows(2)
+            .map(|pair| {
+                let ([ax, ay], [bx, by]) = (coords(&pair[0]), coords(&pair[1]));
+                let (dx, dy) = (bx - ax, by - ay);
+                let t = (((x - ax) * dx + (y - ay) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+                (x - ax - t * dx).hypot(y - ay - t * dy)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn equal_weights_give_voronoi_cells() {
+        let bounding_box = bounding_box();
+        let points = random_sites(200, 1000.0, 31);
+        let sites = points
+            .iter()
+            .map(|p| WeightedSite::new(*p, OrderedFloat(12.5)))
+            .collect::<Vec<_>>();
+        let diagram = apollonius_diagram(&sites, &bounding_box, OrderedFloat(1e-9)).unwrap();
+        assert!(diagram.is_additively_weighted());
+        assert!(check_diagram_by(&sites, &diagram, 1e-9, weighted_distance));
+        let voronoi = fortunes_algorithm(&points, &bounding_box).cell_polygons(&bounding_box);
+        let cells = diagram.cell_polygons(&bounding_box);
+
+        for (cell, polygon) in cells.iter().zip(&voronoi) {
+            assert_eq!(cell.len(), polygon.len());
+            assert!(cell.iter().all(|p| polygon.iter().any(|q| close(p, q))));
+        }
+        // straight edges have no bends
+        assert!(diagram
+            .vertices()
+            .iter()
+            .all(|v| voronoi.iter().flatten().any(|q| close(&v.point, q))));
+    }
+
+    #[test]
+    fn random_weights() {
+        let bounding_box = bounding_box();
+        let points = random_sites(300, 1000.0, 32);
+        let weights = random_sites(300, 60.0, 33);
+        let sites = points
+            .iter()
+            .zip(&weights)
+            .map(|(p, w)| WeightedSite::new(*p, w.x))
+            .collect::<Vec<_>>();
+        let diagram = apollonius_diagram(&sites, &bounding_box, OrderedFloat(0.01)).unwrap();
+        assert!(check_diagram_by(&sites, &diagram, 1e-9, weighted_distance));
+
+        // where the cells cross chords of their edges, they stray as far as
+        // those do from their hyperbolas
+        let cells = diagram.cell_polygons(&bounding_box);
+        for (face, cell) in cells.iter().enumerate() {
+            for p in cell {
+                let own = weighted_distance(p, &sites[face]);
+                assert!(sites
+                    .iter()
+                    .all(|other| weighted_distance(p, other) >= own - 0.01));
+            }
+        }
+        assert!((total_area(&cells) - 1e6).abs() < 1.0);
+
+        let polygon = PolygonWithHoles::new(
+            vec![point(0.0, 0.0), point(1000.0, 0.0), point(0.0, 1000.0)],
+            vec![],
+        )
+        .unwrap();
+        let clipped = diagram.cells_in_polygon(&polygon);
+        let area = clipped
+            .parts
+            .iter()
+            .flatten()
+            .map(|part| polygon_area(part.outer()).into_inner())
+            .sum::<f64>();
+        assert!((area - 5e5).abs() < 1.0);
+    }
+
+    #[test]
+    fn hyperbolic_edges_within_tolerance() {
+        let bounding_box = bounding_box();
+        let sites = [site(300.0, 500.0, 0.0), site(700.0, 500.0, 100.0)];
+        let diagram =
+            |tolerance| apollonius_diagram(&sites, &bounding_box, OrderedFloat(tolerance)).unwrap();
+        let (coarse, fine) = (diagram(0.5), diagram(1e-4));
+        assert!(check_diagram_by(&sites, &fine, 1e-9, weighted_distance));
+
+        let (coarse, fine) = (polyline(&coarse, 0, 1), polyline(&fine, 0, 1));
+        assert!(coarse.len() > 2 && fine.len() > coarse.len());
+        for p in &fine {
+            assert!(
+                (weighted_distance(p, &sites[0]) - weighted_distance(p, &sites[1])).abs() < 1e-9
+            );
+            assert!(distance_to_polyline(p, &coarse) < 0.55);
+        }
+
+        // the cell of the lighter site is the convex side
+        let cells = diagram(1e-4).cell_polygons(&bounding_box);
+        assert!(ConvexPolygon::new(cells[0].clone()).is_ok());
+        assert!(ConvexPolygon::new(cells[1].clone()).is_err());
+    }
+
+    #[test]
+    fn outweighed_and_duplicate_sites() {
+        let bounding_box = bounding_box();
+        let sites = [
+            site(500.0, 500.0, 100.0),
+            site(520.0, 500.0, 10.0),
+            site(500.0, 500.0, 100.0),
+            site(800.0, 500.0, 0.0),
+            site(500.0, 500.0, 30.0),
+        ];
+        let diagram = apollonius_diagram(&sites, &bounding_box, OrderedFloat(0.01)).unwrap();
+        assert_eq!(diagram.site_map(), [0, 1, 0, 3, 4]);
+        assert_eq!(diagram.convex_hull(), [0, 3]);
+        for face in [1, 2, 4] {
+            assert!(diagram.boundary(face).is_empty());
+        }
+        let neighbours = |face: usize| {
+            let mut faces = diagram
+                .neighbours(face)
+                .iter()
+                .map(|n| n.face)
+                .collect::<Vec<_>>();
+            faces.dedup();
+            faces
+        };
+        assert_eq!(neighbours(0), [3]);
+        assert_eq!(neighbours(3), [0]);
+        let cells = diagram.cell_polygons(&bounding_box);
+        assert!(cells[1].is_empty());
+        assert!((total_area(&cells) - 1e6).abs() < 1.0);
+    }
+
+    #[test]
+    fn sites_on_the_boundary() {
+        let bounding_box = bounding_box();
+        let cases = [
+            // on an edge
+            (
+                [site(0.0, 500.0, 0.0), site(500.0, 500.0, 0.0)],
+                Some(2.5e5),
+            ),
+            // on a corner, once with a heavier site beside it
+            ([site(0.0, 0.0, 0.0), site(500.0, 500.0, 0.0)], Some(1.25e5)),
+            ([site(1000.0, 0.0, 0.0), site(700.0, 300.0, 50.0)], None),
+        ];
+        for (sites, area) in cases {
+            let diagram = apollonius_diagram(&sites, &bounding_box, OrderedFloat(0.01)).unwrap();
+            assert!(check_diagram_by(&sites, &diagram, 1e-9, weighted_distance));
+            let cells = diagram.cell_polygons(&bounding_box);
+            // the cell reaches the site itself
+            let mut ring = cells[0].clone();
+            ring.push(ring[0]);
+            assert!(distance_to_polyline(&sites[0].point, &ring) < 1e-9);
+
+            if let Some(area) = area {
+                assert!((polygon_area(&cells[0]).into_inner() - area).abs() < 1e-6);
+            }
+            assert!((total_area(&cells) - 1e6).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bounding_box = bounding_box();
+        let tolerance = OrderedFloat(0.01);
+        assert_eq!(
+            apollonius_diagram(
+                &[site(1.0, 5.0, 0.0), site(2.0, 5.0, f64::NAN)],
+                &bounding_box,
+                tolerance
+            )
+            .err(),
+            Some(Error::NonFiniteSite(1))
+        );
+        assert_eq!(
+            apollonius_diagram(&[site(-1.0, 5.0, 0.0)], &bounding_box, tolerance).err(),
+            Some(Error::SiteOutsideBoundingBox(0))
+        );
+        assert_eq!(
+            apollonius_diagram(&[site(1.0, 5.0, 0.0)], &bounding_box, OrderedFloat(0.0)).err(),
+            Some(Error::DegenerateInput)
+        );
+
+        let mut diagram =
+            apollonius_diagram(&[site(1.0, 5.0, 2.0)], &bounding_box, tolerance).unwrap();
+        assert!(diagram.boundary(0).is_empty());
+        assert_eq!(diagram.cell_polygons(&bounding_box)[0].len(), 4);
+        assert!(matches!(
+            PointLocator::new(&diagram).err(),
+            Some(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            diagram.insert_site(point(3.0, 5.0), &bounding_box),
+            Err(Error::Unsupported(_))
+        ));
+    }
+}
@@ -1,12 +1,26 @@
-use core::panic;
+use std::cmp::{min, Ordering, Reverse};
 
-use ordered_float::OrderedFloat;
+use crate::{diagram::Diagram, geometry::*, Error, Event, EventQueue};
 
-use crate::{geometry::*, Event, EventQueue};
+#[cfg(test)]
+thread_local! {
+    /// Steps taken through beachline trees on this thread, for tests to
+    /// check the cost of a sweep.
+    pub static NODE_VISITS: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+}
 
-pub struct Beachline {
+/// Binary tree of arcs (leaves) and the breakpoints between them.
+///
+/// Nodes live in an arena. Slots freed by arcs and breakpoints leaving the
+/// beachline are reused, with their generation bumped so that circle events
+/// queued for the old arc can be told apart from ones for the new node.
+pub struct Beachline<S> {
     root: Option<usize>,
-    nodes: Vec<BeachlineEntry>,
+    nodes: Vec<BeachlineEntry<S>>,
+    free: Vec<usize>,
+    /// The sites by face and their largest weight, when sweeping a power
+    /// diagram, see [`Beachline::with_weights`]
+    weights: Option<(Vec<WeightedSite<S>>, S)>,
 }
 
 // impl Beachline {
@@ -26,170 +40,240 @@ pub struct Beachline {
 //     }
 // }
 
-pub struct BeachlineEntry {
+pub struct BeachlineEntry<S> {
     left_child: Option<usize>,
     right_child: Option<usize>,
     parent: Option<usize>,
-    data: BeachlineData,
-}
-
-impl BeachlineEntry {
-    fn new(
-        left_child: Option<usize>,
-        right_child: Option<usize>,
-        parent: Option<usize>,
-        data: BeachlineData,
-    ) -> Self {
-        Self {
-            left_child,
-            right_child,
-            parent,
-            data,
-        }
-    }
+    /// Height of the subtree rooted here, 1 for an arc
+    height: usize,
+    /// Number of times this slot has been freed
+    generation: u32,
+    data: BeachlineData<S>,
 }
 
-pub enum BeachlineData {
-    BreakPoint(BreakPoint),
-    Arc(Arc),
+pub enum BeachlineData<S> {
+    BreakPoint(BreakPoint<S>),
+    Arc(Arc<S>),
 }
 
-pub struct BreakPoint {
-    pub origin: Point,
-    direction: Point,
-    l: Point,
-    r: Point,
+pub struct BreakPoint<S> {
+    direction: Point<S>,
+    l: Point<S>,
+    r: Point<S>,
+    /// Faces of `l` and `r`
+    faces: (usize, usize),
+    /// Half-edge on the face of `r` whose destination is this breakpoint
+    pub edge: usize,
 }
 
-impl BreakPoint {
-    pub fn new(origin: Point, direction: Point, l: Point, r: Point) -> Self {
+impl<S: Scalar> BreakPoint<S> {
+    /// The breakpoint between `l` on the left and `r` on the right.
+    pub fn new(l: &Arc<S>, r: &Arc<S>, edge: usize) -> Self {
         Self {
-            origin,
-            direction,
-            l,
-            r,
+            direction: normal_vector(l.site.clone() - r.site.clone()),
+            l: l.site.clone(),
+            r: r.site.clone(),
+            faces: (l.face, r.face),
+            edge,
         }
     }
 }
 
-pub struct Arc {
-    pub site: Point,
+pub struct Arc<S> {
+    pub site: Point<S>,
+    /// Index of the site, and so of its face in the diagram
+    pub face: usize,
 }
 
-impl Arc {
-    pub fn new(site: Point) -> Self {
-        Self { site }
+impl<S> Arc<S> {
+    pub fn new(site: Point<S>, face: usize) -> Self {
+        Self { site, face }
     }
 }
 
-impl Beachline {
+impl<S: Scalar> Beachline<S> {
     pub fn new() -> Self {
         Self {
             root: None,
             nodes: vec![],
+            free: vec![],
+            weights: None,
         }
     }
 
-    pub fn add_first_parabola(self: &mut Self, p: Point) {
-        assert!(self.root.is_none());
+    /// A beachline for the power diagram of `sites`, whose arcs are those of
+    /// the sites lifted by `top`, the largest weight, less their own weight.
+    pub fn with_weights(sites: Vec<WeightedSite<S>>, top: S) -> Self {
+        Self {
+            weights: Some((sites, top)),
+            ..Self::new()
+        }
+    }
 
-        let entry = BeachlineEntry::new(None, None, None, BeachlineData::Arc(Arc::new(p)));
-        self.root = Some(self.nodes.len());
-        self.nodes.push(entry);
+    /// Store a new node without any links, reusing a freed slot if there is
+    /// one.
+    fn alloc(&mut self, data: BeachlineData<S>) -> usize {
+        let mut entry = BeachlineEntry {
+            left_child: None,
+            right_child: None,
+            parent: None,
+            height: 1,
+            generation: 0,
+            data,
+        };
+        if let Some(idx) = self.free.pop() {
+            entry.generation = self.nodes[idx].generation;
+            self.nodes[idx] = entry;
+            idx
+        } else {
+            self.nodes.push(entry);
+            self.nodes.len() - 1
+        }
+    }
+
+    /// Give the slot of a node no longer in the tree back for reuse.
+    fn release(&mut self, idx: usize) {
+        self.nodes[idx].generation += 1;
+        self.free.push(idx);
+    }
+
+    /// Current generation of the slot `idx`, which circle events for the
+    /// arc stored there must match.
+    pub fn generation(&self, idx: usize) -> u32 {
+        self.nodes[idx].generation
+    }
+
+    /// Make `left` and `right` the children of `idx`.
+    fn set_children(&mut self, idx: usize, left: usize, right: usize) {
+        self.nodes[idx].left_child = Some(left);
+        self.nodes[idx].right_child = Some(right);
+        self.nodes[left].parent = Some(idx);
+        self.nodes[right].parent = Some(idx);
+        self.update_height(idx);
     }
 
-    pub fn arc_under_point(self: &Self, p: &Point, yl: OrderedFloat<f64>) -> Option<(&Arc, usize)> {
-        self.root.map(|mut curr_idx| loop {
+    /// Start the beachline with `arc`, returning its index.
+    pub fn add_first_parabola(&mut self, arc: Arc<S>) -> Result<usize, Error> {
+        if self.root.is_some() {
+            return Err(Error::InvariantViolation("first parabola added twice"));
+        }
+
+        let idx = self.alloc(BeachlineData::Arc(arc));
+        self.root = Some(idx);
+        Ok(idx)
+    }
+
+    /// The arc above `p`, with the sweep line passing through `p`.
+    pub fn arc_under_point(&self, p: &Point<S>) -> Result<Option<(&Arc<S>, usize)>, Error> {
+        let Some(mut curr_idx) = self.root else {
+            return Ok(None);
+        };
+        loop {
+            self.visit();
             let node = &self.nodes[curr_idx];
             match &node.data {
                 BeachlineData::BreakPoint(bp) => {
-                    let x = breakpoint_at_x(&bp.l, &bp.r, yl);
-                    if p.x < x {
-                        curr_idx = node.left_child.unwrap();
+                    if self.compare_to_breakpoint(p, bp) == Ordering::Less {
+                        curr_idx = self.left_child(curr_idx)?;
                     } else {
-                        curr_idx = node.right_child.unwrap();
+                        curr_idx = self.right_child(curr_idx)?;
                     }
                 }
-                BeachlineData::Arc(arc) => return (arc, curr_idx),
+                BeachlineData::Arc(arc) => return Ok(Some((arc, curr_idx))),
             }
-        })
+        }
+    }
+
+    fn compare_to_breakpoint(&self, p: &Point<S>, bp: &BreakPoint<S>) -> Ordering {
+        match &self.weights {
+            Some((sites, top)) => {
+                compare_to_power_breakpoint(p, &sites[bp.faces.0], &sites[bp.faces.1], top)
+            }
+            None => compare_to_breakpoint(p, &bp.l, &bp.r),
+        }
+    }
+
+    /// Where the arc of `p` vanishes between those of `l` and `r`, or
+    /// `None` if the three sites are collinear.
+    pub fn vertex(&self, l: &Arc<S>, p: &Arc<S>, r: &Arc<S>) -> Option<Point<S>> {
+        match &self.weights {
+            Some((sites, _)) => power_center(&sites[l.face], &sites[p.face], &sites[r.face]),
+            None => circumcenter(&l.site, &p.site, &r.site),
+        }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn replace_arc(
-        self: &mut Self,
+        &mut self,
         arc_idx: usize,
-        a: Arc,
-        xl: BreakPoint,
-        b: Arc,
-        xr: BreakPoint,
-        c: Arc,
-        eq: &mut EventQueue,
-        yl: OrderedFloat<f64>,
-    ) {
+        a: Arc<S>,
+        xl: BreakPoint<S>,
+        b: Arc<S>,
+        xr: BreakPoint<S>,
+        c: Arc<S>,
+        eq: &mut EventQueue<S>,
+        yl: S,
+    ) -> Result<(), Error> {
         let parent = self.nodes[arc_idx].parent;
 
-        let a_idx = self.nodes.len();
-        let xl_idx = a_idx + 1;
-        let b_idx = a_idx + 2;
-        let xr_idx = a_idx + 3;
-        let c_idx = a_idx + 4;
-
-        let a_entry = BeachlineEntry::new(None, None, Some(xl_idx), BeachlineData::Arc(a));
-        let xl_entry = BeachlineEntry::new(
-            Some(a_idx),
-            Some(xr_idx),
-            parent,
-            BeachlineData::BreakPoint(xl),
-        );
-        let b_entry = BeachlineEntry::new(None, None, Some(xr_idx), BeachlineData::Arc(b));
-        let xr_entry = BeachlineEntry::new(
-            Some(b_idx),
-            Some(c_idx),
-            Some(xl_idx),
-            BeachlineData::BreakPoint(xr),
-        );
-        let c_entry = BeachlineEntry::new(None, None, Some(xr_idx), BeachlineData::Arc(c));
-
-        self.nodes.push(a_entry);
-        self.nodes.push(xl_entry);
-        self.nodes.push(b_entry);
-        self.nodes.push(xr_entry);
-        self.nodes.push(c_entry);
-
-        if let Some(parent_idx) = parent {
-            let parent_node = &mut self.nodes[parent_idx];
-            if parent_node.left_child.unwrap() == arc_idx {
-                parent_node.left_child = Some(xl_idx);
-            } else {
-                parent_node.right_child = Some(xl_idx);
-            }
-        } else {
-            self.root = Some(xl_idx);
-        }
+        let a_idx = self.alloc(BeachlineData::Arc(a));
+        let xl_idx = self.alloc(BeachlineData::BreakPoint(xl));
+        let b_idx = self.alloc(BeachlineData::Arc(b));
+        let xr_idx = self.alloc(BeachlineData::BreakPoint(xr));
+        let c_idx = self.alloc(BeachlineData::Arc(c));
+        self.set_children(xr_idx, b_idx, c_idx);
+        self.set_children(xl_idx, a_idx, xr_idx);
+
+        self.replace_child(parent, arc_idx, xl_idx)?;
+        self.release(arc_idx);
+        self.rebalance(parent)?;
 
-        self.check_circle_event(a_idx, eq, yl);
-        self.check_circle_event(c_idx, eq, yl);
+        self.check_circle_event(a_idx, eq, yl.clone())?;
+        self.check_circle_event(c_idx, eq, yl)
+    }
+
+    /// Split the leaf `arc_idx` into itself and `b` to its right, separated
+    /// by `x`. Returns the index of `b`.
+    pub fn add_arc_right(
+        &mut self,
+        arc_idx: usize,
+        x: BreakPoint<S>,
+        b: Arc<S>,
+        eq: &mut EventQueue<S>,
+        yl: S,
+    ) -> Result<usize, Error> {
+        let parent = self.nodes[arc_idx].parent;
+
+        let x_idx = self.alloc(BeachlineData::BreakPoint(x));
+        let b_idx = self.alloc(BeachlineData::Arc(b));
+
+        self.replace_child(parent, arc_idx, x_idx)?;
+        self.set_children(x_idx, arc_idx, b_idx);
+        self.rebalance(parent)?;
+
+        self.check_circle_event(arc_idx, eq, yl)?;
+        Ok(b_idx)
     }
 
     pub fn replace_breakpoint(
-        self: &mut Self,
+        &mut self,
         xl_idx: usize,
         p_idx: usize,
         xr_idx: usize,
-        x: BreakPoint,
-        eq: &mut EventQueue,
-        yl: OrderedFloat<f64>,
-    ) {
+        x: BreakPoint<S>,
+        eq: &mut EventQueue<S>,
+        yl: S,
+    ) -> Result<(), Error> {
         let (_, l_arc_idx) = self
-            .left_arc(p_idx)
-            .expect("replace_breakpoint: left arc not found");
+            .left_arc(p_idx)?
+            .ok_or(Error::InvariantViolation("left arc not found"))?;
         let (_, r_arc_idx) = self
-            .right_arc(p_idx)
-            .expect("replace_breakpoint: right arc not found");
+            .right_arc(p_idx)?
+            .ok_or(Error::InvariantViolation("right arc not found"))?;
 
         let parent_idx = self.nodes[p_idx]
             .parent
-            .expect("repalce_breakpoint: parent not found");
+            .ok_or(Error::InvariantViolation("parent not found"))?;
 
         let other_node = if parent_idx == xr_idx {
             &mut self.nodes[xl_idx]
@@ -199,90 +283,218 @@ impl Beachline {
         other_node.data = BeachlineData::BreakPoint(x);
 
         let parent_node = &self.nodes[parent_idx];
-        let sibling = if parent_node.left_child.unwrap() == p_idx {
+        let sibling_idx = if parent_node.left_child == Some(p_idx) {
             parent_node.right_child
-        } else if parent_node.right_child.unwrap() == p_idx {
+        } else if parent_node.right_child == Some(p_idx) {
             parent_node.left_child
         } else {
-            panic!("parent not claiming child")
-        };
+            None
+        }
+        .ok_or(Error::InvariantViolation("parent not claiming child"))?;
+
+        let granny_idx = parent_node
+            .parent
+            .ok_or(Error::InvariantViolation("granny lost"))?;
+        self.replace_child(Some(granny_idx), parent_idx, sibling_idx)?;
+        self.release(parent_idx);
+        self.release(p_idx);
+        self.rebalance(Some(granny_idx))?;
+
+        self.check_circle_event(l_arc_idx, eq, yl.clone())?;
+        self.check_circle_event(r_arc_idx, eq, yl)
+    }
 
-        let granny_idx = parent_node.parent.expect("granny lost");
-        let granny_node = &mut self.nodes[granny_idx];
-        if granny_node.left_child.unwrap() == parent_idx {
-            granny_node.left_child = sibling;
-        } else if granny_node.right_child.unwrap() == parent_idx {
-            granny_node.right_child = sibling;
+    /// Put `new` in the place of `old` under `parent`, or at the root.
+    fn replace_child(
+        &mut self,
+        parent: Option<usize>,
+        old: usize,
+        new: usize,
+    ) -> Result<(), Error> {
+        self.nodes[new].parent = parent;
+        let Some(parent_idx) = parent else {
+            self.root = Some(new);
+            return Ok(());
+        };
+        let parent_node = &mut self.nodes[parent_idx];
+        if parent_node.left_child == Some(old) {
+            parent_node.left_child = Some(new);
+        } else if parent_node.right_child == Some(old) {
+            parent_node.right_child = Some(new);
         } else {
-            panic!("granny not claiming parent")
+            return Err(Error::InvariantViolation("parent not claiming child"));
         }
+        Ok(())
+    }
+
+    fn height(&self, idx: Option<usize>) -> usize {
+        idx.map_or(0, |idx| self.nodes[idx].height)
+    }
 
-        self.check_circle_event(l_arc_idx, eq, yl);
-        self.check_circle_event(r_arc_idx, eq, yl);
+    fn update_height(&mut self, idx: usize) {
+        let node = &self.nodes[idx];
+        let height = 1 + self
+            .height(node.left_child)
+            .max(self.height(node.right_child));
+        self.nodes[idx].height = height;
     }
 
-    fn check_circle_event(self: &Self, arc_idx: usize, eq: &mut EventQueue, yl: OrderedFloat<f64>) {
-        let p = self.arc(arc_idx);
-        let l_opt = self.left_arc(arc_idx);
-        let r_opt = self.right_arc(arc_idx);
-        let xl_opt = self.left_edge(arc_idx);
-        let xr_opt = self.right_edge(arc_idx);
+    /// How much taller the left subtree of `idx` is than its right one.
+    fn balance(&self, idx: usize) -> isize {
+        let node = &self.nodes[idx];
+        self.height(node.left_child) as isize - self.height(node.right_child) as isize
+    }
 
-        match (l_opt, r_opt) {
-            (Some((l, _)), Some((r, _))) => {
-                if l.site == r.site {
-                    return;
+    /// Restore the AVL invariant on the path from `idx` up to the root.
+    /// Rotations only ever move breakpoints, so arc indices stay valid.
+    fn rebalance(&mut self, mut idx: Option<usize>) -> Result<(), Error> {
+        while let Some(curr_idx) = idx {
+            self.visit();
+            self.update_height(curr_idx);
+            let balance = self.balance(curr_idx);
+            let subtree_idx = if balance > 1 {
+                let left_idx = self.left_child(curr_idx)?;
+                if self.balance(left_idx) < 0 {
+                    self.rotate_left(left_idx)?;
                 }
-                let (xl, _) = xl_opt.unwrap();
-                let (xr, _) = xr_opt.unwrap();
-                if let Some(s) = intersection(&xl.origin, &xl.direction, &xr.origin, &xr.direction)
-                {
-                    let r = distance(&p.site, &s);
-                    let circle_top = s.y - r;
-                    if circle_top > yl {
-                        return;
-                    }
-                    eq.push(Event::Circle(arc_idx), circle_top);
-                } else {
-                    return;
+                self.rotate_right(curr_idx)?
+            } else if balance < -1 {
+                let right_idx = self.right_child(curr_idx)?;
+                if self.balance(right_idx) > 0 {
+                    self.rotate_right(right_idx)?;
                 }
+                self.rotate_left(curr_idx)?
+            } else {
+                curr_idx
+            };
+            idx = self.nodes[subtree_idx].parent;
+        }
+        Ok(())
+    }
+
+    /// Lift the right child of `idx` into its place, returning it.
+    fn rotate_left(&mut self, idx: usize) -> Result<usize, Error> {
+        let pivot_idx = self.right_child(idx)?;
+        let inner_idx = self.left_child(pivot_idx)?;
+
+        self.replace_child(self.nodes[idx].parent, idx, pivot_idx)?;
+        self.nodes[idx].right_child = Some(inner_idx);
+        self.nodes[inner_idx].parent = Some(idx);
+        self.nodes[pivot_idx].left_child = Some(idx);
+        self.nodes[idx].parent = Some(pivot_idx);
+
+        self.update_height(idx);
+        self.update_height(pivot_idx);
+        Ok(pivot_idx)
+    }
+
+    /// Lift the left child of `idx` into its place, returning it.
+    fn rotate_right(&mut self, idx: usize) -> Result<usize, Error> {
+        let pivot_idx = self.left_child(idx)?;
+        let inner_idx = self.right_child(pivot_idx)?;
+
+        self.replace_child(self.nodes[idx].parent, idx, pivot_idx)?;
+        self.nodes[idx].left_child = Some(inner_idx);
+        self.nodes[inner_idx].parent = Some(idx);
+        self.nodes[pivot_idx].right_child = Some(idx);
+        self.nodes[idx].parent = Some(pivot_idx);
+
+        self.update_height(idx);
+        self.update_height(pivot_idx);
+        Ok(pivot_idx)
+    }
+
+    /// Count a step through the tree, see [`NODE_VISITS`].
+    fn visit(&self) {
+        #[cfg(test)]
+        NODE_VISITS.with(|visits| visits.set(visits.get() + 1));
+    }
+
+    fn check_circle_event(
+        &self,
+        arc_idx: usize,
+        eq: &mut EventQueue<S>,
+        yl: S,
+    ) -> Result<(), Error> {
+        let p = self.arc(arc_idx)?;
+        let l_opt = self.left_arc(arc_idx)?;
+        let r_opt = self.right_arc(arc_idx)?;
+
+        if let (Some((l, _)), Some((r, _))) = (l_opt, r_opt) {
+            if l.site == r.site {
+                return Ok(());
+            }
+            // the breakpoints on either side of p only meet if l, p and r
+            // turn clockwise
+            if orient2d(&l.site, &p.site, &r.site) != Ordering::Less {
+                return Ok(());
             }
-            _ => return,
+            // a center too far out to represent is met past the end of the
+            // sweep, so the edges it would close stay open
+            let Some(s) = self.vertex(l, p, r) else {
+                return Ok(());
+            };
+            if !s.x.is_finite() || !s.y.is_finite() {
+                return Ok(());
+            }
+            let bottom = match &self.weights {
+                Some((sites, top)) => power_circle_bottom(&s, &sites[p.face], top),
+                None => circle_bottom(&s, &p.site),
+            };
+            // a circle bottom a hair above the sweep line is rounding error
+            // on an event that is due right now
+            let circle_top = min(bottom, yl);
+            eq.push(
+                Event::Circle(arc_idx, self.generation(arc_idx)),
+                (circle_top, false, Reverse(s.x)),
+            );
         }
+        Ok(())
+    }
+
+    fn left_child(&self, idx: usize) -> Result<usize, Error> {
+        self.nodes[idx]
+            .left_child
+            .ok_or(Error::InvariantViolation("breakpoint without left child"))
     }
 
-    fn minimum(self: &Self, mut curr_idx: usize) -> usize {
+    fn right_child(&self, idx: usize) -> Result<usize, Error> {
+        self.nodes[idx]
+            .right_child
+            .ok_or(Error::InvariantViolation("breakpoint without right child"))
+    }
+
+    fn minimum(&self, mut curr_idx: usize) -> Result<usize, Error> {
         loop {
-            let node = &self.nodes[curr_idx];
-            match &node.data {
+            self.visit();
+            match &self.nodes[curr_idx].data {
                 BeachlineData::BreakPoint(..) => {
-                    curr_idx = node.left_child.unwrap();
+                    curr_idx = self.left_child(curr_idx)?;
                 }
-                BeachlineData::Arc(..) => return curr_idx,
+                BeachlineData::Arc(..) => return Ok(curr_idx),
             }
         }
     }
 
-    fn maximum(self: &Self, mut curr_idx: usize) -> usize {
+    fn maximum(&self, mut curr_idx: usize) -> Result<usize, Error> {
         loop {
-            let node = &self.nodes[curr_idx];
-            match &node.data {
+            self.visit();
+            match &self.nodes[curr_idx].data {
                 BeachlineData::BreakPoint(..) => {
-                    curr_idx = node.right_child.unwrap();
+                    curr_idx = self.right_child(curr_idx)?;
                 }
-                BeachlineData::Arc(..) => return curr_idx,
+                BeachlineData::Arc(..) => return Ok(curr_idx),
             }
         }
     }
 
-    fn predecessor(self: &Self, mut curr_idx: usize) -> Option<usize> {
-        while self.nodes[curr_idx].parent.is_some()
-            && self.nodes[self.nodes[curr_idx].parent.unwrap()]
-                .left_child
-                .unwrap()
-                == curr_idx
-        {
-            curr_idx = self.nodes[curr_idx].parent.unwrap();
+    fn predecessor(&self, mut curr_idx: usize) -> Option<usize> {
+        while let Some(parent_idx) = self.nodes[curr_idx].parent {
+            self.visit();
+            if self.nodes[parent_idx].left_child != Some(curr_idx) {
+                break;
+            }
+            curr_idx = parent_idx;
         }
         self.nodes[curr_idx].parent
         // let mut curr_node = &self.nodes[curr_idx];
@@ -304,14 +516,13 @@ impl Beachline {
         // }
     }
 
-    fn successor(self: &Self, mut curr_idx: usize) -> Option<usize> {
-        while self.nodes[curr_idx].parent.is_some()
-            && self.nodes[self.nodes[curr_idx].parent.unwrap()]
-                .right_child
-                .unwrap()
-                == curr_idx
-        {
-            curr_idx = self.nodes[curr_idx].parent.unwrap();
+    fn successor(&self, mut curr_idx: usize) -> Option<usize> {
+        while let Some(parent_idx) = self.nodes[curr_idx].parent {
+            self.visit();
+            if self.nodes[parent_idx].right_child != Some(curr_idx) {
+                break;
+            }
+            curr_idx = parent_idx;
         }
         self.nodes[curr_idx].parent
         // let mut curr_node = &self.nodes[curr_idx];
@@ -331,39 +542,39 @@ impl Beachline {
         // }
     }
 
-    pub fn left_arc(self: &Self, arc_idx: usize) -> Option<(&Arc, usize)> {
+    pub fn left_arc(&self, arc_idx: usize) -> Result<Option<(&Arc<S>, usize)>, Error> {
         self.predecessor(arc_idx)
-            .and_then(|succ| self.nodes[succ].left_child)
-            .map(|left_idx| {
-                let arc_idx = self.maximum(left_idx);
-                (self.arc(arc_idx), arc_idx)
+            .map(|pred| {
+                let arc_idx = self.maximum(self.left_child(pred)?)?;
+                Ok((self.arc(arc_idx)?, arc_idx))
             })
+            .transpose()
     }
 
-    pub fn right_arc(self: &Self, arc_idx: usize) -> Option<(&Arc, usize)> {
+    pub fn right_arc(&self, arc_idx: usize) -> Result<Option<(&Arc<S>, usize)>, Error> {
         self.successor(arc_idx)
-            .and_then(|succ| self.nodes[succ].right_child)
-            .map(|right_idx| {
-                let arc_idx = self.minimum(right_idx);
-                (self.arc(arc_idx), arc_idx)
+            .map(|succ| {
+                let arc_idx = self.minimum(self.right_child(succ)?)?;
+                Ok((self.arc(arc_idx)?, arc_idx))
             })
+            .transpose()
     }
 
-    pub fn arc(self: &Self, idx: usize) -> &Arc {
+    pub fn arc(&self, idx: usize) -> Result<&Arc<S>, Error> {
         match &self.nodes[idx].data {
-            BeachlineData::BreakPoint(..) => panic!("not an arc"),
-            BeachlineData::Arc(arc) => arc,
+            BeachlineData::BreakPoint(..) => Err(Error::InvariantViolation("not an arc")),
+            BeachlineData::Arc(arc) => Ok(arc),
         }
     }
 
-    // pub fn breakpoint(self: &Self, idx: usize) -> &BreakPoint {
+    // pub fn breakpoint(&self, idx: usize) -> &BreakPoint {
     //     match &self.nodes[idx].data {
     //         BeachlineData::BreakPoint(bp) => bp,
     //         BeachlineData::Arc(..) => panic!("not a breakpoint"),
     //     }
     // }
 
-    pub fn left_edge(self: &Self, arc_idx: usize) -> Option<(&BreakPoint, usize)> {
+    pub fn left_edge(&self, arc_idx: usize) -> Option<(&BreakPoint<S>, usize)> {
         self.predecessor(arc_idx)
             .and_then(|pred| match &self.nodes[pred].data {
                 BeachlineData::BreakPoint(bp) => Some((bp, pred)),
@@ -371,7 +582,7 @@ impl Beachline {
             })
     }
 
-    pub fn right_edge(self: &Self, arc_idx: usize) -> Option<(&BreakPoint, usize)> {
+    pub fn right_edge(&self, arc_idx: usize) -> Option<(&BreakPoint<S>, usize)> {
         self.successor(arc_idx)
             .and_then(|succ| match &self.nodes[succ].data {
                 BeachlineData::BreakPoint(bp) => Some((bp, succ)),
@@ -379,30 +590,132 @@ impl Beachline {
             })
     }
 
-    pub fn extend_edges_to_bounding_box(
-        self: &Self,
-        bounding_box: &BoundingBox,
-        edges: &mut Vec<Segment>,
-    ) {
-        self.extend_edges_to_bounding_box_aux(bounding_box, edges, self.root);
+    /// The faces of the arcs from left to right.
+    pub fn arc_faces(&self) -> Vec<usize> {
+        let mut res = vec![];
+        let mut stack = self.root.into_iter().collect::<Vec<_>>();
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            match &node.data {
+                BeachlineData::Arc(arc) => res.push(arc.face),
+                BeachlineData::BreakPoint(..) => {
+                    stack.extend(node.right_child);
+                    stack.extend(node.left_child);
+                }
+            }
+        }
+        res
+    }
+
+    pub fn extend_edges_to_boundary<R: ClipRegion<S>>(&self, region: &R, diagram: &mut Diagram<S>) {
+        let mut breakpoints = vec![];
+        self.collect_breakpoints(self.root, &mut breakpoints);
+
+        // Every ray starts from the vertex at its other end, or from where
+        // it crosses the line through the two sites when it has none, so
+        // that where it ends depends on the diagram alone and not on the
+        // course of the sweep
+        let ends = breakpoints
+            .iter()
+            .map(|bp| {
+                let origin = match (diagram.half_edge(bp.edge).origin, &self.weights) {
+                    (Some(v), _) => diagram.vertex(v).point.clone(),
+                    (None, Some((sites, _))) => {
+                        bisector_point(&sites[bp.faces.0], &sites[bp.faces.1])
+                    }
+                    (None, None) => (bp.l.clone() + bp.r.clone()) * S::from_f64(0.5),
+                };
+                region.ray_end(&origin, &bp.direction)
+            })
+            .collect::<Vec<_>>();
+        for (bp, end) in breakpoints.into_iter().zip(ends) {
+            diagram.add_vertex(end, diagram.twin(bp.edge));
+        }
     }
 
-    fn extend_edges_to_bounding_box_aux(
-        self: &Self,
-        bounding_box: &BoundingBox,
-        edges: &mut Vec<Segment>,
-        root: Option<usize>,
-    ) {
+    fn collect_breakpoints<'a>(&'a self, root: Option<usize>, res: &mut Vec<&'a BreakPoint<S>>) {
         if let Some(root_idx) = root {
             let node = &self.nodes[root_idx];
-            match &node.data {
-                BeachlineData::BreakPoint(bp) => {
-                    edges.push(bounded_segment(&bp.origin, &bp.direction, bounding_box))
-                }
-                _ => {}
+            if let BeachlineData::BreakPoint(bp) = &node.data {
+                res.push(bp);
             }
-            self.extend_edges_to_bounding_box_aux(bounding_box, edges, node.left_child);
-            self.extend_edges_to_bounding_box_aux(bounding_box, edges, node.right_child);
+            self.collect_breakpoints(node.left_child, res);
+            self.collect_breakpoints(node.right_child, res);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ordered_float::OrderedFloat;
+
+    use crate::test_utils::point;
+
+    use super::*;
+
+    fn breakpoint(l: &Point, r: &Point) -> BreakPoint<OrderedFloat<f64>> {
+        BreakPoint::new(&Arc::new(*l, 0), &Arc::new(*r, 0), 0)
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let (a, b) = (point(0.0, 10.0), point(1.0, 8.0));
+        let mut beachline = Beachline::new();
+        let mut eq = EventQueue::new();
+
+        beachline.add_first_parabola(Arc::new(a, 0)).unwrap();
+        beachline
+            .replace_arc(
+                0,
+                Arc::new(a, 0),
+                breakpoint(&a, &b),
+                Arc::new(b, 1),
+                breakpoint(&b, &a),
+                Arc::new(a, 0),
+                &mut eq,
+                OrderedFloat(8.0),
+            )
+            .unwrap();
+        assert_eq!(beachline.nodes.len(), 6);
+        assert_eq!(beachline.free, vec![0]);
+
+        // take b out again, freeing it and one of the breakpoints around it
+        let (_, b_idx) = beachline
+            .arc_under_point(&point(1.0, 7.0))
+            .unwrap()
+            .unwrap();
+        let (_, xl_idx) = beachline.left_edge(b_idx).unwrap();
+        let (_, xr_idx) = beachline.right_edge(b_idx).unwrap();
+        let generation = beachline.generation(b_idx);
+        beachline
+            .replace_breakpoint(
+                xl_idx,
+                b_idx,
+                xr_idx,
+                breakpoint(&a, &a),
+                &mut eq,
+                OrderedFloat(7.0),
+            )
+            .unwrap();
+        assert_eq!(beachline.free.len(), 3);
+        assert_ne!(beachline.generation(b_idx), generation);
+
+        // a new site fills the freed slots before growing the arena
+        let c = point(-1.0, 6.0);
+        let (_, arc_idx) = beachline.arc_under_point(&c).unwrap().unwrap();
+        beachline
+            .replace_arc(
+                arc_idx,
+                Arc::new(a, 0),
+                breakpoint(&a, &c),
+                Arc::new(c, 2),
+                breakpoint(&c, &a),
+                Arc::new(a, 0),
+                &mut eq,
+                OrderedFloat(6.0),
+            )
+            .unwrap();
+        assert_eq!(beachline.nodes.len(), 8);
+        assert_eq!(beachline.free.len(), 1);
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashSet;
+
+/// Delaunay triangulation of a set of sites, indexed the same way as the
+/// sites passed to [`crate::delaunay_triangulation`].
+pub struct Triangulation {
+    triangles: Vec<[usize; 3]>,
+    hull_edges: Vec<[usize; 2]>,
+}
+
+impl Triangulation {
+    /// Build the triangulation from its `triangles`, given
+    /// counter-clockwise, and every pair of `neighbours` sharing a Voronoi
+    /// edge. Pairs not on any triangle only occur when all sites lie on a
+    /// line.
+    pub(crate) fn new(
+        triangles: Vec<[usize; 3]>,
+        neighbours: impl IntoIterator<Item = [usize; 2]>,
+    ) -> Self {
+        let edges = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| [[a, b], [b, c], [c, a]])
+            .collect::<HashSet<_>>();
+        // an edge on the hull has a triangle on one side only
+        let mut hull_edges = edges
+            .iter()
+            .filter(|&&[a, b]| !edges.contains(&[b, a]))
+            .copied()
+            .collect::<Vec<_>>();
+        for [a, b] in neighbours {
+            if !edges.contains(&[a, b]) && !edges.contains(&[b, a]) {
+                hull_edges.push([a, b]);
+                hull_edges.push([b, a]);
+            }
+        }
+        hull_edges.sort();
+
+        Self {
+            triangles,
+            hull_edges,
+        }
+    }
+
+    /// The triangles as the indices of their corners, counter-clockwise.
+    /// Four or more cocircular sites are split into triangles arbitrarily.
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    /// The edges of the convex hull, each directed so that the hull lies to
+    /// its left. When all sites lie on a line the hull is flat, and every
+    /// edge on it shows up in both directions.
+    pub fn hull_edges(&self) -> &[[usize; 2]] {
+        &self.hull_edges
+    }
+}
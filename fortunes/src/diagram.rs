@@ -0,0 +1,759 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use ordered_float::OrderedFloat;
+
+use crate::apollonius::AdditiveBisector;
+use crate::delaunay::Triangulation;
+use crate::geometry::{
+    circumcenter, clip_cell, clip_to_power_bisector, incircle, normal_vector, orient2d, Bisector,
+    ClipRegion, Metric, Point, PolygonWithHoles, Scalar, Segment, WeightedSite,
+};
+use crate::Error;
+
+mod incremental;
+
+/// A Voronoi vertex.
+pub struct Vertex<S = OrderedFloat<f64>> {
+    pub point: Point<S>,
+    /// Some half-edge whose origin is this vertex
+    pub incident_edge: usize,
+}
+
+/// One side of a Voronoi edge. The face it belongs to lies on its left.
+pub struct HalfEdge {
+    /// `None` while the edge has not been closed off by a vertex
+    pub origin: Option<usize>,
+    pub twin: usize,
+    pub next: Option<usize>,
+    pub prev: Option<usize>,
+    pub face: usize,
+}
+
+/// The Voronoi cell of a single site.
+pub struct Face<S = OrderedFloat<f64>> {
+    pub site: Point<S>,
+    /// Weight of the site in a power diagram, see [`crate::power_diagram`],
+    /// or in an additively weighted one, see [`crate::apollonius_diagram`],
+    /// and zero otherwise
+    pub weight: S,
+    /// Some half-edge on the boundary of this face
+    pub outer_component: Option<usize>,
+}
+
+/// A site sharing an edge with another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Neighbour {
+    /// Face of the neighbouring site
+    pub face: usize,
+    /// The half-edge between the two, on the face whose neighbours were
+    /// asked for
+    pub edge: usize,
+}
+
+/// The cells of a diagram cut down to a [`PolygonWithHoles`], see
+/// [`Diagram::cells_in_polygon`].
+pub struct ClippedCells<S = OrderedFloat<f64>> {
+    /// For every face, the parts of its cell inside the polygon
+    pub parts: Vec<Vec<PolygonWithHoles<S>>>,
+    /// Faces with a cell of their own that lies entirely outside the
+    /// polygon, or in one of its holes
+    pub empty: Vec<usize>,
+}
+
+/// Doubly-connected edge list describing a Voronoi diagram.
+///
+/// Faces are indexed the same way as the sites passed to
+/// [`crate::fortunes_algorithm`].
+pub struct Diagram<S = OrderedFloat<f64>> {
+    vertices: Vec<Vertex<S>>,
+    half_edges: Vec<HalfEdge>,
+    faces: Vec<Face<S>>,
+    site_map: Vec<usize>,
+    convex_hull: Vec<usize>,
+    /// Whether this is a power diagram
+    weighted: bool,
+    metric: Metric,
+    /// Whether points belong to the site farthest from them
+    farthest: bool,
+    /// For an additively weighted diagram, how far its edges may stray from
+    /// the hyperbolas they follow
+    additive: Option<S>,
+    /// For faces whose boundary falls apart into several chains, like the
+    /// strips of sites on a line, the first half-edge of every chain besides
+    /// that of the outer component
+    chains: HashMap<usize, Vec<usize>>,
+    /// How many faces are merged into an earlier one, see [`Self::site_map`]
+    merged: usize,
+    /// Some face near the last site inserted, moved or removed
+    recent: usize,
+}
+
+impl<S: Scalar> Diagram<S> {
+    pub(crate) fn new(sites: &[Point<S>], site_map: Vec<usize>) -> Self {
+        let merged = site_map
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &face)| face != idx)
+            .count();
+        Self {
+            vertices: vec![],
+            half_edges: vec![],
+            faces: sites
+                .iter()
+                .map(|site| Face {
+                    site: site.clone(),
+                    weight: S::zero(),
+                    outer_component: None,
+                })
+                .collect(),
+            site_map,
+            convex_hull: vec![],
+            weighted: false,
+            metric: Metric::Euclidean,
+            farthest: false,
+            additive: None,
+            chains: HashMap::new(),
+            merged,
+            recent: 0,
+        }
+    }
+
+    /// Turn this into a power diagram with the weights of `sites`.
+    pub(crate) fn set_weights(&mut self, sites: &[WeightedSite<S>]) {
+        for (face, site) in self.faces.iter_mut().zip(sites) {
+            face.weight = site.weight.clone();
+        }
+        self.weighted = true;
+    }
+
+    /// Turn this into an additively weighted diagram with the weights of
+    /// `sites`, whose edges follow their hyperbolas to within `tolerance`.
+    pub(crate) fn set_additive_weights(&mut self, sites: &[WeightedSite<S>], tolerance: S) {
+        for (face, site) in self.faces.iter_mut().zip(sites) {
+            face.weight = site.weight.clone();
+        }
+        self.additive = Some(tolerance);
+    }
+
+    pub(crate) fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    pub(crate) fn set_farthest(&mut self) {
+        self.farthest = true;
+    }
+
+    pub(crate) fn set_convex_hull(&mut self, convex_hull: Vec<usize>) {
+        self.convex_hull = convex_hull;
+    }
+
+    /// Add a vertex and make it the origin of `incident_edge`.
+    pub(crate) fn add_vertex(&mut self, point: Point<S>, incident_edge: usize) -> usize {
+        let idx = self.vertices.len();
+        self.vertices.push(Vertex {
+            point,
+            incident_edge,
+        });
+        self.half_edges[incident_edge].origin = Some(idx);
+        idx
+    }
+
+    /// Create a new edge between two faces, returning the half-edge on
+    /// `left_face` and the half-edge on `right_face`.
+    pub(crate) fn add_edge(&mut self, left_face: usize, right_face: usize) -> (usize, usize) {
+        let l_idx = self.half_edges.len();
+        let r_idx = l_idx + 1;
+
+        self.half_edges.push(HalfEdge {
+            origin: None,
+            twin: r_idx,
+            next: None,
+            prev: None,
+            face: left_face,
+        });
+        self.half_edges.push(HalfEdge {
+            origin: None,
+            twin: l_idx,
+            next: None,
+            prev: None,
+            face: right_face,
+        });
+
+        for (face, edge) in [(left_face, l_idx), (right_face, r_idx)] {
+            self.faces[face].outer_component.get_or_insert(edge);
+        }
+
+        (l_idx, r_idx)
+    }
+
+    pub(crate) fn set_origin(&mut self, edge: usize, vertex: usize) {
+        self.half_edges[edge].origin = Some(vertex);
+    }
+
+    pub(crate) fn link(&mut self, prev: usize, next: usize) {
+        self.half_edges[prev].next = Some(next);
+        self.half_edges[next].prev = Some(prev);
+    }
+
+    /// Remove the zero-length `edges`, merging the endpoints of each into a
+    /// single vertex, and drop the vertices and half-edges left unused.
+    pub(crate) fn collapse_edges(&mut self, edges: &[usize]) {
+        fn find(rep: &mut [usize], mut v: usize) -> usize {
+            while rep[v] != v {
+                rep[v] = rep[rep[v]];
+                v = rep[v];
+            }
+            v
+        }
+
+        let mut rep = (0..self.vertices.len()).collect::<Vec<_>>();
+        let mut removed = vec![false; self.half_edges.len()];
+
+        for &edge in edges {
+            let twin = self.twin(edge);
+            if removed[edge] {
+                continue;
+            }
+            if let (Some(u), Some(w)) = (self.half_edges[edge].origin, self.half_edges[twin].origin)
+            {
+                let u = find(&mut rep, u);
+                let w = find(&mut rep, w);
+                rep[w] = u;
+            }
+            for half_edge in [edge, twin] {
+                self.unlink(half_edge);
+                removed[half_edge] = true;
+            }
+        }
+
+        // renumber what is left
+        let mut edge_map = vec![usize::MAX; self.half_edges.len()];
+        let mut kept = 0;
+        for (idx, &gone) in removed.iter().enumerate() {
+            if !gone {
+                edge_map[idx] = kept;
+                kept += 1;
+            }
+        }
+        let mut vertex_map = vec![usize::MAX; self.vertices.len()];
+        let mut vertices = vec![];
+        for (v, vertex) in self.vertices.iter().enumerate() {
+            if find(&mut rep, v) == v {
+                vertex_map[v] = vertices.len();
+                vertices.push(Vertex {
+                    point: vertex.point.clone(),
+                    incident_edge: usize::MAX,
+                });
+            }
+        }
+
+        let half_edges = std::mem::take(&mut self.half_edges);
+        for (idx, mut half_edge) in half_edges.into_iter().enumerate() {
+            if removed[idx] {
+                continue;
+            }
+            half_edge.origin = half_edge.origin.map(|v| vertex_map[find(&mut rep, v)]);
+            half_edge.twin = edge_map[half_edge.twin];
+            half_edge.next = half_edge.next.map(|e| edge_map[e]);
+            half_edge.prev = half_edge.prev.map(|e| edge_map[e]);
+            if let Some(v) = half_edge.origin {
+                if vertices[v].incident_edge == usize::MAX {
+                    vertices[v].incident_edge = self.half_edges.len();
+                }
+            }
+            self.half_edges.push(half_edge);
+        }
+        self.vertices = vertices;
+        for face in &mut self.faces {
+            face.outer_component = face.outer_component.map(|e| edge_map[e]);
+        }
+    }
+
+    /// Fill in the dual of the triangulation of the sites made of
+    /// `triangles`, each counter-clockwise, with a vertex for every triangle
+    /// and an edge for every edge between two sites. Rays cross the edges of
+    /// the hull into it for a positive `sign` and out of it for a negative
+    /// one, and end at the boundary of `region`.
+    pub(crate) fn dualize<R: ClipRegion<S>>(
+        &mut self,
+        triangles: &[[usize; 3]],
+        sign: S,
+        region: &R,
+    ) -> Result<(), Error> {
+        let mut apex = HashMap::new();
+        for &[a, b, c] in triangles {
+            apex.insert((a, b), c);
+            apex.insert((b, c), a);
+            apex.insert((c, a), b);
+        }
+
+        // the half-edge on the face of u between u and v, for the edge
+        // from u to v of the triangulation
+        let mut half = HashMap::new();
+        let mut cocircular = vec![];
+        for &[a, b, c] in triangles {
+            for (u, v, w) in [(a, b, c), (b, c, a), (c, a, b)] {
+                if half.contains_key(&(u, v)) {
+                    continue;
+                }
+                let (u_edge, v_edge) = self.add_edge(u, v);
+                half.insert((u, v), u_edge);
+                half.insert((v, u), v_edge);
+                if let Some(&x) = apex.get(&(v, u)) {
+                    let site = |v: usize| &self.faces[v].site;
+                    if incircle(site(u), site(v), site(w), site(x)) == Ordering::Equal {
+                        cocircular.push(u_edge);
+                    }
+                }
+            }
+        }
+
+        // Going counter-clockwise around the cell of a, the edge towards b
+        // of the triangle a, b, c ends at the vertex of the triangle, and
+        // the edge towards c starts there.
+        for &[a, b, c] in triangles {
+            let site = |v: usize| &self.faces[v].site;
+            let point = circumcenter(site(a), site(b), site(c))
+                .ok_or(Error::InvariantViolation("flat triangle"))?;
+            let vertex = self.add_vertex(point, half[&(b, a)]);
+            for (u, v, w) in [(a, b, c), (b, c, a), (c, a, b)] {
+                self.set_origin(half[&(v, u)], vertex);
+                self.link(half[&(u, v)], half[&(u, w)]);
+            }
+        }
+
+        for edge in 0..self.half_edges.len() {
+            if self.half_edges[edge].origin.is_some() {
+                continue;
+            }
+            let other_end = self
+                .destination(edge)
+                .ok_or(Error::InvariantViolation("edge without a triangle"))?;
+            let site = |edge: usize| &self.faces[self.half_edges[edge].face].site;
+            let direction =
+                normal_vector(site(self.twin(edge)).clone() - site(edge).clone()) * sign.clone();
+            let point = region.ray_end(&self.vertices[other_end].point, &direction);
+            self.add_vertex(point, edge);
+        }
+        self.collapse_edges(&cocircular);
+        Ok(())
+    }
+
+    /// Record the chains of every face that [`Self::boundary`] does not
+    /// reach, once all the edges are in place.
+    pub(crate) fn find_chains(&mut self) {
+        let mut seen = vec![false; self.half_edges.len()];
+        for face in &self.faces {
+            for edge in face
+                .outer_component
+                .map(|start| self.chain(start))
+                .unwrap_or_default()
+            {
+                seen[edge] = true;
+            }
+        }
+        self.chains.clear();
+        for edge in 0..self.half_edges.len() {
+            if seen[edge] {
+                continue;
+            }
+            let chain = self.chain(edge);
+            for &other in &chain {
+                seen[other] = true;
+            }
+            self.chains
+                .entry(self.half_edges[edge].face)
+                .or_default()
+                .push(chain[0]);
+        }
+    }
+
+    /// Take `edge` out of the boundary of its face, joining its neighbours.
+    fn unlink(&mut self, edge: usize) {
+        let HalfEdge {
+            prev, next, face, ..
+        } = self.half_edges[edge];
+        if let Some(prev) = prev {
+            self.half_edges[prev].next = next;
+        }
+        if let Some(next) = next {
+            self.half_edges[next].prev = prev;
+        }
+        // a cell left without edges, like that of a power diagram site
+        // whose cell shrinks to a single point, loses its outer component
+        if self.faces[face].outer_component == Some(edge) {
+            self.faces[face].outer_component = next.or(prev).filter(|&other| other != edge);
+        }
+    }
+
+    pub fn vertices(&self) -> &[Vertex<S>] {
+        &self.vertices
+    }
+
+    pub fn half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+
+    pub fn faces(&self) -> &[Face<S>] {
+        &self.faces
+    }
+
+    pub fn vertex(&self, idx: usize) -> &Vertex<S> {
+        &self.vertices[idx]
+    }
+
+    pub fn half_edge(&self, idx: usize) -> &HalfEdge {
+        &self.half_edges[idx]
+    }
+
+    pub fn face(&self, idx: usize) -> &Face<S> {
+        &self.faces[idx]
+    }
+
+    /// For every input site, the index of the face containing it. This is
+    /// the site itself unless it was merged into an earlier duplicate, in
+    /// which case its own face has no edges.
+    pub fn site_map(&self) -> &[usize] {
+        &self.site_map
+    }
+
+    /// Whether this is a power diagram, see [`crate::power_diagram`].
+    pub fn is_weighted(&self) -> bool {
+        self.weighted
+    }
+
+    /// How distances to the sites are measured, see
+    /// [`crate::try_fortunes_algorithm_with_metric`].
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Whether this is a farthest-point diagram, see
+    /// [`crate::farthest_point_diagram`].
+    pub fn is_farthest_point(&self) -> bool {
+        self.farthest
+    }
+
+    /// Whether this is an additively weighted diagram, see
+    /// [`crate::apollonius_diagram`].
+    pub fn is_additively_weighted(&self) -> bool {
+        self.additive.is_some()
+    }
+
+    /// Whether `face` has a cell, which it lacks when its site was merged
+    /// into a duplicate or, in a power or additively weighted diagram, is
+    /// outweighed everywhere, or
+    /// in a farthest-point diagram is no corner of the convex hull. Only a
+    /// lone cell has no edges.
+    fn has_cell(&self, face: usize) -> bool {
+        self.faces[face].outer_component.is_some() || self.convex_hull == [face]
+    }
+
+    /// The faces of the sites on the corners of their convex hull, in the
+    /// order of [`crate::geometry::convex_hull`].
+    pub fn convex_hull(&self) -> &[usize] {
+        &self.convex_hull
+    }
+
+    pub fn twin(&self, edge: usize) -> usize {
+        self.half_edges[edge].twin
+    }
+
+    pub fn destination(&self, edge: usize) -> Option<usize> {
+        self.half_edges[self.twin(edge)].origin
+    }
+
+    /// The half-edges leaving `v`, going around it. A vertex where a ray was
+    /// cut off at the region has just the one.
+    pub(crate) fn outgoing(&self, v: usize) -> Vec<usize> {
+        let start = self.vertices[v].incident_edge;
+        let mut res = vec![start];
+        let mut curr = start;
+        while let Some(prev) = self.half_edges[curr].prev {
+            curr = self.twin(prev);
+            if curr == start {
+                break;
+            }
+            res.push(curr);
+        }
+        res
+    }
+
+    /// Whether `v` is where a ray was cut off at the region, rather than a
+    /// Voronoi vertex.
+    pub(crate) fn is_ray_end(&self, v: usize) -> bool {
+        self.half_edges[self.vertices[v].incident_edge]
+            .prev
+            .is_none()
+    }
+
+    /// Half-edges bounding `face` in counter-clockwise order. For an
+    /// unbounded cell the walk starts at the edge entering from the
+    /// bounding box. A cell split into several chains, like the strip of a
+    /// site between others on a line, only has the first walked; see
+    /// [`Self::neighbours`] for all of them.
+    pub fn boundary(&self, face: usize) -> Vec<usize> {
+        match self.faces[face].outer_component {
+            Some(start) => self.chain(start),
+            None => vec![],
+        }
+    }
+
+    /// The half-edges linked up with `start`, from the first one on.
+    fn chain(&self, start: usize) -> Vec<usize> {
+        let mut first = start;
+        while let Some(prev) = self.half_edges[first].prev {
+            if prev == start {
+                break;
+            }
+            first = prev;
+        }
+
+        let mut res = vec![first];
+        let mut curr = first;
+        while let Some(next) = self.half_edges[curr].next {
+            if next == first {
+                break;
+            }
+            res.push(next);
+            curr = next;
+        }
+        res
+    }
+
+    /// The sites sharing an edge with the site of `face`, counter-clockwise
+    /// around it. Sites whose cells only touch at a vertex do not count. A
+    /// cell split into several chains, like the strip of a site between
+    /// others on a line, lists one chain after the other, in no particular
+    /// order.
+    pub fn neighbours(&self, face: usize) -> Vec<Neighbour> {
+        let others = self.chains.get(&face).into_iter().flatten();
+        self.faces[face]
+            .outer_component
+            .iter()
+            .chain(others)
+            .flat_map(|&start| self.chain(start))
+            .map(|edge| Neighbour {
+                face: self.half_edges[self.twin(edge)].face,
+                edge,
+            })
+            .collect()
+    }
+
+    /// [`Self::neighbours`] of every face, indexed like the input sites.
+    /// Duplicate sites have none.
+    pub fn adjacency(&self) -> Vec<Vec<Neighbour>> {
+        (0..self.faces.len())
+            .map(|face| self.neighbours(face))
+            .collect()
+    }
+
+    /// The cell of every face cut off at `region`, as a closed polygon
+    /// listing its vertices counter-clockwise without repeating the first.
+    /// Cells reaching the boundary of the region include the stretches of
+    /// it, and corners, they cover. Faces without a cell, like those left
+    /// empty by duplicate sites, get an empty polygon. The hyperbolic edges
+    /// of an additively weighted diagram are followed as closely as the
+    /// diagram follows them.
+    pub fn cell_polygons<R: ClipRegion<S>>(&self, region: &R) -> Vec<Vec<Point<S>>> {
+        let neighbours = self.faces_across();
+        let sites = self
+            .faces
+            .iter()
+            .map(|face| WeightedSite::new(face.site.clone(), face.weight.clone()))
+            .collect::<Vec<_>>();
+
+        neighbours
+            .into_iter()
+            .enumerate()
+            .map(|(face, neighbours)| {
+                if !self.has_cell(face) {
+                    return vec![];
+                }
+                // the cell is what is left of the region after cutting away
+                // everything closer to a neighbouring site
+                if let Some(tolerance) = &self.additive {
+                    return self
+                        .additive_bisectors(face, neighbours, tolerance)
+                        .fold(region.vertices(), |polygon, bisector| {
+                            clip_cell(polygon, &bisector)
+                        });
+                }
+                if self.metric != Metric::Euclidean {
+                    return self
+                        .bisectors(face, neighbours)
+                        .fold(region.vertices(), |polygon, bisector| {
+                            clip_cell(polygon, &bisector)
+                        });
+                }
+                neighbours
+                    .iter()
+                    .fold(region.vertices(), |polygon, &other| {
+                        // or closer to the site itself, when the farthest
+                        // one wins
+                        if self.farthest {
+                            clip_to_power_bisector(&polygon, &sites[other], &sites[face])
+                        } else {
+                            clip_to_power_bisector(&polygon, &sites[face], &sites[other])
+                        }
+                    })
+            })
+            .collect()
+    }
+
+    /// The faces of [`Self::adjacency`], without the edges.
+    fn faces_across(&self) -> Vec<Vec<usize>> {
+        self.adjacency()
+            .into_iter()
+            .map(|neighbours| neighbours.into_iter().map(|n| n.face).collect())
+            .collect()
+    }
+
+    /// The bisectors between the site of `face` and those of `neighbours`
+    /// under the metric of the diagram, each neighbour once.
+    fn bisectors(
+        &self,
+        face: usize,
+        mut neighbours: Vec<usize>,
+    ) -> impl Iterator<Item = Bisector<S>> + '_ {
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        let site = &self.faces[face].site;
+        neighbours
+            .into_iter()
+            .map(move |other| Bisector::new(site, &self.faces[other].site, self.metric))
+    }
+
+    /// Like [`Self::bisectors`], for an additively weighted diagram whose
+    /// edges follow their hyperbolas to within `tolerance`.
+    fn additive_bisectors<'a>(
+        &'a self,
+        face: usize,
+        mut neighbours: Vec<usize>,
+        tolerance: &'a S,
+    ) -> impl Iterator<Item = AdditiveBisector> + 'a {
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        let site = |face: usize| {
+            let face = &self.faces[face];
+            WeightedSite::new(face.site.clone(), face.weight.clone())
+        };
+        neighbours
+            .into_iter()
+            .map(move |other| AdditiveBisector::new(&site(face), &site(other), tolerance))
+    }
+
+    /// The cell of every face intersected with `polygon`, which need not be
+    /// convex. A cell can fall apart into several parts, and holes of the
+    /// polygon lying inside a cell are kept. Faces without a cell have no
+    /// parts and are not counted as empty.
+    pub fn cells_in_polygon(&self, polygon: &PolygonWithHoles<S>) -> ClippedCells<S> {
+        if self.metric != Metric::Euclidean || self.additive.is_some() {
+            // cells bounded by bent or curved bisectors need not be convex,
+            // so the polygon is cut by the bisectors themselves
+            let parts = self
+                .faces_across()
+                .into_iter()
+                .enumerate()
+                .map(|(face, neighbours)| {
+                    if !self.has_cell(face) {
+                        return vec![];
+                    }
+                    match &self.additive {
+                        Some(tolerance) => polygon
+                            .intersect_cuts(self.additive_bisectors(face, neighbours, tolerance)),
+                        None => polygon.intersect_cuts(self.bisectors(face, neighbours)),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let empty = (0..self.faces.len())
+                .filter(|&face| self.has_cell(face) && parts[face].is_empty())
+                .collect();
+            return ClippedCells { parts, empty };
+        }
+        let cells = self.cell_polygons(&polygon.bounding_box());
+        let parts = cells
+            .iter()
+            .map(|cell| {
+                if cell.is_empty() {
+                    vec![]
+                } else {
+                    polygon.intersect_convex(cell)
+                }
+            })
+            .collect::<Vec<_>>();
+        let empty = (0..self.faces.len())
+            .filter(|&face| self.has_cell(face) && parts[face].is_empty())
+            .collect();
+        ClippedCells { parts, empty }
+    }
+
+    /// Like [`Self::segments`], with every edge cut down to the part of it
+    /// inside `region`. Edges can run outside the region the diagram was
+    /// computed for where the cells of sites close to its boundary meet
+    /// beyond it.
+    pub fn clipped_segments<R: ClipRegion<S>>(&self, region: &R) -> Vec<Segment<S>> {
+        self.segments()
+            .iter()
+            .filter_map(|segment| region.clip_segment(segment))
+            .filter(|[a, b]| a != b)
+            .collect()
+    }
+
+    /// The dual Delaunay triangulation, with a triangle for every Voronoi
+    /// vertex, or a fan of them where more than three cells meet. For a
+    /// farthest-point diagram this is the farthest-point Delaunay
+    /// triangulation of the corners of the hull, whose triangles have
+    /// circumcircles containing every site.
+    pub fn delaunay_triangulation(&self) -> Triangulation {
+        let site = |face: usize| &self.faces[face].site;
+        let mut triangles = vec![];
+        for v in 0..self.vertices.len() {
+            if self.is_ray_end(v) {
+                continue;
+            }
+            let faces = self
+                .outgoing(v)
+                .into_iter()
+                .map(|edge| self.half_edges[edge].face)
+                .collect::<Vec<_>>();
+            // where a bisector under another metric bends
+            if faces.len() < 3 {
+                continue;
+            }
+            let ccw = orient2d(site(faces[0]), site(faces[1]), site(faces[2])) == Ordering::Greater;
+            for pair in faces[1..].windows(2) {
+                triangles.push(if ccw {
+                    [faces[0], pair[0], pair[1]]
+                } else {
+                    [faces[0], pair[1], pair[0]]
+                });
+            }
+        }
+
+        let neighbours = self
+            .half_edges
+            .iter()
+            .enumerate()
+            .filter(|&(idx, edge)| idx < edge.twin)
+            .map(|(_, edge)| [edge.face, self.half_edges[edge.twin].face]);
+        Triangulation::new(triangles, neighbours)
+    }
+
+    /// Every edge with both endpoints known, as a flat list of segments.
+    pub fn segments(&self) -> Vec<Segment<S>> {
+        self.half_edges
+            .iter()
+            .enumerate()
+            .filter(|&(idx, edge)| idx < edge.twin)
+            .filter_map(|(idx, _)| {
+                let a = self.half_edges[idx].origin?;
+                let b = self.destination(idx)?;
+                Some([
+                    self.vertices[a].point.clone(),
+                    self.vertices[b].point.clone(),
+                ])
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,1181 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+use super::{Diagram, Face, HalfEdge, Vertex};
+use crate::{
+    geometry::{
+        circumcenter, convex_hull, incircle, normal_vector, orient2d, ClipRegion, Metric, Point,
+        Scalar,
+    },
+    try_fortunes_algorithm, Error,
+};
+
+#[cfg(test)]
+thread_local! {
+    /// Diagrams computed again from scratch on this thread, for tests to
+    /// check that updates stay local.
+    static REBUILDS: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+    /// Faces looked at by the updates on this thread, likewise.
+    static TOUCHED: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+}
+
+/// Where the cell of a new site cuts into the cell of `face`: it takes the
+/// stretch of the boundary after the half-edge `enters` up to the half-edge
+/// `leaves`, with `None` where that stretch runs off to infinity.
+struct Cut {
+    face: usize,
+    enters: Option<usize>,
+    leaves: Option<usize>,
+}
+
+impl<S: Scalar> Diagram<S> {
+    /// Add `site` to the diagram as a new face after the last one, and
+    /// return that face. Only the cells around the new site are touched, and
+    /// the result is the diagram [`crate::try_fortunes_algorithm`] computes
+    /// for all the sites, given the `region` this one was computed for. The
+    /// site is looked for from where the last update left off, so the
+    /// further apart updates in a row are, the longer that takes.
+    ///
+    /// A site equal to an earlier one gets an empty face, see
+    /// [`Self::site_map`]. While all sites lie on a line, or when the new
+    /// one lies on a circle through three sites around it or on a line with
+    /// two on the hull, the whole diagram is computed again instead. Fails
+    /// with [`Error::Unsupported`] on a weighted diagram of either kind, a
+    /// farthest-point one or one under another [`Metric`].
+    pub fn insert_site<R: ClipRegion<S>>(
+        &mut self,
+        site: Point<S>,
+        region: &R,
+    ) -> Result<usize, Error> {
+        self.check_plain()?;
+        let face = self.faces.len();
+        if !site.x.is_finite() || !site.y.is_finite() {
+            return Err(Error::NonFiniteSite(face));
+        }
+        if !region.contains(&site) {
+            return Err(Error::SiteOutsideBoundingBox(face));
+        }
+
+        self.faces.push(Face {
+            site,
+            weight: S::zero(),
+            outer_component: None,
+        });
+        self.site_map.push(face);
+        if !self.attach(face, self.recent, region) {
+            self.rebuild(region)?;
+        }
+        Ok(face)
+    }
+
+    /// Remove the site of `face`. The last face takes its place, as in
+    /// [`Vec::swap_remove`], and the result is the diagram
+    /// [`crate::try_fortunes_algorithm`] computes for the remaining sites in
+    /// that order, given the `region` this one was computed for. Only the
+    /// cells around the site are touched.
+    ///
+    /// A duplicate of the site takes over its cell. When the remaining sites
+    /// lie on a line, or the cell has a vertex where more than three cells
+    /// meet or that lies on a circle through the sites of its neighbours,
+    /// the whole diagram is computed again instead. Fails with
+    /// [`Error::Unsupported`] on a weighted diagram of either kind, a
+    /// farthest-point one or one under another [`Metric`].
+    pub fn remove_site<R: ClipRegion<S>>(&mut self, face: usize, region: &R) -> Result<(), Error> {
+        self.check_plain()?;
+        let local = self.vacate(face, region);
+        // a rebuild only looks at the sites, so the edges left to the face
+        // when it could not be vacated do no harm
+        self.swap_remove_face(face);
+        if !local {
+            self.rebuild(region)?;
+        }
+        Ok(())
+    }
+
+    /// Move the site of `face` to `site`, keeping the indices of all faces.
+    /// Equivalent to removing it and inserting it again, and only touches
+    /// the cells around its old and new position, see [`Self::remove_site`]
+    /// and [`Self::insert_site`].
+    pub fn move_site<R: ClipRegion<S>>(
+        &mut self,
+        face: usize,
+        site: Point<S>,
+        region: &R,
+    ) -> Result<(), Error> {
+        self.check_plain()?;
+        if !site.x.is_finite() || !site.y.is_finite() {
+            return Err(Error::NonFiniteSite(face));
+        }
+        if !region.contains(&site) {
+            return Err(Error::SiteOutsideBoundingBox(face));
+        }
+
+        // the new position is looked for from a cell next to the old one
+        let near = self
+            .boundary(face)
+            .first()
+            .map_or(self.recent, |&edge| self.half_edges[self.twin(edge)].face);
+        let local = self.vacate(face, region);
+        self.faces[face].site = site;
+        if !(local && self.attach(face, near, region)) {
+            self.rebuild(region)?;
+        }
+        Ok(())
+    }
+
+    /// The updates work with plain distances, and rebuild with
+    /// [`crate::try_fortunes_algorithm`].
+    fn check_plain(&self) -> Result<(), Error> {
+        if self.weighted {
+            return Err(Error::Unsupported("updating the sites of a power diagram"));
+        }
+        if self.metric != Metric::Euclidean {
+            return Err(Error::Unsupported(
+                "updating the sites of a diagram under another metric",
+            ));
+        }
+        if self.farthest {
+            return Err(Error::Unsupported(
+                "updating the sites of a farthest-point diagram",
+            ));
+        }
+        if self.additive.is_some() {
+            return Err(Error::Unsupported(
+                "updating the sites of an additively weighted diagram",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Carve out a cell for the site of `face`, which has no edges yet, from
+    /// the cells around it, looking for it from the cell of `near` if there
+    /// is one. Returns false, without having changed anything, when the
+    /// diagram has to be computed again instead.
+    fn attach<R: ClipRegion<S>>(&mut self, face: usize, near: usize, region: &R) -> bool {
+        if self.convex_hull.len() < 3 {
+            return false;
+        }
+        let q = self.faces[face].site.clone();
+        let start = [near, self.recent]
+            .into_iter()
+            .find(|&other| other < self.faces.len() && self.faces[other].outer_component.is_some())
+            .unwrap_or(self.convex_hull[0]);
+        let nearest = self.nearest_face(start, &q);
+        if self.faces[nearest].site == q {
+            // the earlier of the two keeps the cell
+            if nearest < face {
+                self.site_map[face] = nearest;
+            } else {
+                self.transfer(nearest, face);
+            }
+            self.merged += 1;
+            self.recent = self.site_map[face];
+            return true;
+        }
+
+        // The new cell swallows the vertices closer to q than to their own
+        // sites, and cuts into the cells around them
+        let Some(conflicts) = self.conflicts(nearest, &q) else {
+            return false;
+        };
+        let conflicting = conflicts.iter().copied().collect::<HashSet<_>>();
+        let mut affected = vec![];
+        let mut seen = HashSet::new();
+        for &v in &conflicts {
+            for edge in self.outgoing(v) {
+                for other in [edge, self.twin(edge)] {
+                    let other = self.half_edges[other].face;
+                    if seen.insert(other) {
+                        affected.push(other);
+                    }
+                }
+            }
+        }
+        #[cfg(test)]
+        TOUCHED.with(|touched| touched.set(touched.get() + affected.len() as u64));
+        let Some(cuts) = affected
+            .iter()
+            .map(|&other| self.cut(other, &conflicting))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+
+        // the new vertices, where the boundary of the new cell crosses the
+        // edges running into it
+        let site = |edge: usize| &self.faces[self.half_edges[edge].face].site;
+        let mut crossings = vec![];
+        let mut crossed = HashMap::new();
+        for cut in &cuts {
+            for edge in [cut.enters, cut.leaves].into_iter().flatten() {
+                if crossed.contains_key(&edge) {
+                    continue;
+                }
+                let twin = self.twin(edge);
+                let Some(point) = circumcenter(&q, site(edge), site(twin)) else {
+                    return false;
+                };
+                let v = self.vertices.len() + crossings.len();
+                crossed.extend([(edge, v), (twin, v)]);
+                crossings.push((point, edge));
+            }
+            // q on a circle through three sites would give two of the new
+            // vertices at the same spot
+            if let (Some(enters), Some(leaves)) = (cut.enters, cut.leaves) {
+                let (a, b, c) = (
+                    site(self.twin(enters)),
+                    site(enters),
+                    site(self.twin(leaves)),
+                );
+                let on_circle = match orient2d(&q, b, a) {
+                    Ordering::Greater => incircle(&q, b, a, c),
+                    Ordering::Less => incircle(&q, a, b, c),
+                    Ordering::Equal => Ordering::Equal,
+                };
+                if on_circle == Ordering::Equal {
+                    return false;
+                }
+            }
+        }
+        let extends_hull = conflicts.iter().any(|&v| self.is_ray_end(v));
+
+        let deleted_edges = cuts
+            .iter()
+            .flat_map(|cut| self.boundary(cut.face))
+            .filter(|&edge| {
+                let inside = |v: Option<usize>| v.is_some_and(|v| conflicting.contains(&v));
+                inside(self.half_edges[edge].origin) && inside(self.destination(edge))
+            })
+            .collect::<Vec<_>>();
+
+        for (point, edge) in crossings {
+            self.vertices.push(Vertex {
+                point,
+                incident_edge: edge,
+            });
+        }
+        for cut in &cuts {
+            if let Some(enters) = cut.enters {
+                let v = crossed[&enters];
+                let twin = self.twin(enters);
+                self.set_origin(twin, v);
+                self.vertices[v].incident_edge = twin;
+            }
+            if let Some(leaves) = cut.leaves {
+                self.set_origin(leaves, crossed[&leaves]);
+            }
+        }
+        // rays now starting from a new vertex end where a ray from there
+        // does, which differs when their old start lay beyond the region
+        for cut in &cuts {
+            let Some(enters) = cut.enters else {
+                continue;
+            };
+            let Some(end) = self.half_edges[enters]
+                .origin
+                .filter(|&v| self.is_ray_end(v))
+            else {
+                continue;
+            };
+            let from = &self.vertices[crossed[&enters]].point;
+            let direction = normal_vector(
+                self.faces[cut.face].site.clone()
+                    - self.faces[self.half_edges[self.twin(enters)].face]
+                        .site
+                        .clone(),
+            );
+            self.vertices[end].point = region.ray_end(from, &direction);
+        }
+
+        // every cut gets a new edge between the old cell and the new one,
+        // or a ray where the cut runs off to infinity
+        let mut new_edges = HashMap::new();
+        for cut in &cuts {
+            let (inner, outer) = self.add_edge(cut.face, face);
+            new_edges.insert(cut.face, outer);
+            self.faces[cut.face].outer_component = Some(inner);
+            let other = self.faces[cut.face].site.clone();
+
+            match cut.enters {
+                Some(enters) => {
+                    self.set_origin(inner, crossed[&enters]);
+                    self.link(enters, inner);
+                }
+                None => {
+                    let to = &self.vertices[crossed[&cut.leaves.unwrap()]].point;
+                    let end = region.ray_end(to, &normal_vector(other.clone() - q.clone()));
+                    self.add_vertex(end, inner);
+                }
+            }
+            match cut.leaves {
+                Some(leaves) => {
+                    self.set_origin(outer, crossed[&leaves]);
+                    self.link(inner, leaves);
+                }
+                None => {
+                    let to = &self.vertices[crossed[&cut.enters.unwrap()]].point;
+                    let end = region.ray_end(to, &normal_vector(q.clone() - other));
+                    self.add_vertex(end, outer);
+                }
+            }
+        }
+        // around the new cell, the edge shared with a neighbour is followed
+        // by the one shared with the face across where the new cell entered
+        // the cell of that neighbour
+        for cut in &cuts {
+            if let Some(enters) = cut.enters {
+                let next = new_edges[&self.half_edges[self.twin(enters)].face];
+                self.link(new_edges[&cut.face], next);
+            }
+        }
+
+        self.remove_half_edges(deleted_edges);
+        self.remove_vertices(conflicts);
+        if extends_hull {
+            let corners = self
+                .convex_hull
+                .iter()
+                .copied()
+                .chain([face])
+                .collect::<Vec<_>>();
+            let points = corners
+                .iter()
+                .map(|&corner| self.faces[corner].site.clone())
+                .collect::<Vec<_>>();
+            self.convex_hull = convex_hull(&points)
+                .into_iter()
+                .map(|idx| corners[idx])
+                .collect();
+        }
+        self.recent = face;
+        true
+    }
+
+    /// Take the site of `face` out of the diagram, leaving the face without
+    /// edges and without sites merged into it. Returns false, without having
+    /// changed anything, when the diagram has to be computed again instead.
+    fn vacate<R: ClipRegion<S>>(&mut self, face: usize, region: &R) -> bool {
+        if self.site_map[face] != face {
+            self.site_map[face] = face;
+            self.merged -= 1;
+            return true;
+        }
+        // the first duplicate takes over the cell, as it would have in the
+        // sweep had this site never been there
+        let duplicate = (self.merged > 0)
+            .then(|| (face + 1..self.faces.len()).find(|&other| self.site_map[other] == face))
+            .flatten();
+        if let Some(other) = duplicate {
+            self.transfer(face, other);
+            self.site_map[face] = face;
+            self.merged -= 1;
+            return true;
+        }
+        self.detach(face, region)
+    }
+
+    /// Share out the cell of `face` among the cells around it, leaving the
+    /// face without edges. Returns false, without having changed anything,
+    /// when the diagram has to be computed again instead.
+    fn detach<R: ClipRegion<S>>(&mut self, face: usize, region: &R) -> bool {
+        if self.convex_hull.len() < 3 {
+            return false;
+        }
+        let r = self.faces[face].site.clone();
+        let boundary = self.boundary(face);
+        let neighbours = boundary
+            .iter()
+            .map(|&edge| self.half_edges[self.twin(edge)].face)
+            .collect::<Vec<_>>();
+        #[cfg(test)]
+        TOUCHED.with(|touched| touched.set(touched.get() + neighbours.len() as u64));
+        let mut corners = boundary
+            .iter()
+            .filter_map(|&edge| self.half_edges[edge].origin)
+            .collect::<Vec<_>>();
+        let unbounded = self.half_edges[boundary[0]].prev.is_none();
+        if unbounded {
+            corners.extend(self.destination(*boundary.last().unwrap()));
+        }
+
+        // the hull without the site has its neighbours in its place
+        let hull = if unbounded {
+            let mut candidates = self
+                .convex_hull
+                .iter()
+                .copied()
+                .filter(|&corner| corner != face)
+                .chain(neighbours.iter().copied())
+                .collect::<Vec<_>>();
+            candidates.sort_unstable();
+            candidates.dedup();
+            let points = candidates
+                .iter()
+                .map(|&corner| self.faces[corner].site.clone())
+                .collect::<Vec<_>>();
+            let hull = convex_hull(&points)
+                .into_iter()
+                .map(|idx| candidates[idx])
+                .collect::<Vec<_>>();
+            if hull.len() < 3 {
+                return false;
+            }
+            Some(hull)
+        } else {
+            None
+        };
+
+        // Inside the old cell the diagram of the remaining sites is that of
+        // the neighbours alone, with its vertices there being those closer
+        // to r than to their sites
+        let sites = neighbours
+            .iter()
+            .map(|&other| self.faces[other].site.clone())
+            .collect::<Vec<_>>();
+        let Ok(local) = try_fortunes_algorithm(&sites, region) else {
+            return false;
+        };
+        let mut inside = vec![false; local.vertices.len()];
+        for (v, inside) in inside.iter_mut().enumerate() {
+            if !local.is_ray_end(v) {
+                let Some(conflict) = local.in_conflict(v, &r) else {
+                    return false;
+                };
+                *inside = conflict;
+            }
+        }
+        let ends = |edge: usize| {
+            (
+                local.half_edges[edge].origin.unwrap(),
+                local.destination(edge).unwrap(),
+            )
+        };
+
+        // The edge between two neighbours ending at a corner of the cell
+        // goes on into it, the same way as the one between them in the local
+        // diagram, whose start has to lie inside the cell
+        let position = neighbours
+            .iter()
+            .enumerate()
+            .map(|(idx, &other)| (other, idx))
+            .collect::<HashMap<_, _>>();
+        let between = (0..local.half_edges.len())
+            .map(|edge| {
+                let faces = (
+                    local.half_edges[edge].face,
+                    local.half_edges[local.twin(edge)].face,
+                );
+                (faces, edge)
+            })
+            .collect::<HashMap<_, _>>();
+        let mut crossings = vec![];
+        for &v in &corners {
+            if self.is_ray_end(v) {
+                continue;
+            }
+            let outgoing = self.outgoing(v);
+            if outgoing.len() != 3 {
+                return false;
+            }
+            let Some(edge) = outgoing.into_iter().find(|&edge| {
+                self.half_edges[edge].face != face && self.half_edges[self.twin(edge)].face != face
+            }) else {
+                return false;
+            };
+            let far = self.destination(edge).unwrap();
+            let faces = (
+                position[&self.half_edges[edge].face],
+                position[&self.half_edges[self.twin(edge)].face],
+            );
+            let Some(&local_edge) = between.get(&faces) else {
+                return false;
+            };
+            let (from, to) = ends(local_edge);
+            let starts_inside = inside[from] || local.is_ray_end(from) && !self.is_ray_end(far);
+            if !starts_inside || inside[to] || corners.contains(&far) {
+                return false;
+            }
+            crossings.push((local_edge, edge));
+        }
+
+        // the edges of the local diagram lying inside the cell, or running
+        // off to infinity from there
+        let crossed = crossings
+            .iter()
+            .flat_map(|&(local_edge, _)| [local_edge, local.twin(local_edge)])
+            .collect::<HashSet<_>>();
+        let mut added = vec![];
+        for edge in 0..local.half_edges.len() {
+            if edge > local.twin(edge) || crossed.contains(&edge) {
+                continue;
+            }
+            let (from, to) = ends(edge);
+            if !inside[from] && !inside[to] {
+                continue;
+            }
+            let within = |v: usize| inside[v] || local.is_ray_end(v);
+            if !within(from) || !within(to) {
+                return false;
+            }
+            added.push(edge);
+        }
+
+        let mut edges = HashMap::new();
+        for &(local_edge, edge) in &crossings {
+            edges.insert(local_edge, edge);
+            edges.insert(local.twin(local_edge), self.twin(edge));
+            // no longer followed by the edges of the cell at the corner
+            self.half_edges[edge].prev = None;
+            let twin = self.twin(edge);
+            self.half_edges[twin].next = None;
+        }
+        for &local_edge in &added {
+            let twin = local.twin(local_edge);
+            let (left, right) = self.add_edge(
+                neighbours[local.half_edges[local_edge].face],
+                neighbours[local.half_edges[twin].face],
+            );
+            edges.extend([(local_edge, left), (twin, right)]);
+        }
+        let mut vertices = HashMap::new();
+        for v in (0..local.vertices.len()).filter(|&v| inside[v]) {
+            vertices.insert(v, self.vertices.len());
+            self.vertices.push(Vertex {
+                point: local.vertices[v].point.clone(),
+                incident_edge: edges[&local.vertices[v].incident_edge],
+            });
+        }
+
+        // the ends of the edges inside, and rays starting from them
+        let inner = crossings
+            .iter()
+            .map(|&(local_edge, _)| local_edge)
+            .chain(added.iter().flat_map(|&edge| [edge, local.twin(edge)]))
+            .collect::<Vec<_>>();
+        let mut rays = vec![];
+        for &local_edge in &inner {
+            let edge = edges[&local_edge];
+            match vertices.get(&ends(local_edge).0) {
+                Some(&v) => self.set_origin(edge, v),
+                None => rays.push(edge),
+            }
+        }
+        let site = |edge: usize| self.faces[self.half_edges[edge].face].site.clone();
+        let mut ray_ends = vec![];
+        for edge in rays {
+            let from = &self.vertices[self.destination(edge).unwrap()].point;
+            let direction = normal_vector(site(edge) - site(self.twin(edge)));
+            ray_ends.push((region.ray_end(from, &direction), edge));
+        }
+        // rays that now start further back end where a ray from there does
+        let mut moved_ends = vec![];
+        for &(_, edge) in &crossings {
+            let twin = self.twin(edge);
+            let far = self.half_edges[twin].origin.unwrap();
+            if self.is_ray_end(far) {
+                let from = &self.vertices[self.half_edges[edge].origin.unwrap()].point;
+                let direction = normal_vector(site(twin) - site(edge));
+                moved_ends.push((far, region.ray_end(from, &direction)));
+            }
+        }
+        for (v, point) in moved_ends {
+            self.vertices[v].point = point;
+        }
+        for (point, edge) in ray_ends {
+            self.add_vertex(point, edge);
+        }
+
+        // around the new vertices the edges follow each other as they do in
+        // the local diagram
+        let outer = crossings
+            .iter()
+            .map(|&(local_edge, _)| local.twin(local_edge));
+        for local_edge in inner.iter().copied().chain(outer) {
+            let edge = edges[&local_edge];
+            self.faces[self.half_edges[edge].face].outer_component = Some(edge);
+            let Some(next) = local.half_edges[local_edge].next else {
+                continue;
+            };
+            if inside[ends(local_edge).1] {
+                self.link(edge, edges[&next]);
+            }
+        }
+
+        let deleted = boundary
+            .iter()
+            .flat_map(|&edge| [edge, self.twin(edge)])
+            .collect::<Vec<_>>();
+        self.faces[face].outer_component = None;
+        self.remove_half_edges(deleted);
+        self.remove_vertices(corners);
+        if let Some(hull) = hull {
+            self.convex_hull = hull;
+        }
+        self.recent = neighbours[0];
+        true
+    }
+
+    /// Hand the cell of `from`, and the sites merged into it, over to `to`,
+    /// which has no edges.
+    fn transfer(&mut self, from: usize, to: usize) {
+        for edge in self.boundary(from) {
+            self.half_edges[edge].face = to;
+        }
+        self.faces[to].outer_component = self.faces[from].outer_component.take();
+        if let Some(chains) = self.chains.remove(&from) {
+            for &start in &chains {
+                for edge in self.chain(start) {
+                    self.half_edges[edge].face = to;
+                }
+            }
+            self.chains.insert(to, chains);
+        }
+        for entry in self.site_map.iter_mut().chain(&mut self.convex_hull) {
+            if *entry == from {
+                *entry = to;
+            }
+        }
+    }
+
+    /// Drop `face`, which has no sites merged into it, moving the last face
+    /// into its slot.
+    fn swap_remove_face(&mut self, face: usize) {
+        let last = self.faces.len() - 1;
+        self.faces.swap_remove(face);
+        self.site_map.swap_remove(face);
+        self.chains.remove(&face);
+        if face == last {
+            return;
+        }
+        if let Some(chains) = self.chains.remove(&last) {
+            self.chains.insert(face, chains);
+        }
+        for neighbour in self.neighbours(face) {
+            self.half_edges[neighbour.edge].face = face;
+        }
+        if self.recent == last {
+            self.recent = face;
+        }
+        for entry in &mut self.convex_hull {
+            if *entry == last {
+                *entry = face;
+            }
+        }
+        // without merged sites only the moved face refers to itself
+        if self.merged == 0 {
+            self.site_map[face] = face;
+            return;
+        }
+        for entry in &mut self.site_map {
+            if *entry == last {
+                *entry = face;
+            }
+        }
+        // a site merged into a later one now comes first
+        let merged_into = self.site_map[face];
+        if merged_into > face {
+            self.transfer(merged_into, face);
+        }
+    }
+
+    /// The face whose site is nearest to `p`, walking from `face` to
+    /// whichever neighbour is closer for as long as there is one.
+    fn nearest_face(&self, mut face: usize, p: &Point<S>) -> usize {
+        let dist = |face: usize| {
+            let site = &self.faces[face].site;
+            let dx = site.x.clone() - p.x.clone();
+            let dy = site.y.clone() - p.y.clone();
+            dx.clone() * dx + dy.clone() * dy
+        };
+        let mut best = dist(face);
+        loop {
+            #[cfg(test)]
+            TOUCHED.with(|touched| touched.set(touched.get() + 1));
+            let closer = self
+                .neighbours(face)
+                .iter()
+                .map(|neighbour| (dist(neighbour.face), neighbour.face))
+                .filter(|(d, _)| *d < best)
+                .min();
+            let Some((d, other)) = closer else {
+                return face;
+            };
+            best = d;
+            face = other;
+        }
+    }
+
+    /// The vertices closer to `q` than to the sites they are equally far
+    /// from, found spreading out from the cell of `face`, which contains
+    /// `q`. `None` when `q` is exactly as close to one of them.
+    fn conflicts(&self, face: usize, q: &Point<S>) -> Option<Vec<usize>> {
+        let mut seen = HashSet::new();
+        let mut stack = self
+            .boundary(face)
+            .iter()
+            .flat_map(|&edge| [self.half_edges[edge].origin, self.destination(edge)])
+            .flatten()
+            .filter(|&v| seen.insert(v))
+            .collect::<Vec<_>>();
+
+        let mut res = vec![];
+        while let Some(v) = stack.pop() {
+            if !self.in_conflict(v, q)? {
+                continue;
+            }
+            res.push(v);
+            let mut next = self
+                .outgoing(v)
+                .into_iter()
+                .filter_map(|edge| self.destination(edge))
+                .collect::<Vec<_>>();
+            if self.is_ray_end(v) {
+                // the rays along the hull on either side meet this one at
+                // infinity
+                let edge = self.vertices[v].incident_edge;
+                let before = self.boundary(self.half_edges[edge].face);
+                let after = self.boundary(self.half_edges[self.twin(edge)].face);
+                next.extend(self.destination(*before.last().unwrap()));
+                next.extend(self.half_edges[after[0]].origin);
+            }
+            stack.extend(next.into_iter().filter(|&other| seen.insert(other)));
+        }
+        (!res.is_empty()).then_some(res)
+    }
+
+    /// Whether `q` is closer to the vertex `v` than the sites it is equally
+    /// far from. For the end of a ray this asks about the far end of the
+    /// ray, which goes to a site beyond the edge of the hull it crosses.
+    fn in_conflict(&self, v: usize, q: &Point<S>) -> Option<bool> {
+        let site = |edge: usize| &self.faces[self.half_edges[edge].face].site;
+        let edge = self.vertices[v].incident_edge;
+        let order = if self.is_ray_end(v) {
+            // coming in from the ray's end, the hull lies to the right
+            orient2d(site(edge), site(self.twin(edge)), q).reverse()
+        } else {
+            let prev = self.half_edges[edge].prev.unwrap();
+            let (a, b, c) = (site(edge), site(self.twin(edge)), site(self.twin(prev)));
+            if orient2d(a, b, c) == Ordering::Greater {
+                incircle(a, b, c, q)
+            } else {
+                incircle(a, c, b, q)
+            }
+        };
+        match order {
+            Ordering::Greater => Some(true),
+            Ordering::Less => Some(false),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// How the cell of a new site swallowing the vertices `conflicts` cuts
+    /// into the cell of `face`. `None` unless it takes a single stretch of
+    /// the boundary, leaving some of it.
+    fn cut(&self, face: usize, conflicts: &HashSet<usize>) -> Option<Cut> {
+        let boundary = self.boundary(face);
+        let inside = |v: Option<usize>| v.is_some_and(|v| conflicts.contains(&v));
+        let (mut enters, mut leaves) = (None, None);
+        for (idx, &edge) in boundary.iter().enumerate() {
+            match (
+                inside(self.half_edges[edge].origin),
+                inside(self.destination(edge)),
+            ) {
+                (false, true) if enters.replace(idx).is_some() => return None,
+                (true, false) if leaves.replace(idx).is_some() => return None,
+                _ => {}
+            }
+        }
+
+        let (first, last) = (boundary[0], *boundary.last().unwrap());
+        if self.half_edges[first].prev.is_none() {
+            // An unbounded cell, where a stretch without an edge entering it
+            // has to start at the open end, and likewise for its end. One
+            // taking both ends runs around through infinity, and closes the
+            // cell off.
+            if enters.is_none() && !inside(self.half_edges[first].origin)
+                || leaves.is_none() && !inside(self.destination(last))
+            {
+                return None;
+            }
+        } else if enters.is_none() || leaves.is_none() {
+            return None;
+        }
+        if enters.is_none() && leaves.is_none() {
+            return None;
+        }
+
+        Some(Cut {
+            face,
+            enters: enters.map(|idx| boundary[idx]),
+            leaves: leaves.map(|idx| boundary[idx]),
+        })
+    }
+
+    /// Drop the half-edges `edges`, which nothing left refers to, moving the
+    /// last ones into the slots they free.
+    fn remove_half_edges(&mut self, mut edges: Vec<usize>) {
+        edges.sort_unstable_by(|a, b| b.cmp(a));
+        edges.dedup();
+        for idx in edges {
+            let last = self.half_edges.len() - 1;
+            self.half_edges.swap_remove(idx);
+            if idx == last {
+                continue;
+            }
+            let &HalfEdge {
+                origin,
+                twin,
+                next,
+                prev,
+                face,
+            } = &self.half_edges[idx];
+            self.half_edges[twin].twin = idx;
+            if let Some(next) = next {
+                self.half_edges[next].prev = Some(idx);
+            }
+            if let Some(prev) = prev {
+                self.half_edges[prev].next = Some(idx);
+            }
+            if self.faces[face].outer_component == Some(last) {
+                self.faces[face].outer_component = Some(idx);
+            }
+            if let Some(v) = origin {
+                if self.vertices[v].incident_edge == last {
+                    self.vertices[v].incident_edge = idx;
+                }
+            }
+        }
+    }
+
+    /// Drop the vertices `vertices`, which no half-edge starts at any more,
+    /// moving the last ones into the slots they free.
+    fn remove_vertices(&mut self, mut vertices: Vec<usize>) {
+        vertices.sort_unstable_by(|a, b| b.cmp(a));
+        vertices.dedup();
+        for idx in vertices {
+            let last = self.vertices.len() - 1;
+            self.vertices.swap_remove(idx);
+            if idx == last {
+                continue;
+            }
+            for edge in self.outgoing(idx) {
+                self.set_origin(edge, idx);
+            }
+        }
+    }
+
+    /// Compute the diagram again from scratch, over the same sites.
+    fn rebuild<R: ClipRegion<S>>(&mut self, region: &R) -> Result<(), Error> {
+        #[cfg(test)]
+        REBUILDS.with(|rebuilds| rebuilds.set(rebuilds.get() + 1));
+        let sites = self
+            .faces
+            .iter()
+            .map(|face| face.site.clone())
+            .collect::<Vec<_>>();
+        // merged sites take the position of the one they were merged into,
+        // so they end up merged again
+        let merged = self
+            .site_map
+            .iter()
+            .map(|&face| sites[face].clone())
+            .collect::<Vec<_>>();
+        let mut diagram = try_fortunes_algorithm(&merged, region)?;
+        for (face, site) in diagram.faces.iter_mut().zip(sites) {
+            face.site = site;
+        }
+        *self = diagram;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordered_float::OrderedFloat;
+
+    use crate::{
+        fortunes_algorithm,
+        test_utils::{bounding_box, check_diagram, point, random_sites, same_diagram},
+    };
+
+    use super::*;
+
+    /// Insert the sites after the first `initial` one by one, comparing with
+    /// the diagram computed from scratch each time. Returns how often the
+    /// insertion had to do that as well.
+    fn check_insertions<S: Scalar>(sites: &[Point<S>], initial: usize, eps: f64) -> u64 {
+        let rebuilds = REBUILDS.with(|rebuilds| rebuilds.get());
+        let bounding_box = bounding_box();
+        let mut diagram = fortunes_algorithm(&sites[..initial], &bounding_box);
+        for idx in initial..sites.len() {
+            let face = diagram
+                .insert_site(sites[idx].clone(), &bounding_box)
+                .unwrap();
+            assert_eq!(face, idx);
+            assert!(check_diagram(&sites[..=idx], &diagram), "site {}", idx);
+            let rebuilt = fortunes_algorithm(&sites[..=idx], &bounding_box);
+            assert!(same_diagram(&diagram, &rebuilt, eps), "site {}", idx);
+        }
+        REBUILDS.with(|rebuilds| rebuilds.get()) - rebuilds
+    }
+
+    /// Remove `removals` of the sites one by one, then move as many of the
+    /// rest to the positions in `moves`, comparing with the diagram computed
+    /// from scratch each time. Returns how often the updates had to do that
+    /// as well.
+    fn check_updates<S: Scalar>(
+        sites: &[Point<S>],
+        removals: usize,
+        moves: &[Point<S>],
+        eps: f64,
+    ) -> u64 {
+        let rebuilds = REBUILDS.with(|rebuilds| rebuilds.get());
+        let bounding_box = bounding_box();
+        let mut sites = sites.to_vec();
+        let mut diagram = fortunes_algorithm(&sites, &bounding_box);
+        for step in 0..removals + moves.len() {
+            let face = step * 37 % sites.len();
+            if step < removals {
+                diagram.remove_site(face, &bounding_box).unwrap();
+                sites.swap_remove(face);
+            } else {
+                let site = moves[step - removals].clone();
+                diagram
+                    .move_site(face, site.clone(), &bounding_box)
+                    .unwrap();
+                sites[face] = site;
+            }
+            assert!(check_diagram(&sites, &diagram), "step {}", step);
+            let rebuilt = fortunes_algorithm(&sites, &bounding_box);
+            assert!(same_diagram(&diagram, &rebuilt, eps), "step {}", step);
+        }
+        REBUILDS.with(|rebuilds| rebuilds.get()) - rebuilds
+    }
+
+    #[test]
+    fn insert_random_sites() {
+        assert_eq!(check_insertions(&random_sites(200, 1000.0, 7), 50, 1e-6), 0);
+    }
+
+    #[test]
+    fn insert_outside_hull() {
+        // a cluster in the middle, then sites all over the box
+        let mut sites = random_sites(20, 200.0, 3)
+            .into_iter()
+            .map(|p| Point::new(p.x + 400.0, p.y + 400.0))
+            .collect::<Vec<_>>();
+        sites.extend(random_sites(60, 1000.0, 8));
+        assert_eq!(check_insertions(&sites, 20, 1e-6), 0);
+    }
+
+    #[test]
+    fn insert_degenerate_sites() {
+        // from a single site, along a line, then cocircular with the first
+        // four, and duplicates
+        let sites = [
+            point(100.0, 100.0),
+            point(300.0, 100.0),
+            point(500.0, 100.0),
+            point(300.0, 300.0),
+            point(100.0, 300.0),
+            point(500.0, 300.0),
+            point(300.0, 100.0),
+            point(200.0, 200.0),
+            point(700.0, 500.0),
+            point(900.0, 700.0),
+            point(700.0, 500.0),
+        ];
+        assert!(check_insertions(&sites, 1, 1e-6) > 0);
+    }
+
+    #[test]
+    fn insert_into_empty_diagram() {
+        let bounding_box = bounding_box();
+        let mut diagram = fortunes_algorithm::<OrderedFloat<f64>, _>(&[], &bounding_box);
+        let sites = random_sites(3, 1000.0, 1);
+        for site in &sites {
+            diagram.insert_site(*site, &bounding_box).unwrap();
+        }
+        assert!(same_diagram(
+            &diagram,
+            &fortunes_algorithm(&sites, &bounding_box),
+            1e-6
+        ));
+
+        let outside = Point::new(OrderedFloat(-1.0), OrderedFloat(0.0));
+        assert!(matches!(
+            diagram.insert_site(outside, &bounding_box),
+            Err(Error::SiteOutsideBoundingBox(3))
+        ));
+        assert_eq!(diagram.faces().len(), 3);
+    }
+
+    #[test]
+    fn remove_and_move_random_sites() {
+        let sites = random_sites(200, 1000.0, 12);
+        let moves = random_sites(100, 1000.0, 13);
+        assert_eq!(check_updates(&sites, 120, &moves, 1e-6), 0);
+    }
+
+    #[test]
+    fn move_sites_a_little() {
+        // agents taking small steps among static sites
+        let sites = random_sites(200, 1000.0, 21);
+        let moves = (0..100)
+            .map(|step| {
+                let site = sites[step * 37 % sites.len()];
+                let step = step as f64;
+                let p = Point::new(site.x + step.sin() * 3.0, site.y + step.cos() * 3.0);
+                Point::new(
+                    p.x.clamp(0.0.into(), 1000.0.into()),
+                    p.y.clamp(0.0.into(), 1000.0.into()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(check_updates(&sites, 0, &moves, 1e-6), 0);
+    }
+
+    #[test]
+    fn update_degenerate_sites() {
+        // a grid with cocircular sites everywhere, and duplicates
+        let mut sites = (0..25)
+            .map(|idx| {
+                point(
+                    100.0 + 200.0 * (idx % 5) as f64,
+                    100.0 + 200.0 * (idx / 5) as f64,
+                )
+            })
+            .collect::<Vec<_>>();
+        sites.extend([sites[3], sites[18], sites[3]]);
+        let moves = [
+            point(300.0, 300.0),
+            point(500.0, 500.0),
+            point(250.0, 650.0),
+            point(100.0, 100.0),
+            point(910.0, 20.0),
+            point(410.0, 430.0),
+        ];
+        assert!(check_updates(&sites, 10, &moves, 1e-6) > 0);
+    }
+
+    #[test]
+    fn remove_duplicates_of_collinear_sites() {
+        // the strips of the sites take several chains each, which the
+        // duplicates taking over their cells carry along
+        let bounding_box = bounding_box();
+        let mut sites = (0..5)
+            .map(|idx| {
+                Point::new(
+                    OrderedFloat(100.0 + 200.0 * idx as f64),
+                    OrderedFloat(500.0),
+                )
+            })
+            .collect::<Vec<_>>();
+        sites.extend([sites[1], sites[3]]);
+        let mut diagram = fortunes_algorithm(&sites, &bounding_box);
+        for face in [1, 3, 0] {
+            diagram.remove_site(face, &bounding_box).unwrap();
+            sites.swap_remove(face);
+            let rebuilt = fortunes_algorithm(&sites, &bounding_box);
+            assert!(same_diagram(&diagram, &rebuilt, 1e-6), "face {}", face);
+        }
+    }
+
+    #[test]
+    fn updates_stay_local() {
+        // an agent crossing the box, leaving a trail of new sites behind it
+        // while sites elsewhere go away, looks at no more faces per update
+        // in a large diagram than in a small one
+        let bounding_box = bounding_box();
+        for n in [1000, 16000] {
+            let mut sites = random_sites(n, 1000.0, 31);
+            let mut diagram = fortunes_algorithm(&sites, &bounding_box);
+            let touched = TOUCHED.with(|touched| touched.get());
+            let rebuilds = REBUILDS.with(|rebuilds| rebuilds.get());
+            for step in 0..100 {
+                let t = step as f64;
+                let p = Point::new(
+                    OrderedFloat(100.0 + 8.0 * t),
+                    OrderedFloat(500.0 + 300.0 * (t / 10.0).sin()),
+                );
+                diagram.move_site(0, p, &bounding_box).unwrap();
+                sites[0] = p;
+                let trail = Point::new(p.x - 3.0, p.y + 2.0);
+                diagram.insert_site(trail, &bounding_box).unwrap();
+                sites.push(trail);
+                let face = 1 + step * 37 % (n / 2);
+                diagram.remove_site(face, &bounding_box).unwrap();
+                sites.swap_remove(face);
+            }
+            let touched = TOUCHED.with(|touched| touched.get()) - touched;
+            assert!(touched < 300 * 15, "{} faces for {} sites", touched, n);
+            assert_eq!(REBUILDS.with(|rebuilds| rebuilds.get()), rebuilds);
+            let rebuilt = fortunes_algorithm(&sites, &bounding_box);
+            assert!(same_diagram(&diagram, &rebuilt, 1e-6));
+        }
+    }
+
+    #[test]
+    fn remove_down_to_nothing() {
+        let bounding_box = bounding_box();
+        let mut sites = random_sites(6, 1000.0, 9);
+        sites.push(sites[2]);
+        let mut diagram = fortunes_algorithm(&sites, &bounding_box);
+        while !sites.is_empty() {
+            diagram.remove_site(0, &bounding_box).unwrap();
+            sites.swap_remove(0);
+            let rebuilt = fortunes_algorithm(&sites, &bounding_box);
+            assert!(same_diagram(&diagram, &rebuilt, 1e-6));
+        }
+        assert!(diagram.faces().is_empty());
+
+        let mut diagram = fortunes_algorithm(&sites, &bounding_box);
+        diagram
+            .insert_site(
+                Point::new(OrderedFloat(5.0), OrderedFloat(5.0)),
+                &bounding_box,
+            )
+            .unwrap();
+        let outside = Point::new(OrderedFloat(f64::NAN), OrderedFloat(0.0));
+        assert!(matches!(
+            diagram.move_site(0, outside, &bounding_box),
+            Err(Error::NonFiniteSite(0))
+        ));
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_insertions_are_exact() {
+        use num_rational::BigRational;
+
+        let r = |v: f64| BigRational::from_float(v).unwrap();
+        let sites = random_sites(40, 1000.0, 5)
+            .iter()
+            .map(|p| Point::new(r(p.x.0), r(p.y.0)))
+            .collect::<Vec<_>>();
+        assert_eq!(check_insertions(&sites, 10, 0.0), 0);
+    }
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_updates_are_exact() {
+        use num_rational::BigRational;
+
+        let r = |p: &Point| {
+            Point::new(
+                BigRational::from_float(p.x.0).unwrap(),
+                BigRational::from_float(p.y.0).unwrap(),
+            )
+        };
+        let sites = random_sites(30, 1000.0, 6)
+            .iter()
+            .map(r)
+            .collect::<Vec<_>>();
+        let moves = random_sites(12, 1000.0, 14)
+            .iter()
+            .map(r)
+            .collect::<Vec<_>>();
+        assert_eq!(check_updates(&sites, 10, &moves, 0.0), 0);
+    }
+}
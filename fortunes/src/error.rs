@@ -0,0 +1,32 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input admits no well-defined diagram, e.g. an empty bounding box
+    /// or three collinear sites meeting in a circle event
+    DegenerateInput,
+    /// Index of a site with a NaN or infinite coordinate
+    NonFiniteSite(usize),
+    /// Index of a site lying outside the bounding box or other clip region
+    SiteOutsideBoundingBox(usize),
+    /// The beachline reached a state the algorithm never produces on its own
+    InvariantViolation(&'static str),
+    /// The operation does not apply to this kind of diagram
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::DegenerateInput => write!(f, "degenerate input"),
+            Error::NonFiniteSite(idx) => write!(f, "site {} has a non-finite coordinate", idx),
+            Error::SiteOutsideBoundingBox(idx) => {
+                write!(f, "site {} lies outside the clip region", idx)
+            }
+            Error::InvariantViolation(msg) => write!(f, "internal invariant violated: {}", msg),
+            Error::Unsupported(msg) => write!(f, "unsupported: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
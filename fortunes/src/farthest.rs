@@ -0,0 +1,410 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    diagram::Diagram,
+    geometry::{convex_hull, dedup_sites, incircle, normal_vector, ClipRegion, Point, Scalar},
+    locate::splitmix,
+    Error,
+};
+
+/// Compute the farthest-point Voronoi diagram of `sites` within `region`,
+/// in which a point belongs to the site farthest from it.
+///
+/// Only the corners of the convex hull of the sites are farthest from
+/// anywhere, so the faces of all other sites get no cell. Every cell is
+/// unbounded and lies on the far side of the hull from its site, and the
+/// edges form a tree, with a ray leaving it across every edge of the hull.
+/// The center of the smallest circle enclosing the sites lies on it, at a
+/// vertex or halfway between two sites sharing an edge. Sites equal to an
+/// earlier one are merged into it, see [`Diagram::site_map`].
+///
+/// The diagram is built from the dual farthest-point Delaunay triangulation
+/// of the corners of the hull, whose triangles have circumcircles containing
+/// every site.
+pub fn farthest_point_diagram<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+) -> Result<Diagram<S>, Error> {
+    if region.is_empty() {
+        return Err(Error::DegenerateInput);
+    }
+    for (idx, site) in sites.iter().enumerate() {
+        if !site.x.is_finite() || !site.y.is_finite() {
+            return Err(Error::NonFiniteSite(idx));
+        }
+        if !region.contains(site) {
+            return Err(Error::SiteOutsideBoundingBox(idx));
+        }
+    }
+
+    let mut diagram = Diagram::new(sites, dedup_sites(sites, S::zero()));
+    diagram.set_farthest();
+    let hull = convex_hull(sites);
+    diagram.set_convex_hull(hull.clone());
+    match hull[..] {
+        [] | [_] => {}
+        [a, b] => split_plane(sites, a, b, &mut diagram, region),
+        // The cells lie beyond the vertex from their sites, which turns
+        // both the edges and the order they are met in around, so the edges
+        // link up as in the nearest-site diagram, but the rays cross the
+        // hull into it.
+        _ => {
+            let triangles = FarthestTriangulation::new(sites, &hull).triangles();
+            diagram.dualize(&triangles, S::from_f64(1.0), region)?;
+        }
+    }
+    Ok(diagram)
+}
+
+/// Fill in the diagram of sites on a line from `a` to `b`, whose cells are
+/// the two halves of the plane on either side of their bisector.
+fn split_plane<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    a: usize,
+    b: usize,
+    diagram: &mut Diagram<S>,
+    region: &R,
+) {
+    let (a_edge, b_edge) = diagram.add_edge(a, b);
+    let origin = (sites[a].clone() + sites[b].clone()) * S::from_f64(0.5);
+    // the cell of a lies on the side of b
+    let a_end = region.ray_end(&origin, &normal_vector(sites[b].clone() - sites[a].clone()));
+    let b_end = region.ray_end(&origin, &normal_vector(sites[a].clone() - sites[b].clone()));
+    diagram.add_vertex(a_end, a_edge);
+    diagram.add_vertex(b_end, b_edge);
+}
+
+/// The farthest-point Delaunay triangulation of the corners of a convex
+/// polygon.
+struct FarthestTriangulation<'a, S> {
+    sites: &'a [Point<S>],
+    /// The third corner of the counter-clockwise triangle left of each
+    /// directed edge
+    apex: HashMap<(usize, usize), usize>,
+}
+
+impl<'a, S: Scalar> FarthestTriangulation<'a, S> {
+    /// The triangulation of `hull`, the corners of the polygon
+    /// counter-clockwise. Following Chew, the corners are taken away in
+    /// random order and put back the other way round, each between the two
+    /// it was taken from, so that it only has to be flipped into place.
+    fn new(sites: &'a [Point<S>], hull: &[usize]) -> Self {
+        let n = hull.len();
+        let mut order = (0..n).collect::<Vec<_>>();
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        for idx in (1..n).rev() {
+            seed = splitmix(seed);
+            order.swap(idx, (seed % (idx as u64 + 1)) as usize);
+        }
+
+        let mut prev = (0..n).map(|idx| (idx + n - 1) % n).collect::<Vec<_>>();
+        let mut next = (0..n).map(|idx| (idx + 1) % n).collect::<Vec<_>>();
+        let mut taken = vec![];
+        for &idx in &order[3..] {
+            let (p, q) = (prev[idx], next[idx]);
+            next[p] = q;
+            prev[q] = p;
+            taken.push([p, idx, q]);
+        }
+
+        let mut res = Self {
+            sites,
+            apex: HashMap::new(),
+        };
+        let a = order[0];
+        res.add([hull[a], hull[next[a]], hull[next[next[a]]]]);
+        for &[p, idx, q] in taken.iter().rev() {
+            res.insert([hull[p], hull[idx], hull[q]]);
+        }
+        res
+    }
+
+    fn add(&mut self, [a, b, c]: [usize; 3]) {
+        self.apex.insert((a, b), c);
+        self.apex.insert((b, c), a);
+        self.apex.insert((c, a), b);
+    }
+
+    fn remove(&mut self, [a, b, c]: [usize; 3]) {
+        self.apex.remove(&(a, b));
+        self.apex.remove(&(b, c));
+        self.apex.remove(&(c, a));
+    }
+
+    /// Put `q` back between the corners `u` and `w` next to it, flipping
+    /// away every edge with a corner across it outside the circle through
+    /// the triangle of `q`.
+    fn insert(&mut self, [u, q, w]: [usize; 3]) {
+        let site = |v: usize| &self.sites[v];
+        self.add([u, q, w]);
+        let mut stack = vec![(w, u)];
+        while let Some((a, b)) = stack.pop() {
+            let Some(&x) = self.apex.get(&(b, a)) else {
+                continue;
+            };
+            if incircle(site(a), site(b), site(q), site(x)) != Ordering::Less {
+                continue;
+            }
+            self.remove([a, b, q]);
+            self.remove([b, a, x]);
+            self.add([a, x, q]);
+            self.add([x, b, q]);
+            stack.push((a, x));
+            stack.push((x, b));
+        }
+    }
+
+    /// The triangles, each counter-clockwise and listed once.
+    fn triangles(&self) -> Vec<[usize; 3]> {
+        let mut res = self
+            .apex
+            .iter()
+            .filter(|&(&(a, b), &c)| a < b && a < c)
+            .map(|(&(a, b), &c)| [a, b, c])
+            .collect::<Vec<_>>();
+        res.sort_unstable();
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordered_float::OrderedFloat;
+
+    use crate::{
+        geometry::{circumcenter, orient2d, polygon_area, ConvexPolygon},
+        locate::PointLocator,
+        test_utils::{bounding_box, check_diagram_by, point, random_sites, squared_distance},
+    };
+
+    use super::*;
+
+    #[test]
+    fn random_sites_get_cells_on_the_hull() {
+        let bounding_box = bounding_box();
+        for seed in 0..5 {
+            let mut sites = random_sites(500, 1000.0, seed);
+            let hull = convex_hull(&sites);
+            sites.push(sites[hull[0]]);
+            let diagram = farthest_point_diagram(&sites, &bounding_box).unwrap();
+            assert!(check_diagram_by(&sites, &diagram, 1e-9, |a, b| {
+                -squared_distance(a, b).to_f64()
+            }));
+            assert!(diagram.is_farthest_point());
+            assert_eq!(diagram.convex_hull(), hull);
+            assert_eq!(diagram.site_map()[500], hull[0]);
+
+            // a tree, with a ray across every edge of the hull
+            let ray_ends = (0..diagram.vertices().len())
+                .filter(|&v| diagram.is_ray_end(v))
+                .count();
+            assert_eq!(ray_ends, hull.len());
+            assert_eq!(
+                diagram.half_edges().len(),
+                2 * (diagram.vertices().len() - 1)
+            );
+
+            let cells = diagram.cell_polygons(&bounding_box);
+            // the cells of some corners lie entirely beyond the box
+            for (face, cell) in cells.iter().enumerate() {
+                assert!(cell.is_empty() || hull.contains(&face));
+            }
+            let area = cells.iter().map(|cell| polygon_area(cell).0).sum::<f64>();
+            assert!((area - 1e6).abs() < 1e-6);
+
+            // every point lies in the cell of the site it is farthest from
+            for p in random_sites(200, 1000.0, seed + 100) {
+                let farthest = (0..sites.len())
+                    .max_by_key(|&idx| squared_distance(&sites[idx], &p))
+                    .unwrap();
+                let cell = ConvexPolygon::new(cells[farthest].clone()).unwrap();
+                let dist = |q: &Point| (q.x - p.x).abs() + (q.y - p.y).abs();
+                assert!(cell.contains(&p) || cells[farthest].iter().any(|q| dist(q) < 1e-6));
+            }
+
+            // the dual triangles have circles around every site
+            let triangulation = diagram.delaunay_triangulation();
+            assert_eq!(triangulation.triangles().len(), hull.len() - 2);
+            for &[a, b, c] in triangulation.triangles() {
+                let [a, b, c] = [a, b, c].map(|v| &sites[v]);
+                assert_eq!(orient2d(a, b, c), Ordering::Greater);
+                assert!(sites.iter().all(|d| incircle(a, b, c, d) != Ordering::Less));
+            }
+        }
+    }
+
+    #[test]
+    fn smallest_enclosing_circle_lies_on_the_diagram() {
+        let bounding_box = bounding_box();
+        let sites = random_sites(100, 1000.0, 7);
+        let diagram = farthest_point_diagram(&sites, &bounding_box).unwrap();
+        // the vertex with the smallest circle around it, or the midpoint of
+        // an edge whose sites are farthest apart
+        let vertices = (0..diagram.vertices().len())
+            .filter(|&v| !diagram.is_ray_end(v))
+            .map(|v| {
+                let edge = diagram.vertex(v).incident_edge;
+                let site = &sites[diagram.half_edge(edge).face];
+                (
+                    diagram.vertex(v).point,
+                    squared_distance(site, &diagram.vertex(v).point),
+                )
+            });
+        let midpoints = diagram.half_edges().iter().filter_map(|edge| {
+            let (a, b) = (&sites[edge.face], &sites[diagram.half_edge(edge.twin).face]);
+            let mid = (*a + *b) * OrderedFloat(0.5);
+            let radius = squared_distance(a, &mid);
+            // only when no site lies outside the circle
+            sites
+                .iter()
+                .all(|site| squared_distance(site, &mid) <= radius * OrderedFloat(1.0 + 1e-12))
+                .then_some((mid, radius))
+        });
+        let (center, radius) = vertices
+            .chain(midpoints)
+            .min_by_key(|&(_, radius)| radius)
+            .unwrap();
+
+        // no circle around all sites is smaller, checked against those
+        // through three or two hull corners
+        let hull = diagram.convex_hull();
+        let encloses = |center: &Point, radius: OrderedFloat<f64>| {
+            sites
+                .iter()
+                .all(|site| squared_distance(site, center) <= radius * OrderedFloat(1.0 + 1e-9))
+        };
+        assert!(encloses(&center, radius));
+        for (i, &a) in hull.iter().enumerate() {
+            for &b in &hull[i + 1..] {
+                let mid = (sites[a] + sites[b]) * OrderedFloat(0.5);
+                let r = squared_distance(&sites[a], &mid);
+                assert!(!encloses(&mid, r) || r >= radius * OrderedFloat(1.0 - 1e-9));
+                for &c in hull {
+                    if let Some(p) = circumcenter(&sites[a], &sites[b], &sites[c]) {
+                        let r = squared_distance(&sites[a], &p);
+                        assert!(!encloses(&p, r) || r >= radius * OrderedFloat(1.0 - 1e-9));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cocircular_sites_meet_at_one_vertex() {
+        let bounding_box = bounding_box();
+        // the twelve points with integer coordinates on a circle of radius
+        // 5, scaled up, and some inside it
+        let mut sites = [
+            (5, 0),
+            (4, 3),
+            (3, 4),
+            (0, 5),
+            (-3, 4),
+            (-4, 3),
+            (-5, 0),
+            (-4, -3),
+            (-3, -4),
+            (0, -5),
+            (3, -4),
+            (4, -3),
+        ]
+        .map(|(x, y)| point(500.0 + 80.0 * x as f64, 500.0 + 80.0 * y as f64))
+        .to_vec();
+        sites.extend([point(500.0, 500.0), point(600.0, 450.0)]);
+        let diagram = farthest_point_diagram(&sites, &bounding_box).unwrap();
+        assert!(check_diagram_by(&sites, &diagram, 1e-12, |a, b| {
+            -squared_distance(a, b).to_f64()
+        }));
+        assert_eq!(diagram.vertices().len(), 13);
+        assert_eq!(diagram.half_edges().len(), 24);
+        let center = (0..13).find(|&v| !diagram.is_ray_end(v)).unwrap();
+        assert_eq!(diagram.vertex(center).point, point(500.0, 500.0));
+        assert_eq!(diagram.outgoing(center).len(), 12);
+        // each cell is the wedge opposite its site
+        for face in 0..12 {
+            assert_eq!(diagram.neighbours(face).len(), 2);
+        }
+        assert!(diagram.neighbours(12).is_empty());
+    }
+
+    #[test]
+    fn sites_on_a_line() {
+        let bounding_box = bounding_box();
+        let sites = [100.0, 900.0, 400.0, 900.0].map(|x| point(x, 300.0));
+        let diagram = farthest_point_diagram(&sites, &bounding_box).unwrap();
+        assert!(check_diagram_by(&sites, &diagram, 1e-12, |a, b| {
+            -squared_distance(a, b).to_f64()
+        }));
+        assert_eq!(diagram.convex_hull(), [0, 1]);
+        assert_eq!(
+            diagram.segments(),
+            [[point(500.0, 1000.0), point(500.0, 0.0)]]
+        );
+
+        let cells = diagram.cell_polygons(&bounding_box);
+        assert!(cells[0].iter().all(|p| p.x.0 >= 500.0));
+        assert!(cells[1].iter().all(|p| p.x.0 <= 500.0));
+        assert!(cells[2].is_empty() && cells[3].is_empty());
+        assert_eq!(polygon_area(&cells[0]).0, 5e5);
+
+        // a lone site is farthest from everywhere
+        let diagram = farthest_point_diagram(&sites[..1], &bounding_box).unwrap();
+        assert_eq!(diagram.cell_polygons(&bounding_box)[0].len(), 4);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bounding_box = bounding_box();
+        assert_eq!(
+            farthest_point_diagram(&[point(1.0, 2.0), point(f64::NAN, 2.0)], &bounding_box).err(),
+            Some(Error::NonFiniteSite(1))
+        );
+        assert_eq!(
+            farthest_point_diagram(&[point(-1.0, 2.0)], &bounding_box).err(),
+            Some(Error::SiteOutsideBoundingBox(0))
+        );
+        assert!(
+            farthest_point_diagram::<OrderedFloat<f64>, _>(&[], &bounding_box)
+                .unwrap()
+                .faces()
+                .is_empty()
+        );
+
+        let sites = random_sites(20, 1000.0, 3);
+        let mut diagram = farthest_point_diagram(&sites, &bounding_box).unwrap();
+        assert!(matches!(
+            diagram.insert_site(point(500.0, 500.0), &bounding_box),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            PointLocator::new(&diagram),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_vertices_are_exact() {
+        use num_rational::BigRational;
+
+        let sites = random_sites(200, 1000.0, 8)
+            .iter()
+            .map(|p| {
+                let round = |v: f64| BigRational::from_f64((v / 50.0).round() * 50.0);
+                Point::new(round(p.x.0), round(p.y.0))
+            })
+            .collect::<Vec<_>>();
+        let diagram = farthest_point_diagram(&sites, &bounding_box::<BigRational>()).unwrap();
+        for v in 0..diagram.vertices().len() {
+            if diagram.is_ray_end(v) {
+                continue;
+            }
+            let p = &diagram.vertex(v).point;
+            let farthest = sites.iter().map(|site| squared_distance(site, p)).max();
+            for edge in diagram.outgoing(v) {
+                let face = diagram.half_edge(edge).face;
+                assert_eq!(Some(squared_distance(&sites[face], p)), farthest);
+            }
+        }
+    }
+}
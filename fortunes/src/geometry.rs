@@ -1,30 +1,69 @@
 use std::{
-    cmp::min,
+    cmp::{min, Ordering},
+    collections::HashMap,
     fmt,
     ops::{Add, Mul, Sub},
 };
 
 use ordered_float::OrderedFloat;
 
+mod bisector;
+mod clip;
+mod metrics;
+mod polygon;
+mod predicates;
+mod scalar;
+
+pub(crate) use bisector::Bisector;
+pub use bisector::Metric;
+pub use clip::{ClipRegion, ConvexPolygon};
+pub use metrics::{
+    polygon_area, polygon_bounding_box, polygon_centroid, polygon_perimeter, polygon_second_moment,
+};
+pub use polygon::PolygonWithHoles;
+pub(crate) use polygon::{clip_cell, clip_labelled_cell, Cut};
+pub use scalar::Scalar;
+
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
-pub struct Point {
-    pub x: OrderedFloat<f64>,
-    pub y: OrderedFloat<f64>,
+pub struct Point<S = OrderedFloat<f64>> {
+    pub x: S,
+    pub y: S,
 }
 
-impl fmt::Debug for Point {
+impl<S: fmt::Display> fmt::Debug for Point<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({}, {})", self.x.into_inner(), self.y.into_inner())
+        write!(f, "({}, {})", self.x, self.y)
     }
 }
 
-impl Point {
-    pub fn new(x: OrderedFloat<f64>, y: OrderedFloat<f64>) -> Self {
+impl<S> Point<S> {
+    pub fn new(x: S, y: S) -> Self {
         Self { x, y }
     }
 }
 
-impl Add for Point {
+/// A site of a power diagram, see [`crate::power_diagram`]. Its power
+/// distance to a point `p` is `|p - point|^2 - weight`, so heavier sites
+/// reach further.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct WeightedSite<S = OrderedFloat<f64>> {
+    pub point: Point<S>,
+    pub weight: S,
+}
+
+impl<S: fmt::Display> fmt::Debug for WeightedSite<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} weighing {}", self.point, self.weight)
+    }
+}
+
+impl<S> WeightedSite<S> {
+    pub fn new(point: Point<S>, weight: S) -> Self {
+        Self { point, weight }
+    }
+}
+
+impl<S: Scalar> Add for Point<S> {
     type Output = Self;
 
     fn add(self, other: Self) -> Self::Output {
@@ -35,18 +74,18 @@ impl Add for Point {
     }
 }
 
-impl Add<&Point> for Point {
+impl<S: Scalar> Add<&Point<S>> for Point<S> {
     type Output = Self;
 
-    fn add(self, other: &Point) -> Self::Output {
+    fn add(self, other: &Point<S>) -> Self::Output {
         Point {
-            x: self.x + other.x,
-            y: self.y + other.y,
+            x: self.x + other.x.clone(),
+            y: self.y + other.y.clone(),
         }
     }
 }
 
-impl Sub for Point {
+impl<S: Scalar> Sub for Point<S> {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self::Output {
@@ -57,44 +96,39 @@ impl Sub for Point {
     }
 }
 
-impl Mul<OrderedFloat<f64>> for Point {
+impl<S: Scalar> Mul<S> for Point<S> {
     type Output = Self;
 
-    fn mul(self, rhs: OrderedFloat<f64>) -> Self::Output {
+    fn mul(self, rhs: S) -> Self::Output {
         Self {
-            x: self.x * rhs,
+            x: self.x * rhs.clone(),
             y: self.y * rhs,
         }
     }
 }
 
-impl Mul<OrderedFloat<f64>> for &Point {
-    type Output = Point;
+impl<S: Scalar> Mul<S> for &Point<S> {
+    type Output = Point<S>;
 
-    fn mul(self, rhs: OrderedFloat<f64>) -> Self::Output {
+    fn mul(self, rhs: S) -> Self::Output {
         Point {
-            x: self.x * rhs,
-            y: self.y * rhs,
+            x: self.x.clone() * rhs.clone(),
+            y: self.y.clone() * rhs,
         }
     }
 }
 
-pub type Segment = [Point; 2];
+pub type Segment<S = OrderedFloat<f64>> = [Point<S>; 2];
 
-pub struct BoundingBox {
-    x_min: OrderedFloat<f64>,
-    x_max: OrderedFloat<f64>,
-    y_min: OrderedFloat<f64>,
-    y_max: OrderedFloat<f64>,
+pub struct BoundingBox<S = OrderedFloat<f64>> {
+    x_min: S,
+    x_max: S,
+    y_min: S,
+    y_max: S,
 }
 
-impl BoundingBox {
-    pub fn new(
-        x_min: OrderedFloat<f64>,
-        x_max: OrderedFloat<f64>,
-        y_min: OrderedFloat<f64>,
-        y_max: OrderedFloat<f64>,
-    ) -> Self {
+impl<S: Scalar> BoundingBox<S> {
+    pub fn new(x_min: S, x_max: S, y_min: S, y_max: S) -> Self {
         Self {
             x_min,
             x_max,
@@ -102,20 +136,52 @@ impl BoundingBox {
             y_max,
         }
     }
+
+    /// Whether the box encloses no area, which includes boxes with
+    /// non-finite sides.
+    pub fn is_empty(&self) -> bool {
+        let finite = [&self.x_min, &self.x_max, &self.y_min, &self.y_max]
+            .iter()
+            .all(|c| c.is_finite());
+        !(finite && self.x_min < self.x_max && self.y_min < self.y_max)
+    }
+
+    pub fn diagonal(&self) -> S {
+        distance(
+            &Point::new(self.x_min.clone(), self.y_min.clone()),
+            &Point::new(self.x_max.clone(), self.y_max.clone()),
+        )
+    }
+
+    pub fn contains(&self, p: &Point<S>) -> bool {
+        self.x_min <= p.x && p.x <= self.x_max && self.y_min <= p.y && p.y <= self.y_max
+    }
+
+    /// The corners of the box in counter-clockwise order, starting at the
+    /// bottom left.
+    pub fn corners(&self) -> [Point<S>; 4] {
+        [
+            Point::new(self.x_min.clone(), self.y_min.clone()),
+            Point::new(self.x_max.clone(), self.y_min.clone()),
+            Point::new(self.x_max.clone(), self.y_max.clone()),
+            Point::new(self.x_min.clone(), self.y_max.clone()),
+        ]
+    }
 }
 
-pub fn point_on_arc_at_x(focus: &Point, yl: OrderedFloat<f64>, x: OrderedFloat<f64>) -> Point {
-    let xf = focus.x;
-    let yf = focus.y;
+pub fn point_on_arc_at_x<S: Scalar>(focus: &Point<S>, yl: S, x: S) -> Point<S> {
+    let two = S::from_f64(2.0);
+    let xf = focus.x.clone();
+    let yf = focus.y.clone();
 
-    let dx = x - xf;
-    let dx2 = dx * dx;
-    let dy = yf - yl;
+    let dx = x.clone() - xf.clone();
+    let dx2 = dx.clone() * dx;
+    let dy = yf.clone() - yl.clone();
 
-    if dy == 0.0 {
-        Point::new((focus.x + x) / 2.0, yl)
+    if dy == S::zero() {
+        Point::new((xf + x) / two, yl)
     } else {
-        Point::new(x, dx2 / (dy * 2.0) + (yf + yl) / 2.0)
+        Point::new(x, dx2 / (dy * two.clone()) + (yf + yl) / two)
     }
 
     // let y = if dy == 0.0 {
@@ -127,148 +193,458 @@ pub fn point_on_arc_at_x(focus: &Point, yl: OrderedFloat<f64>, x: OrderedFloat<f
     // Point::new(x, y)
 }
 
-pub fn normal_vector(point: Point) -> Point {
+pub fn normal_vector<S: Scalar>(point: Point<S>) -> Point<S> {
     Point::new(-point.y, point.x)
 }
 
-pub fn circumcenter(a: &Point, b: &Point, c: &Point) -> Point {
-    let x1 = a.x;
-    let y1 = a.y;
-    let x2 = b.x;
-    let y2 = b.y;
-    let x3 = c.x;
-    let y3 = c.y;
+/// Which way `a`, `b`, `c` turn: `Greater` for counter-clockwise, `Less` for
+/// clockwise and `Equal` when they are collinear. Exact for every input.
+pub fn orient2d<S: Scalar>(a: &Point<S>, b: &Point<S>, c: &Point<S>) -> Ordering {
+    S::orient2d(a, b, c)
+}
 
-    let c1 = x3 * x3 + y3 * y3 - x1 * x1 - y1 * y1;
-    let c2 = x3 * x3 + y3 * y3 - x2 * x2 - y2 * y2;
-    let a1 = (x1 - x3) * -2.;
-    let a2 = (x2 - x3) * -2.;
-    let b1 = (y1 - y3) * -2.;
-    let b2 = (y2 - y3) * -2.;
+/// Where `d` lies relative to the circle through `a`, `b` and `c`, which
+/// must turn counter-clockwise: `Greater` inside, `Less` outside and `Equal`
+/// on the circle. Exact for every input.
+pub fn incircle<S: Scalar>(a: &Point<S>, b: &Point<S>, c: &Point<S>, d: &Point<S>) -> Ordering {
+    S::incircle(a, b, c, d)
+}
+
+/// Where `d` lies relative to the point with equal power distance to `a`,
+/// `b` and `c`, which must turn counter-clockwise: `Greater` when its power
+/// distance to that point is smaller than theirs, `Less` when it is larger
+/// and `Equal` when they agree. With equal weights this is [`incircle`].
+/// Exact for every input.
+pub fn power_test<S: Scalar>(
+    a: &WeightedSite<S>,
+    b: &WeightedSite<S>,
+    c: &WeightedSite<S>,
+    d: &WeightedSite<S>,
+) -> Ordering {
+    S::power_test(a, b, c, d)
+}
+
+/// [`power_test`] for `d` on the line through `a` and `b`, which must be
+/// distinct: `Greater` when the power distance from some point of the line
+/// to `d` is smaller than to both `a` and `b`. With equal weights this is
+/// whether `d` lies strictly between them. Exact for every input.
+pub fn power_test_collinear<S: Scalar>(
+    a: &WeightedSite<S>,
+    b: &WeightedSite<S>,
+    d: &WeightedSite<S>,
+) -> Ordering {
+    S::power_test_collinear(a, b, d)
+}
+
+/// Compare the x coordinate of `p` with that of the breakpoint between the
+/// arcs of `l` (on the left) and `r` (on the right), with the sweep line
+/// passing through `p`. Both sites must lie on or above the sweep line.
+/// Exact for every input.
+pub fn compare_to_breakpoint<S: Scalar>(p: &Point<S>, l: &Point<S>, r: &Point<S>) -> Ordering {
+    S::compare_to_breakpoint(p, l, r)
+}
+
+/// [`compare_to_breakpoint`] in the sweep for a power diagram, where each arc
+/// belongs to a site lifted by `top` less its weight, see
+/// [`crate::power_diagram`]. `top` must be at least both weights. Exact for
+/// every input.
+pub fn compare_to_power_breakpoint<S: Scalar>(
+    p: &Point<S>,
+    l: &WeightedSite<S>,
+    r: &WeightedSite<S>,
+    top: &S,
+) -> Ordering {
+    S::compare_to_power_breakpoint(p, l, r, top)
+}
 
-    let numer = c1 * a2 - c2 * a1;
-    let denom = b1 * a2 - b2 * a1;
+/// Center of the circle through `a`, `b` and `c`, or `None` if they are
+/// collinear.
+pub fn circumcenter<S: Scalar>(a: &Point<S>, b: &Point<S>, c: &Point<S>) -> Option<Point<S>> {
+    equal_power_point(a, b, c, S::zero(), S::zero())
+}
+
+/// The point with equal power distance to `a`, `b` and `c`, or `None` if
+/// they are collinear. With equal weights this is [`circumcenter`].
+pub fn power_center<S: Scalar>(
+    a: &WeightedSite<S>,
+    b: &WeightedSite<S>,
+    c: &WeightedSite<S>,
+) -> Option<Point<S>> {
+    equal_power_point(
+        &a.point,
+        &b.point,
+        &c.point,
+        a.weight.clone() - b.weight.clone(),
+        a.weight.clone() - c.weight.clone(),
+    )
+}
 
-    if denom == 0.0 {
-        panic!("circle center does not exist");
+/// The point `p` with `|p - a|^2 = |p - b|^2 - b_shift = |p - c|^2 - c_shift`.
+fn equal_power_point<S: Scalar>(
+    a: &Point<S>,
+    b: &Point<S>,
+    c: &Point<S>,
+    b_shift: S,
+    c_shift: S,
+) -> Option<Point<S>> {
+    // work relative to a, which keeps the squares small and well conditioned
+    let b = b.clone() - a.clone();
+    let c = c.clone() - a.clone();
+
+    let denom = (b.x.clone() * c.y.clone() - b.y.clone() * c.x.clone()) * S::from_f64(2.0);
+    if denom == S::zero() {
+        return None;
     }
-    let y_cen = numer / denom;
+    let b2 = b.x.clone() * b.x.clone() + b.y.clone() * b.y.clone() + b_shift;
+    let c2 = c.x.clone() * c.x.clone() + c.y.clone() * c.y.clone() + c_shift;
+
+    let x_cen = (c.y * b2.clone() - b.y * c2.clone()) / denom.clone();
+    let y_cen = (b.x * c2 - c.x * b2) / denom;
+
+    Some(Point::new(x_cen, y_cen) + a)
+}
+
+/// Lowest y of the circle around `center` through `p`. For a large circle
+/// the radius is not subtracted from the height of the center, which would
+/// cancel out all the digits of the result.
+pub fn circle_bottom<S: Scalar>(center: &Point<S>, p: &Point<S>) -> S {
+    lifted_circle_bottom(center, p, S::zero())
+}
 
-    let x_cen = if a2 != 0.0 {
-        (c2 - b2 * y_cen) / a2
+/// [`circle_bottom`] for the arc of `site` lifted by `top` less its weight,
+/// the height of the sweep line when that arc passes over `center`.
+pub fn power_circle_bottom<S: Scalar>(center: &Point<S>, site: &WeightedSite<S>, top: &S) -> S {
+    lifted_circle_bottom(center, &site.point, top.clone() - site.weight.clone())
+}
+
+/// `center.y - sqrt(|center - p|^2 + lift)`, for a non-negative `lift`.
+fn lifted_circle_bottom<S: Scalar>(center: &Point<S>, p: &Point<S>, lift: S) -> S {
+    let v = center.clone() - p.clone();
+    let rest = v.x.clone() * v.x + lift;
+    let r = (rest.clone() + v.y.clone() * v.y.clone()).sqrt();
+    let drop = if v.y > S::zero() {
+        -rest / (v.y + r)
     } else {
-        (c1 - b1 * y_cen) / a1
+        v.y - r
     };
-
-    Point::new(x_cen, y_cen)
+    p.y.clone() + drop
 }
 
-pub fn intersection(ao: &Point, ad: &Point, bo: &Point, bd: &Point) -> Option<Point> {
-    let dx = bo.x - ao.x;
-    let dy = bo.y - ao.y;
-    let det = bd.x * ad.y - bd.y * ad.x;
-    if det == OrderedFloat(0.0) {
+pub fn intersection<S: Scalar>(
+    ao: &Point<S>,
+    ad: &Point<S>,
+    bo: &Point<S>,
+    bd: &Point<S>,
+) -> Option<Point<S>> {
+    let dx = bo.x.clone() - ao.x.clone();
+    let dy = bo.y.clone() - ao.y.clone();
+    let det = bd.x.clone() * ad.y.clone() - bd.y.clone() * ad.x.clone();
+    if det == S::zero() {
         return None;
     }
 
-    let u = (dy * bd.x - dx * bd.y) / det;
-    let v = (dy * ad.x - dx * ad.y) / det;
-    if u.signum() != v.signum() {
+    let u = (dy.clone() * bd.x.clone() - dx.clone() * bd.y.clone()) / det.clone();
+    let v = (dy * ad.x.clone() - dx * ad.y.clone()) / det;
+    // Both rays have to move forward to meet
+    if u < S::zero() || v < S::zero() {
         return None;
     }
 
     Some((ad * u) + ao)
 }
 
-pub fn distance(a: &Point, b: &Point) -> OrderedFloat<f64> {
-    let dx = a.x - b.x;
-    let dy = a.y - b.y;
-    OrderedFloat((dx * dx + dy * dy).sqrt())
+/// The part of `polygon` at least as close to `site` as to `other`, keeping
+/// the orientation of its vertices.
+pub fn clip_to_bisector<S: Scalar>(
+    polygon: &[Point<S>],
+    site: &Point<S>,
+    other: &Point<S>,
+) -> Vec<Point<S>> {
+    clip_to_half_plane(polygon, site, other, S::zero())
 }
 
-pub fn breakpoint_at_x(l: &Point, r: &Point, yl: OrderedFloat<f64>) -> OrderedFloat<f64> {
-    let ax = l.x;
-    let bx = r.x;
-    let ay = l.y;
-    let by = r.y;
+/// Like [`clip_to_bisector`], in power distance.
+pub fn clip_to_power_bisector<S: Scalar>(
+    polygon: &[Point<S>],
+    site: &WeightedSite<S>,
+    other: &WeightedSite<S>,
+) -> Vec<Point<S>> {
+    let shift = (other.weight.clone() - site.weight.clone()) * S::from_f64(0.5);
+    clip_to_half_plane(polygon, &site.point, &other.point, shift)
+}
 
-    // shift frames
-    let bx_s = bx - ax;
-    let ay_s = ay - yl;
-    let by_s = by - yl;
+/// The part of `polygon` where `(p - mid) . (other - site) + shift <= 0`,
+/// with `mid` halfway between `site` and `other`.
+fn clip_to_half_plane<S: Scalar>(
+    polygon: &[Point<S>],
+    site: &Point<S>,
+    other: &Point<S>,
+    shift: S,
+) -> Vec<Point<S>> {
+    // positive on the side of other
+    let mid = (site.clone() + other.clone()) * S::from_f64(0.5);
+    let normal = other.clone() - site.clone();
+    let side = |p: &Point<S>| {
+        (p.x.clone() - mid.x.clone()) * normal.x.clone()
+            + (p.y.clone() - mid.y.clone()) * normal.y.clone()
+            + shift.clone()
+    };
+
+    let zero = S::zero();
+    let mut res = vec![];
+    for (idx, a) in polygon.iter().enumerate() {
+        let b = &polygon[(idx + 1) % polygon.len()];
+        let (fa, fb) = (side(a), side(b));
+        if fa <= zero {
+            res.push(a.clone());
+        }
+        if (fa < zero && fb > zero) || (fa > zero && fb < zero) {
+            let t = fa.clone() / (fa - fb);
+            res.push((b.clone() - a.clone()) * t + a);
+        }
+    }
+    res
+}
 
-    let discrim = ay_s * by_s * ((ay_s - by_s) * (ay_s - by_s) + bx_s * bx_s);
-    let numer = ay_s * bx_s - discrim.sqrt();
-    let denom = ay_s - by_s;
+/// Indices of the corners of the convex hull of `points`, counter-clockwise
+/// from the leftmost, lowest one. Points on the hull between two corners are
+/// left out, and of repeated points only the first is used, so points on a
+/// line only give the two ends.
+pub fn convex_hull<S: Scalar>(points: &[Point<S>]) -> Vec<usize> {
+    let mut order = (0..points.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let (p, q) = (&points[a], &points[b]);
+        (&p.x, &p.y, a).cmp(&(&q.x, &q.y, b))
+    });
+    order.dedup_by(|later, earlier| points[*later] == points[*earlier]);
+    if order.len() < 3 {
+        return order;
+    }
 
-    let mut x_bp = if denom != 0.0 {
-        numer / denom
+    // Andrew's monotone chain, the lower half left to right and the upper
+    // one back
+    let mut hull: Vec<usize> = vec![];
+    let turns_left = |hull: &[usize], idx: usize| {
+        let [a, b] = [hull[hull.len() - 2], hull[hull.len() - 1]];
+        orient2d(&points[a], &points[b], &points[idx]) == Ordering::Greater
+    };
+    for &idx in &order {
+        while hull.len() >= 2 && !turns_left(&hull, idx) {
+            hull.pop();
+        }
+        hull.push(idx);
+    }
+    let lower_len = hull.len();
+    for &idx in order.iter().rev().skip(1) {
+        while hull.len() > lower_len && !turns_left(&hull, idx) {
+            hull.pop();
+        }
+        hull.push(idx);
+    }
+    // back at the start
+    hull.pop();
+    hull
+}
+
+pub fn distance<S: Scalar>(a: &Point<S>, b: &Point<S>) -> S {
+    let dx = a.x.clone() - b.x.clone();
+    let dy = a.y.clone() - b.y.clone();
+    (dx.clone() * dx + dy.clone() * dy).sqrt()
+}
+
+pub fn breakpoint_at_x<S: Scalar>(l: &Point<S>, r: &Point<S>, yl: S) -> S {
+    let site = |point: &Point<S>| WeightedSite::new(point.clone(), S::zero());
+    power_breakpoint_at_x(&site(l), &site(r), &S::zero(), yl)
+}
+
+/// [`breakpoint_at_x`] for the arcs of weighted sites, lifted by `top` less
+/// their weights as in [`compare_to_power_breakpoint`].
+pub fn power_breakpoint_at_x<S: Scalar>(
+    l: &WeightedSite<S>,
+    r: &WeightedSite<S>,
+    top: &S,
+    yl: S,
+) -> S {
+    let ax = l.point.x.clone();
+    let bx = r.point.x.clone();
+    let ay = l.point.y.clone();
+    let by = r.point.y.clone();
+    let al = top.clone() - l.weight.clone();
+    let bl = top.clone() - r.weight.clone();
+
+    // shift frames
+    let bx_s = bx - ax.clone();
+    let ay_s = ay - yl.clone();
+    let by_s = by - yl;
+    let denom = ay_s.clone() - by_s.clone();
+
+    let mut x_bp = if denom != S::zero() {
+        let discrim = ay_s.clone()
+            * by_s.clone()
+            * (denom.clone() * denom.clone() + bx_s.clone() * bx_s.clone())
+            + denom.clone() * (by_s * al - ay_s.clone() * bl);
+        (ay_s * bx_s - discrim.sqrt()) / denom
     } else {
-        bx_s / 2.
+        (bx_s.clone() * bx_s.clone() + bl - al) / (bx_s * S::from_f64(2.))
     };
-    x_bp += ax; // shift back to original frame
+    x_bp = x_bp + ax; // shift back to original frame
 
     x_bp
 }
 
-pub fn bounded_segment(origin: &Point, direction: &Point, bounding_box: &BoundingBox) -> Segment {
-    let x_min = bounding_box.x_min;
-    let x_max = bounding_box.x_max;
-    let y_min = bounding_box.y_min;
-    let y_max = bounding_box.y_max;
+/// Where the power bisector of `a` and `b`, which must lie apart, crosses
+/// the line through them. With equal weights this is their midpoint.
+pub fn bisector_point<S: Scalar>(a: &WeightedSite<S>, b: &WeightedSite<S>) -> Point<S> {
+    let span = b.point.clone() - a.point.clone();
+    let length = span.x.clone() * span.x.clone() + span.y.clone() * span.y.clone();
+    let shift = (a.weight.clone() - b.weight.clone()) / (length * S::from_f64(2.0));
+    (a.point.clone() + b.point.clone()) * S::from_f64(0.5) + span * shift
+}
+
+/// Candidate neighbours of a site taken on together, nearest first.
+const BATCH: usize = 32;
+
+/// Hand the sites of `candidates`, paired with how close they come, to
+/// `visit` nearest first until it returns `false`. Only the batches it gets
+/// to are sorted.
+pub(crate) fn nearest_first<K: Ord>(
+    candidates: &mut [(K, usize)],
+    mut visit: impl FnMut(&K, usize) -> bool,
+) {
+    let mut rest = candidates;
+    while !rest.is_empty() {
+        let len = rest.len().min(BATCH);
+        if len < rest.len() {
+            rest.select_nth_unstable(len);
+        }
+        let (batch, tail) = rest.split_at_mut(len);
+        batch.sort_unstable();
+        for (key, t) in batch.iter() {
+            if !visit(key, *t) {
+                return;
+            }
+        }
+        rest = tail;
+    }
+}
+
+/// Map every site to the first site in `sites` lying within `epsilon` of
+/// it. Sites mapped to themselves are the ones that survive deduplication.
+pub fn dedup_sites<S: Scalar>(sites: &[Point<S>], epsilon: S) -> Vec<usize> {
+    if epsilon <= S::zero() {
+        let mut first = HashMap::new();
+        return sites
+            .iter()
+            .enumerate()
+            .map(|(idx, site)| *first.entry(site).or_insert(idx))
+            .collect();
+    }
 
-    let x = origin.x;
-    let y = origin.y;
-    let dx = direction.x;
-    let dy = direction.y;
+    // bucket survivors in a grid of epsilon sized cells, so only the
+    // neighbouring cells need to be searched
+    let cell = |p: &Point<S>| {
+        (
+            (p.x.to_f64() / epsilon.to_f64()).floor() as i64,
+            (p.y.to_f64() / epsilon.to_f64()).floor() as i64,
+        )
+    };
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    let mut res = Vec::with_capacity(sites.len());
+    for (idx, site) in sites.iter().enumerate() {
+        let (cx, cy) = cell(site);
+        let survivor = (cx - 1..=cx + 1)
+            .flat_map(|x| (cy - 1..=cy + 1).map(move |y| (x, y)))
+            .filter_map(|key| grid.get(&key))
+            .flatten()
+            .copied()
+            .filter(|&other| distance(site, &sites[other]) <= epsilon)
+            .min();
+        match survivor {
+            Some(other) => res.push(other),
+            None => {
+                grid.entry((cx, cy)).or_default().push(idx);
+                res.push(idx);
+            }
+        }
+    }
+    res
+}
 
-    let cx: OrderedFloat<f64> = if dx == OrderedFloat(0.0) {
-        0.0.into()
-    } else if dx < OrderedFloat(0.0) {
-        (x_min - x) / dx
+pub fn bounded_segment<S: Scalar>(
+    origin: &Point<S>,
+    direction: &Point<S>,
+    bounding_box: &BoundingBox<S>,
+) -> Segment<S> {
+    let x_min = bounding_box.x_min.clone();
+    let x_max = bounding_box.x_max.clone();
+    let y_min = bounding_box.y_min.clone();
+    let y_max = bounding_box.y_max.clone();
+
+    let x = origin.x.clone();
+    let y = origin.y.clone();
+    let dx = direction.x.clone();
+    let dy = direction.y.clone();
+    let zero = S::zero();
+
+    let cx = if dx == zero {
+        zero.clone()
+    } else if dx < zero {
+        (x_min.clone() - x.clone()) / dx.clone()
     } else {
-        (x_max - x) / dx
+        (x_max.clone() - x.clone()) / dx.clone()
     };
 
-    let cy: OrderedFloat<f64> = if dy == OrderedFloat(0.0) {
-        0.0.into()
-    } else if dy < OrderedFloat(0.0) {
-        (y_min - y) / dy
+    let cy = if dy == zero {
+        zero.clone()
+    } else if dy < zero {
+        (y_min.clone() - y.clone()) / dy.clone()
     } else {
-        (y_max - y) / dy
+        (y_max.clone() - y.clone()) / dy.clone()
     };
 
-    let c = if dx == OrderedFloat(0.0) {
-        cy
-    } else if dy == OrderedFloat(0.0) {
-        cx
+    let c = if dx == zero {
+        cy.clone()
+    } else if dy == zero {
+        cx.clone()
     } else {
-        min(cx, cy)
+        min(cx.clone(), cy.clone())
     };
-    let destination = Point {
-        x: x + c * dx,
-        y: y + c * dy,
+    // an origin already past the box is not extended any further
+    if c <= zero {
+        return [origin.clone(), origin.clone()];
+    }
+
+    // land exactly on the side of the box that was hit
+    let mut destination = Point {
+        x: x + c.clone() * dx.clone(),
+        y: y + c.clone() * dy.clone(),
     };
+    if dx != zero && c == cx {
+        destination.x = if dx < zero { x_min } else { x_max };
+    }
+    if dy != zero && c == cy {
+        destination.y = if dy < zero { y_min } else { y_max };
+    }
 
-    [*origin, destination]
+    [origin.clone(), destination]
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::test_utils::compare_segments;
+    use crate::test_utils::{bounding_box, compare_segments};
 
     use super::*;
 
     #[test]
     fn bounding_box_vertical() {
-        let bbox = BoundingBox::new(0.0.into(), 1000.0.into(), 0.0.into(), 1000.0.into());
+        let bbox = bounding_box();
 
-        let origin = Point::new(500.0.into(), 500.0.into());
-        let direction = Point::new(0.0.into(), 500.0.into());
+        let origin = Point::new(OrderedFloat(500.0), OrderedFloat(500.0));
+        let direction = Point::new(OrderedFloat(0.0), OrderedFloat(500.0));
 
         let gold = [
-            Point::new(500.0.into(), 500.0.into()),
-            Point::new(500.0.into(), 1000.0.into()),
+            Point::new(OrderedFloat(500.0), OrderedFloat(500.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(1000.0)),
         ];
 
         let seg = bounded_segment(&origin, &direction, &bbox);
@@ -278,14 +654,14 @@ mod tests {
 
     #[test]
     fn bounding_box_vertical_neg_0() {
-        let bbox = BoundingBox::new(0.0.into(), 1000.0.into(), 0.0.into(), 1000.0.into());
+        let bbox = bounding_box();
 
-        let origin = Point::new(500.0.into(), 500.0.into());
-        let direction = Point::new((-0.0).into(), 500.0.into());
+        let origin = Point::new(OrderedFloat(500.0), OrderedFloat(500.0));
+        let direction = Point::new(OrderedFloat(-0.0), OrderedFloat(500.0));
 
         let gold = [
-            Point::new(500.0.into(), 500.0.into()),
-            Point::new(500.0.into(), 1000.0.into()),
+            Point::new(OrderedFloat(500.0), OrderedFloat(500.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(1000.0)),
         ];
 
         let seg = bounded_segment(&origin, &direction, &bbox);
@@ -295,18 +671,61 @@ mod tests {
 
     #[test]
     fn bounding_box_vertical_neg_both() {
-        let bbox = BoundingBox::new(0.0.into(), 1000.0.into(), 0.0.into(), 1000.0.into());
+        let bbox = bounding_box();
 
-        let origin = Point::new(750.0.into(), 500.0.into());
-        let direction = Point::new((-0.0).into(), (-500.0).into());
+        let origin = Point::new(OrderedFloat(750.0), OrderedFloat(500.0));
+        let direction = Point::new(OrderedFloat(-0.0), OrderedFloat(-500.0));
 
         let gold = [
-            Point::new(750.0.into(), 500.0.into()),
-            Point::new(750.0.into(), 0.0.into()),
+            Point::new(OrderedFloat(750.0), OrderedFloat(500.0)),
+            Point::new(OrderedFloat(750.0), OrderedFloat(0.0)),
         ];
 
         let seg = bounded_segment(&origin, &direction, &bbox);
 
         assert!(compare_segments(&gold, &seg));
     }
+
+    #[test]
+    fn dedup_exact_and_epsilon() {
+        let sites = [
+            Point::new(OrderedFloat(0.0), OrderedFloat(0.0)),
+            Point::new(OrderedFloat(1.0), OrderedFloat(0.0)),
+            Point::new(OrderedFloat(-0.0), OrderedFloat(0.0)),
+            Point::new(OrderedFloat(1.05), OrderedFloat(0.0)),
+            Point::new(OrderedFloat(0.95), OrderedFloat(0.0)),
+            Point::new(OrderedFloat(2.0), OrderedFloat(0.0)),
+        ];
+
+        assert_eq!(
+            dedup_sites(&sites, OrderedFloat(0.0)),
+            vec![0, 1, 0, 3, 4, 5]
+        );
+        assert_eq!(
+            dedup_sites(&sites, OrderedFloat(0.1)),
+            vec![0, 1, 0, 1, 1, 5]
+        );
+    }
+
+    #[test]
+    fn convex_hull_corners() {
+        let points = [
+            (1.0, 1.0),
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 2.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 0.0),
+            (1.0, 0.5),
+        ]
+        .map(|(x, y)| Point::new(OrderedFloat(x), OrderedFloat(y)));
+        assert_eq!(convex_hull(&points), vec![1, 2, 3, 5]);
+
+        let line = [(2.0, 2.0), (0.0, 0.0), (1.0, 1.0), (3.0, 3.0)]
+            .map(|(x, y)| Point::new(OrderedFloat(x), OrderedFloat(y)));
+        assert_eq!(convex_hull(&line), vec![1, 3]);
+        assert_eq!(convex_hull(&line[..1]), vec![0]);
+        assert!(convex_hull::<OrderedFloat<f64>>(&[]).is_empty());
+    }
 }
@@ -0,0 +1,374 @@
+use std::cmp::Ordering;
+
+use super::{polygon::Cut, Point, Scalar};
+
+/// How the distance between two points is measured.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Metric {
+    #[default]
+    Euclidean,
+    /// The L1 distance `|dx| + |dy|`
+    Manhattan,
+    /// The L∞ distance `max(|dx|, |dy|)`
+    Chebyshev,
+}
+
+impl Metric {
+    pub fn distance<S: Scalar>(&self, a: &Point<S>, b: &Point<S>) -> S {
+        let dx = abs(a.x.clone() - b.x.clone());
+        let dy = abs(a.y.clone() - b.y.clone());
+        match self {
+            Metric::Euclidean => (dx.clone() * dx + dy.clone() * dy).sqrt(),
+            Metric::Manhattan => dx + dy,
+            Metric::Chebyshev => dx.max(dy),
+        }
+    }
+}
+
+/// The boundary of the points closer to `site` than to `other` under a
+/// metric, a polyline running with the site on its left.
+///
+/// Under L1 two sites as far apart horizontally as vertically are at the same
+/// distance from whole quadrants of the plane, and under L∞ so are two sites
+/// on a horizontal or vertical line. Such ties go to the site closer in plain
+/// distance, which splits the quadrants along the plain bisector, and makes
+/// the bisector a straight line there.
+pub(crate) struct Bisector<S> {
+    /// The sites, in the coordinates of [`Self::inner`]
+    site: Point<S>,
+    other: Point<S>,
+    metric: Metric,
+    /// Where the bisector bends, in order, and in the same coordinates
+    corners: Vec<Point<S>>,
+    /// Which way the bisector runs: first along `-direction` towards the
+    /// first corner, and along `direction` after the last
+    direction: Point<S>,
+}
+
+impl<S: Scalar> Bisector<S> {
+    pub fn new(site: &Point<S>, other: &Point<S>, metric: Metric) -> Self {
+        let (site, other) = match metric {
+            Metric::Chebyshev => (turn(site), turn(other)),
+            _ => (site.clone(), other.clone()),
+        };
+        let (corners, direction) = match metric {
+            Metric::Euclidean => (vec![], normal(&(other.clone() - site.clone()))),
+            _ => manhattan_corners(&site, &other),
+        };
+        Self {
+            site,
+            other,
+            metric,
+            corners,
+            direction,
+        }
+    }
+
+    /// On which side `p` lies: [`Ordering::Less`] when closer to the site.
+    pub fn side(&self, p: &Point<S>) -> Ordering {
+        self.inner_side(&self.inner(p))
+    }
+
+    /// `p` in the coordinates the bisector is worked out in. Under L∞ these
+    /// are turned by 45 degrees and scaled by sqrt 2, which takes L∞
+    /// distances to L1 distances twice as large, so that the kinks of the
+    /// distances run along the axes under both metrics, and points split at
+    /// them lie on them exactly.
+    fn inner(&self, p: &Point<S>) -> Point<S> {
+        match self.metric {
+            Metric::Chebyshev => turn(p),
+            _ => p.clone(),
+        }
+    }
+
+    /// The inverse of [`Self::inner`].
+    fn outer(&self, p: Point<S>) -> Point<S> {
+        match self.metric {
+            Metric::Chebyshev => {
+                let half = S::from_f64(0.5);
+                Point::new(
+                    (p.x.clone() + p.y.clone()) * half.clone(),
+                    (p.y - p.x) * half,
+                )
+            }
+            _ => p,
+        }
+    }
+
+    /// The point `t` of the way from `a` to `b`, where the segment crosses
+    /// the line through `value` on the vertical or horizontal axis of
+    /// [`Self::inner`], placed on the line exactly where it can be.
+    fn on_kink(&self, a: &Point<S>, b: &Point<S>, t: S, value: &S, vertical: bool) -> Point<S> {
+        let (ia, ib) = (self.inner(a), self.inner(b));
+        let p = (ib - ia.clone()) * t + &ia;
+        let p = if vertical {
+            Point::new(value.clone(), p.y)
+        } else {
+            Point::new(p.x, value.clone())
+        };
+        if self.metric != Metric::Chebyshev {
+            return p;
+        }
+        // the line is x - y = value when vertical and x + y = value
+        // otherwise, which a segment along an axis meets right away
+        let sign = S::from_f64(if vertical { 1.0 } else { -1.0 });
+        if a.x == b.x {
+            Point::new(a.x.clone(), (a.x.clone() - value.clone()) * sign)
+        } else if a.y == b.y {
+            Point::new(value.clone() + a.y.clone() * sign, a.y.clone())
+        } else {
+            self.outer(p)
+        }
+    }
+
+    fn inner_side(&self, p: &Point<S>) -> Ordering {
+        self.difference(p)
+            .cmp(&S::zero())
+            .then_with(|| self.plain_difference(p).cmp(&S::zero()))
+    }
+
+    /// A multiple of how much closer `p` is to the site than to the other
+    /// one, under the metric, leaving plain distances to
+    /// [`Self::plain_difference`].
+    fn difference(&self, p: &Point<S>) -> S {
+        match self.metric {
+            Metric::Euclidean => S::zero(),
+            // in parts, so that ties come out exactly zero in floating point
+            _ => gap(&p.x, &self.site.x, &self.other.x) + gap(&p.y, &self.site.y, &self.other.y),
+        }
+    }
+
+    /// A multiple of the difference in squared plain distance from `p` to
+    /// the site and to the other one, which unlike the distances is linear.
+    fn plain_difference(&self, p: &Point<S>) -> S {
+        let mid = (self.site.clone() + self.other.clone()) * S::from_f64(0.5);
+        dot(
+            &(p.clone() - mid),
+            &(self.other.clone() - self.site.clone()),
+        )
+    }
+}
+
+impl<S: Scalar> Cut<S> for Bisector<S> {
+    fn kept(&self, p: &Point<S>) -> bool {
+        self.side(p) == Ordering::Less
+    }
+
+    /// Splits where the segment crosses the lines through the sites along
+    /// the axes, at which the distances have kinks, so that they change
+    /// linearly along every piece.
+    fn splits(&self, a: &Point<S>, b: &Point<S>) -> Vec<Point<S>> {
+        if self.metric == Metric::Euclidean {
+            return vec![];
+        }
+        let (ia, ib) = (self.inner(a), self.inner(b));
+        let mut res = vec![];
+        for (value, vertical) in [
+            (&self.site.x, true),
+            (&self.other.x, true),
+            (&self.site.y, false),
+            (&self.other.y, false),
+        ] {
+            let (from, to) = if vertical {
+                (&ia.x, &ib.x)
+            } else {
+                (&ia.y, &ib.y)
+            };
+            if (from < value && value < to) || (to < value && value < from) {
+                let t = (value.clone() - from.clone()) / (to.clone() - from.clone());
+                res.push((t.clone(), self.on_kink(a, b, t, value, vertical)));
+            }
+        }
+        res.sort_by(|x, y| x.0.cmp(&y.0));
+        res.dedup_by(|later, earlier| later.0 == earlier.0);
+        res.into_iter().map(|(_, p)| p).collect()
+    }
+
+    fn crossing(&self, a: &Point<S>, b: &Point<S>) -> Point<S> {
+        let (ia, ib) = (self.inner(a), self.inner(b));
+        if self.inner_side(&ia) == Ordering::Equal {
+            return a.clone();
+        }
+        if self.inner_side(&ib) == Ordering::Equal {
+            return b.clone();
+        }
+        let (mut fa, mut fb) = (self.difference(&ia), self.difference(&ib));
+        if fa == fb {
+            // both lie where the metric ties
+            (fa, fb) = (self.plain_difference(&ia), self.plain_difference(&ib));
+        }
+        // interpolated in the original coordinates, so that crossings of
+        // a segment along an axis stay on it exactly
+        let t = fa.clone() / (fa - fb);
+        (b.clone() - a.clone()) * t + a
+    }
+
+    fn along(&self, p: &Point<S>) -> S {
+        dot(&self.inner(p), &self.direction)
+    }
+
+    fn corners(&self, from: &S, to: &S) -> Vec<Point<S>> {
+        self.corners
+            .iter()
+            .filter(|corner| {
+                let along = dot(corner, &self.direction);
+                *from < along && along < *to
+            })
+            .map(|corner| self.outer(corner.clone()))
+            .collect()
+    }
+}
+
+/// The corners of the L1 bisector of `site` and `other`, and the direction
+/// it runs in with the site on its left.
+fn manhattan_corners<S: Scalar>(site: &Point<S>, other: &Point<S>) -> (Vec<Point<S>>, Point<S>) {
+    let d = other.clone() - site.clone();
+    let (adx, ady) = (abs(d.x.clone()), abs(d.y.clone()));
+    let zero = S::zero();
+    let half = S::from_f64(0.5);
+    let one = S::from_f64(1.0);
+    let sign = |v: &S| if *v > zero { one.clone() } else { -one.clone() };
+    match adx.cmp(&ady) {
+        Ordering::Equal => (vec![], normal(&d)),
+        Ordering::Greater => {
+            // vertical beyond the sites, and diagonal between them, where
+            // |x - site.x| - |x - other.x| makes up for the difference in y
+            let corner = |y: &S| {
+                let k = abs(y.clone() - other.y.clone()) - abs(y.clone() - site.y.clone());
+                let k = if d.x > zero { k } else { -k };
+                Point::new(
+                    (site.x.clone() + other.x.clone() + k) * half.clone(),
+                    y.clone(),
+                )
+            };
+            let direction = Point::new(zero.clone(), sign(&d.x));
+            ordered(vec![corner(&site.y), corner(&other.y)], direction)
+        }
+        Ordering::Less => {
+            let corner = |x: &S| {
+                let k = abs(x.clone() - other.x.clone()) - abs(x.clone() - site.x.clone());
+                let k = if d.y > zero { k } else { -k };
+                Point::new(
+                    x.clone(),
+                    (site.y.clone() + other.y.clone() + k) * half.clone(),
+                )
+            };
+            let direction = Point::new(-sign(&d.y), zero.clone());
+            ordered(vec![corner(&site.x), corner(&other.x)], direction)
+        }
+    }
+}
+
+/// `corners` sorted along `direction`, or none if they coincide, when the
+/// bisector is straight.
+fn ordered<S: Scalar>(
+    mut corners: Vec<Point<S>>,
+    direction: Point<S>,
+) -> (Vec<Point<S>>, Point<S>) {
+    corners.sort_by_key(|corner| dot(corner, &direction));
+    corners.dedup();
+    if corners.len() == 1 {
+        corners.clear();
+    }
+    (corners, direction)
+}
+
+/// `|v - s| - |v - t|`, which is just `s - t` or `t - s` beyond both.
+fn gap<S: Scalar>(v: &S, s: &S, t: &S) -> S {
+    let (lo, hi) = if s < t { (s, t) } else { (t, s) };
+    if v <= lo {
+        s.clone() - t.clone()
+    } else if v >= hi {
+        t.clone() - s.clone()
+    } else {
+        abs(v.clone() - s.clone()) - abs(v.clone() - t.clone())
+    }
+}
+
+/// `p` turned counter-clockwise by 45 degrees and scaled by sqrt 2.
+fn turn<S: Scalar>(p: &Point<S>) -> Point<S> {
+    Point::new(p.x.clone() - p.y.clone(), p.x.clone() + p.y.clone())
+}
+
+/// `v` turned counter-clockwise by a right angle.
+fn normal<S: Scalar>(v: &Point<S>) -> Point<S> {
+    Point::new(-v.y.clone(), v.x.clone())
+}
+
+fn dot<S: Scalar>(a: &Point<S>, b: &Point<S>) -> S {
+    a.x.clone() * b.x.clone() + a.y.clone() * b.y.clone()
+}
+
+fn abs<S: Scalar>(v: S) -> S {
+    if v < S::zero() {
+        -v
+    } else {
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordered_float::OrderedFloat;
+
+    use crate::test_utils::point;
+
+    use super::*;
+
+    /// Where `bisector` bends, in order.
+    fn bends(bisector: &Bisector<OrderedFloat<f64>>) -> Vec<Point> {
+        let corners = bisector.corners.iter().cloned();
+        corners.map(|p| bisector.outer(p)).collect()
+    }
+
+    #[test]
+    fn distances() {
+        let (a, b) = (point(1.0, 2.0), point(4.0, -2.0));
+        assert_eq!(Metric::Euclidean.distance(&a, &b).0, 5.0);
+        assert_eq!(Metric::Manhattan.distance(&a, &b).0, 7.0);
+        assert_eq!(Metric::Chebyshev.distance(&a, &b).0, 4.0);
+    }
+
+    #[test]
+    fn bends_and_sides() {
+        let (site, other) = (point(0.0, 0.0), point(4.0, 1.0));
+        let manhattan = Bisector::new(&site, &other, Metric::Manhattan);
+        assert_eq!(bends(&manhattan), [point(2.5, 0.0), point(1.5, 1.0)]);
+        // the same bends from the other side, running the other way
+        let back = Bisector::new(&other, &site, Metric::Manhattan);
+        assert_eq!(bends(&back), [point(1.5, 1.0), point(2.5, 0.0)]);
+        let chebyshev = Bisector::new(&site, &other, Metric::Chebyshev);
+        assert_eq!(bends(&chebyshev), [point(2.0, -1.0), point(2.0, 2.0)]);
+
+        for (metric, bisector, far) in [
+            (Metric::Manhattan, &manhattan, point(2.5, -10.0)),
+            (Metric::Chebyshev, &chebyshev, point(5.0, -4.0)),
+        ] {
+            assert_eq!(bisector.side(&point(1.0, 0.5)), Ordering::Less);
+            assert_eq!(bisector.side(&point(3.0, 0.5)), Ordering::Greater);
+            assert!(bends(bisector)
+                .iter()
+                .chain([&far])
+                .all(|p| metric.distance(p, &site) == metric.distance(p, &other)));
+        }
+    }
+
+    #[test]
+    fn splits_lie_on_the_kinks() {
+        let bisector = Bisector::new(&point(0.0, 0.0), &point(3.0, 1.0), Metric::Manhattan);
+        let splits = bisector.splits(&point(-1.0, -0.3), &point(5.0, 2.0));
+        assert_eq!(splits.len(), 4);
+        assert!(splits[..2].iter().all(|p| p.x.0 == 0.0 || p.y.0 == 0.0));
+        assert!(splits[2..].iter().all(|p| p.x.0 == 3.0 || p.y.0 == 1.0));
+    }
+
+    #[test]
+    fn ties_split_along_the_plain_bisector() {
+        let bisector = Bisector::new(&point(0.0, 0.0), &point(2.0, 2.0), Metric::Manhattan);
+        assert!(bisector.corners.is_empty());
+        // equally far under L1, but closer to the site in plain distance
+        assert_eq!(bisector.side(&point(-5.0, 3.0)), Ordering::Less);
+        assert_eq!(bisector.side(&point(-1.0, 6.0)), Ordering::Greater);
+        assert_eq!(bisector.side(&point(-4.0, 6.0)), Ordering::Equal);
+    }
+}
@@ -0,0 +1,248 @@
+use std::cmp::{max, min, Ordering};
+
+use ordered_float::OrderedFloat;
+
+use super::{bounded_segment, orient2d, BoundingBox, Point, Scalar, Segment};
+use crate::Error;
+
+/// A convex area the diagram is cut off at.
+///
+/// Rays left over at the end of the sweep are extended up to its boundary,
+/// and [`crate::diagram::Diagram::cell_polygons`] and
+/// [`crate::diagram::Diagram::clipped_segments`] cut the cells and edges
+/// down to it.
+pub trait ClipRegion<S: Scalar> {
+    /// The corners of the region in counter-clockwise order.
+    fn vertices(&self) -> Vec<Point<S>>;
+
+    /// Whether the region encloses no area.
+    fn is_empty(&self) -> bool;
+
+    fn contains(&self, p: &Point<S>) -> bool {
+        let vertices = self.vertices();
+        let inside = edges(&vertices).all(|(a, b)| orient2d(a, b, p) != Ordering::Less);
+        inside
+    }
+
+    /// Where the ray from `origin` along `direction` leaves the region, or
+    /// `origin` itself when the ray already left it there.
+    fn ray_end(&self, origin: &Point<S>, direction: &Point<S>) -> Point<S> {
+        let vertices = self.vertices();
+        let zero = S::zero();
+        let exit = edges(&vertices)
+            .filter_map(|(a, b)| {
+                let (num, den) = crossing(a, b, origin, direction);
+                (den > zero).then(|| -num / den)
+            })
+            .min();
+        match exit {
+            Some(t) if t > zero => direction * t + origin,
+            _ => origin.clone(),
+        }
+    }
+
+    /// The part of `segment` inside the region, if any.
+    fn clip_segment(&self, segment: &Segment<S>) -> Option<Segment<S>> {
+        let [a, b] = segment;
+        let direction = b.clone() - a.clone();
+        let vertices = self.vertices();
+        let zero = S::zero();
+        let one = S::from_f64(1.0);
+
+        // Cyrus-Beck: narrow the range of the segment down edge by edge
+        let (mut lo, mut hi) = (zero.clone(), one.clone());
+        for (u, v) in edges(&vertices) {
+            let (num, den) = crossing(u, v, a, &direction);
+            match den.cmp(&zero) {
+                Ordering::Equal if num > zero => return None,
+                Ordering::Equal => {}
+                Ordering::Less => lo = max(lo, -num / den),
+                Ordering::Greater => hi = min(hi, -num / den),
+            }
+        }
+        if lo > hi {
+            return None;
+        }
+
+        let at = |t: S| {
+            if t == zero {
+                a.clone()
+            } else if t == one {
+                b.clone()
+            } else {
+                &direction * t + a
+            }
+        };
+        Some([at(lo), at(hi)])
+    }
+}
+
+/// Consecutive pairs of `vertices`, wrapping around.
+fn edges<S>(vertices: &[Point<S>]) -> impl Iterator<Item = (&Point<S>, &Point<S>)> {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .take(vertices.len())
+}
+
+/// How far `origin` lies outside the edge from `a` to `b`, and how fast
+/// moving along `direction` takes it further out, both scaled by the length
+/// of the edge.
+fn crossing<S: Scalar>(
+    a: &Point<S>,
+    b: &Point<S>,
+    origin: &Point<S>,
+    direction: &Point<S>,
+) -> (S, S) {
+    // outward normal of a counter-clockwise edge
+    let nx = b.y.clone() - a.y.clone();
+    let ny = a.x.clone() - b.x.clone();
+    let num = nx.clone() * (origin.x.clone() - a.x.clone())
+        + ny.clone() * (origin.y.clone() - a.y.clone());
+    let den = nx * direction.x.clone() + ny * direction.y.clone();
+    (num, den)
+}
+
+impl<S: Scalar> ClipRegion<S> for BoundingBox<S> {
+    fn vertices(&self) -> Vec<Point<S>> {
+        self.corners().to_vec()
+    }
+
+    fn is_empty(&self) -> bool {
+        BoundingBox::is_empty(self)
+    }
+
+    fn contains(&self, p: &Point<S>) -> bool {
+        BoundingBox::contains(self, p)
+    }
+
+    fn ray_end(&self, origin: &Point<S>, direction: &Point<S>) -> Point<S> {
+        let [_, end] = bounded_segment(origin, direction, self);
+        end
+    }
+}
+
+/// A convex polygon to clip to, e.g. a rotated rectangle or a hexagon.
+pub struct ConvexPolygon<S = OrderedFloat<f64>> {
+    vertices: Vec<Point<S>>,
+}
+
+impl<S: Scalar> ConvexPolygon<S> {
+    /// Takes the corners in either order. Fails with
+    /// [`Error::DegenerateInput`] unless there are at least three of them,
+    /// all finite, making a strictly convex turn at every corner.
+    pub fn new(mut vertices: Vec<Point<S>>) -> Result<Self, Error> {
+        if vertices.len() < 3
+            || vertices
+                .iter()
+                .any(|v| !v.x.is_finite() || !v.y.is_finite())
+        {
+            return Err(Error::DegenerateInput);
+        }
+        if orient2d(&vertices[0], &vertices[1], &vertices[2]) == Ordering::Less {
+            vertices.reverse();
+        }
+        // every other corner strictly to the left of every edge also rules
+        // out polygons winding around more than once
+        for (idx, (a, b)) in edges(&vertices).enumerate() {
+            let others = vertices
+                .iter()
+                .enumerate()
+                .filter(|&(other, _)| other != idx && other != (idx + 1) % vertices.len());
+            for (_, c) in others {
+                if orient2d(a, b, c) != Ordering::Greater {
+                    return Err(Error::DegenerateInput);
+                }
+            }
+        }
+        Ok(Self { vertices })
+    }
+}
+
+impl<S: Scalar> ClipRegion<S> for ConvexPolygon<S> {
+    fn vertices(&self) -> Vec<Point<S>> {
+        self.vertices.clone()
+    }
+
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::point;
+
+    use super::*;
+
+    fn diamond() -> ConvexPolygon {
+        ConvexPolygon::new(vec![
+            point(0.0, -1.0),
+            point(1.0, 0.0),
+            point(0.0, 1.0),
+            point(-1.0, 0.0),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_bad_polygons() {
+        let line = vec![point(0.0, 0.0), point(1.0, 1.0), point(2.0, 2.0)];
+        assert!(ConvexPolygon::new(line).is_err());
+        let dent = vec![
+            point(0.0, 0.0),
+            point(2.0, 0.0),
+            point(1.0, 0.5),
+            point(2.0, 2.0),
+            point(0.0, 2.0),
+        ];
+        assert!(ConvexPolygon::new(dent).is_err());
+        let star = (0..5)
+            .map(|i| {
+                let angle = i as f64 * 4.0 * std::f64::consts::PI / 5.0;
+                point(angle.cos(), angle.sin())
+            })
+            .collect();
+        assert!(ConvexPolygon::new(star).is_err());
+
+        let clockwise = ConvexPolygon::new(vec![point(0.0, 0.0), point(0.0, 1.0), point(1.0, 0.0)]);
+        assert_eq!(
+            clockwise.unwrap().vertices(),
+            vec![point(1.0, 0.0), point(0.0, 1.0), point(0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn clip_to_diamond() {
+        let diamond = diamond();
+        assert!(diamond.contains(&point(0.5, 0.5)));
+        assert!(!diamond.contains(&point(0.6, 0.5)));
+
+        assert_eq!(
+            diamond.ray_end(&point(0.0, 0.0), &point(1.0, 1.0)),
+            point(0.5, 0.5)
+        );
+        assert_eq!(
+            diamond.ray_end(&point(-2.0, 0.0), &point(1.0, 0.0)),
+            point(1.0, 0.0)
+        );
+        // past the region already
+        assert_eq!(
+            diamond.ray_end(&point(2.0, 0.0), &point(1.0, 0.0)),
+            point(2.0, 0.0)
+        );
+
+        assert_eq!(
+            diamond.clip_segment(&[point(-2.0, 0.5), point(2.0, 0.5)]),
+            Some([point(-0.5, 0.5), point(0.5, 0.5)])
+        );
+        assert_eq!(
+            diamond.clip_segment(&[point(0.0, 0.0), point(0.25, 0.25)]),
+            Some([point(0.0, 0.0), point(0.25, 0.25)])
+        );
+        assert_eq!(
+            diamond.clip_segment(&[point(1.0, 1.0), point(2.0, 0.0)]),
+            None
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use super::{distance, BoundingBox, Point, Scalar};
+
+/// Signed area of `polygon`, positive when its vertices run
+/// counter-clockwise as those of cells do.
+pub fn polygon_area<S: Scalar>(polygon: &[Point<S>]) -> S {
+    let Some(origin) = polygon.first() else {
+        return S::zero();
+    };
+    // relative to the first vertex, which keeps cells far from the origin
+    // from cancelling out
+    let twice_area = (1..polygon.len().saturating_sub(1)).fold(S::zero(), |sum, idx| {
+        let a = polygon[idx].clone() - origin.clone();
+        let b = polygon[idx + 1].clone() - origin.clone();
+        sum + a.x * b.y - b.x * a.y
+    });
+    twice_area / S::from_f64(2.0)
+}
+
+/// Centre of mass of `polygon`, or `None` for a polygon without area.
+pub fn polygon_centroid<S: Scalar>(polygon: &[Point<S>]) -> Option<Point<S>> {
+    let origin = polygon.first()?;
+    let zero = S::zero();
+    let (mut twice_area, mut cx, mut cy) = (zero.clone(), zero.clone(), zero.clone());
+    // sum up the triangles fanning out from the first vertex
+    for idx in 1..polygon.len().saturating_sub(1) {
+        let a = polygon[idx].clone() - origin.clone();
+        let b = polygon[idx + 1].clone() - origin.clone();
+        let cross = a.x.clone() * b.y.clone() - b.x.clone() * a.y.clone();
+        cx = cx + (a.x + b.x) * cross.clone();
+        cy = cy + (a.y + b.y) * cross.clone();
+        twice_area = twice_area + cross;
+    }
+    if twice_area == zero {
+        return None;
+    }
+    let scale = twice_area * S::from_f64(3.0);
+    Some(Point::new(cx / scale.clone(), cy / scale) + origin)
+}
+
+/// Integral of the squared distance to `p` over `polygon`, its polar moment
+/// of inertia about `p`. Negative for clockwise polygons.
+pub fn polygon_second_moment<S: Scalar>(polygon: &[Point<S>], p: &Point<S>) -> S {
+    let sum = (0..polygon.len()).fold(S::zero(), |sum, idx| {
+        let a = polygon[idx].clone() - p.clone();
+        let b = polygon[(idx + 1) % polygon.len()].clone() - p.clone();
+        let cross = a.x.clone() * b.y.clone() - b.x.clone() * a.y.clone();
+        let squares = a.x.clone() * a.x.clone()
+            + a.x * b.x.clone()
+            + b.x.clone() * b.x
+            + a.y.clone() * a.y.clone()
+            + a.y * b.y.clone()
+            + b.y.clone() * b.y;
+        sum + cross * squares
+    });
+    sum / S::from_f64(12.0)
+}
+
+/// Length of the boundary of `polygon`, including the edge closing it.
+pub fn polygon_perimeter<S: Scalar>(polygon: &[Point<S>]) -> S {
+    (0..polygon.len()).fold(S::zero(), |sum, idx| {
+        sum + distance(&polygon[idx], &polygon[(idx + 1) % polygon.len()])
+    })
+}
+
+/// The smallest box around `polygon`, or `None` for an empty one.
+pub fn polygon_bounding_box<S: Scalar>(polygon: &[Point<S>]) -> Option<BoundingBox<S>> {
+    let xs = || polygon.iter().map(|p| p.x.clone());
+    let ys = || polygon.iter().map(|p| p.y.clone());
+    Some(BoundingBox::new(
+        xs().min()?,
+        xs().max()?,
+        ys().min()?,
+        ys().max()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ordered_float::OrderedFloat;
+
+    use crate::test_utils::point;
+
+    use super::*;
+
+    #[test]
+    fn square_metrics() {
+        let square = [
+            point(1.0, 1.0),
+            point(3.0, 1.0),
+            point(3.0, 3.0),
+            point(1.0, 3.0),
+        ];
+        assert_eq!(polygon_area(&square), OrderedFloat(4.0));
+        assert_eq!(polygon_centroid(&square), Some(point(2.0, 2.0)));
+        assert_eq!(polygon_perimeter(&square), OrderedFloat(8.0));
+        let bounding_box = polygon_bounding_box(&square).unwrap();
+        assert_eq!(bounding_box.corners(), square);
+
+        // side^4 / 6 about the centre
+        assert_eq!(
+            polygon_second_moment(&square, &point(2.0, 2.0)),
+            OrderedFloat(16.0 / 6.0)
+        );
+        // moving the point away adds the area times the squared distance
+        assert_eq!(
+            polygon_second_moment(&square, &point(0.0, 2.0)),
+            OrderedFloat(16.0 / 6.0 + 16.0)
+        );
+
+        let mut clockwise = square;
+        clockwise.reverse();
+        assert_eq!(polygon_area(&clockwise), OrderedFloat(-4.0));
+        assert_eq!(polygon_centroid(&clockwise), Some(point(2.0, 2.0)));
+    }
+
+    #[test]
+    fn hexagon_metrics() {
+        // side 1, far from the origin
+        let (cx, cy) = (1e6, -2e6);
+        let hexagon = (0..6)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::PI / 3.0;
+                point(cx + angle.cos(), cy + angle.sin())
+            })
+            .collect::<Vec<_>>();
+        let half_height = 3f64.sqrt() / 2.0;
+
+        assert_relative_eq!(
+            polygon_area(&hexagon).0,
+            6.0 * half_height / 2.0,
+            max_relative = 1e-9
+        );
+        let centroid = polygon_centroid(&hexagon).unwrap();
+        assert_relative_eq!(centroid.x.0, cx, epsilon = 1e-9);
+        assert_relative_eq!(centroid.y.0, cy, epsilon = 1e-9);
+        assert_relative_eq!(polygon_perimeter(&hexagon).0, 6.0, max_relative = 1e-9);
+
+        let [low, _, high, _] = polygon_bounding_box(&hexagon).unwrap().corners();
+        assert_eq!(low.x.0, cx - 1.0);
+        assert_eq!(high.x.0, cx + 1.0);
+        assert_relative_eq!(low.y.0, cy - half_height, epsilon = 1e-9);
+        assert_relative_eq!(high.y.0, cy + half_height, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn degenerate_metrics() {
+        let line = [point(0.0, 0.0), point(1.0, 1.0), point(2.0, 2.0)];
+        assert_eq!(polygon_area(&line), OrderedFloat(0.0));
+        assert_eq!(polygon_centroid(&line), None);
+        assert_eq!(polygon_centroid::<OrderedFloat<f64>>(&[]), None);
+        assert!(polygon_bounding_box::<OrderedFloat<f64>>(&[]).is_none());
+    }
+}
@@ -0,0 +1,437 @@
+use std::{cmp::Ordering, fmt};
+
+use ordered_float::OrderedFloat;
+
+use super::{orient2d, polygon_area, polygon_bounding_box, BoundingBox, Point, Scalar};
+use crate::Error;
+
+/// A simple polygon with holes, e.g. a floor plan with courtyards.
+///
+/// The outer ring runs counter-clockwise and the holes clockwise, so the
+/// polygon always lies to the left of its boundary.
+#[derive(Clone, PartialEq)]
+pub struct PolygonWithHoles<S = OrderedFloat<f64>> {
+    outer: Vec<Point<S>>,
+    holes: Vec<Vec<Point<S>>>,
+}
+
+impl<S: fmt::Display> fmt::Debug for PolygonWithHoles<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PolygonWithHoles")
+            .field("outer", &self.outer)
+            .field("holes", &self.holes)
+            .finish()
+    }
+}
+
+impl<S: Scalar> PolygonWithHoles<S> {
+    /// Takes the rings in either order. The rings have to be simple, the
+    /// holes inside the outer ring, and no two of them may cross; fails
+    /// with [`Error::DegenerateInput`] on rings with fewer than three
+    /// corners, non-finite ones or no area.
+    pub fn new(mut outer: Vec<Point<S>>, mut holes: Vec<Vec<Point<S>>>) -> Result<Self, Error> {
+        for ring in std::iter::once(&outer).chain(&holes) {
+            if ring.len() < 3
+                || ring.iter().any(|p| !p.x.is_finite() || !p.y.is_finite())
+                || twice_area(ring) == S::zero()
+            {
+                return Err(Error::DegenerateInput);
+            }
+        }
+        if twice_area(&outer) < S::zero() {
+            outer.reverse();
+        }
+        for hole in &mut holes {
+            if twice_area(hole) > S::zero() {
+                hole.reverse();
+            }
+        }
+        Ok(Self { outer, holes })
+    }
+
+    /// The outer boundary, counter-clockwise.
+    pub fn outer(&self) -> &[Point<S>] {
+        &self.outer
+    }
+
+    /// The boundaries of the holes, clockwise.
+    pub fn holes(&self) -> &[Vec<Point<S>>] {
+        &self.holes
+    }
+
+    /// The smallest box around the polygon.
+    pub fn bounding_box(&self) -> BoundingBox<S> {
+        polygon_bounding_box(&self.outer).expect("polygons have corners")
+    }
+
+    /// The parts of the polygon inside the convex polygon `convex`, given
+    /// counter-clockwise. Cutting away a neck of the polygon splits it into
+    /// several parts, while holes are cut open or kept whole.
+    pub fn intersect_convex(&self, convex: &[Point<S>]) -> Vec<PolygonWithHoles<S>> {
+        self.intersect_cuts((0..convex.len()).map(|idx| HalfPlane {
+            u: &convex[idx],
+            v: &convex[(idx + 1) % convex.len()],
+        }))
+    }
+
+    /// Like [`Self::intersect_convex`], keeping the side of every cut in
+    /// turn.
+    pub(crate) fn intersect_cuts<C: Cut<S>>(
+        &self,
+        cuts: impl IntoIterator<Item = C>,
+    ) -> Vec<PolygonWithHoles<S>> {
+        let mut rings = std::iter::once(self.outer.clone())
+            .chain(self.holes.iter().cloned())
+            .collect::<Vec<_>>();
+        for cut in cuts {
+            rings = clip_rings(rings, &cut);
+        }
+
+        let (outers, holes): (Vec<_>, Vec<_>) = rings
+            .into_iter()
+            .partition(|ring| twice_area(ring) > S::zero());
+        let mut parts = outers
+            .into_iter()
+            .map(|outer| PolygonWithHoles {
+                outer,
+                holes: vec![],
+            })
+            .collect::<Vec<_>>();
+        for hole in holes {
+            if let Some(part) = parts.iter_mut().find(|part| inside(&part.outer, &hole[0])) {
+                part.holes.push(hole);
+            }
+        }
+        parts
+    }
+}
+
+/// A curve cutting the plane in two, which [`clip_rings`] keeps one side
+/// of: a line, or a bisector under a metric other than the plain one.
+pub(crate) trait Cut<S: Scalar> {
+    /// Whether `p` lies strictly on the kept side.
+    fn kept(&self, p: &Point<S>) -> bool;
+
+    /// Points to split the segment from `a` to `b` at, so that every piece
+    /// crosses the curve at most once.
+    fn splits(&self, _a: &Point<S>, _b: &Point<S>) -> Vec<Point<S>> {
+        vec![]
+    }
+
+    /// Where the segment from `a` to `b`, which lie on different sides,
+    /// crosses the curve.
+    fn crossing(&self, a: &Point<S>, b: &Point<S>) -> Point<S>;
+
+    /// Position of a point of the curve along it, increasing in the
+    /// direction with the kept side on the left.
+    fn along(&self, p: &Point<S>) -> S;
+
+    /// The corners of the curve strictly between two positions along it,
+    /// in order.
+    fn corners(&self, _from: &S, _to: &S) -> Vec<Point<S>> {
+        vec![]
+    }
+}
+
+/// Everything strictly left of the line from `u` through `v`.
+struct HalfPlane<'a, S> {
+    u: &'a Point<S>,
+    v: &'a Point<S>,
+}
+
+impl<S: Scalar> Cut<S> for HalfPlane<'_, S> {
+    fn kept(&self, p: &Point<S>) -> bool {
+        orient2d(self.u, self.v, p) == Ordering::Greater
+    }
+
+    fn crossing(&self, a: &Point<S>, b: &Point<S>) -> Point<S> {
+        let (u, v) = (self.u, self.v);
+        if orient2d(u, v, a) == Ordering::Equal {
+            return a.clone();
+        }
+        if orient2d(u, v, b) == Ordering::Equal {
+            return b.clone();
+        }
+        let side = |p: &Point<S>| {
+            (v.x.clone() - u.x.clone()) * (p.y.clone() - u.y.clone())
+                - (v.y.clone() - u.y.clone()) * (p.x.clone() - u.x.clone())
+        };
+        let (fa, fb) = (side(a), side(b));
+        let t = fa.clone() / (fa - fb);
+        (b.clone() - a.clone()) * t + a
+    }
+
+    fn along(&self, p: &Point<S>) -> S {
+        let (u, v) = (self.u, self.v);
+        (v.x.clone() - u.x.clone()) * (p.x.clone() - u.x.clone())
+            + (v.y.clone() - u.y.clone()) * (p.y.clone() - u.y.clone())
+    }
+}
+
+/// Twice the signed area of `ring`, positive when it runs counter-clockwise.
+fn twice_area<S: Scalar>(ring: &[Point<S>]) -> S {
+    (0..ring.len()).fold(S::zero(), |sum, idx| {
+        let (a, b) = (&ring[idx], &ring[(idx + 1) % ring.len()]);
+        sum + a.x.clone() * b.y.clone() - b.x.clone() * a.y.clone()
+    })
+}
+
+/// Whether `p` lies inside `ring`, by counting the edges crossing the ray
+/// from `p` to the right.
+fn inside<S: Scalar>(ring: &[Point<S>], p: &Point<S>) -> bool {
+    let mut res = false;
+    for (idx, a) in ring.iter().enumerate() {
+        let b = &ring[(idx + 1) % ring.len()];
+        if (a.y > p.y) != (b.y > p.y) {
+            // the crossing lies right of p if p lies left of the upward edge
+            let (lo, hi) = if a.y < b.y { (a, b) } else { (b, a) };
+            if orient2d(lo, hi, p) == Ordering::Greater {
+                res = !res;
+            }
+        }
+    }
+    res
+}
+
+/// The parts of `rings` strictly on the kept side of `cut`.
+///
+/// Every ring crossing the cut is cut into chains running from where they
+/// enter the kept side to where they leave it. Along the cut, in the
+/// direction with the kept side on the left, each point where a chain
+/// leaves is followed by the one where the next chain enters, and the
+/// chains are joined up accordingly, by way of the corners of the cut in
+/// between.
+pub(crate) fn clip_rings<S: Scalar, C: Cut<S>>(
+    rings: Vec<Vec<Point<S>>>,
+    cut: &C,
+) -> Vec<Vec<Point<S>>> {
+    let rings = rings
+        .into_iter()
+        .map(|ring| ring.into_iter().map(|p| (p, ())).collect())
+        .collect();
+    clip_labelled_rings(rings, cut, &())
+        .into_iter()
+        .map(|ring| ring.into_iter().map(|(p, _)| p).collect())
+        .collect()
+}
+
+/// [`clip_rings`] for rings with a label on every edge, given with the
+/// point it leaves. Edges along the cut get `label`, and pieces of the
+/// others keep theirs.
+fn clip_labelled_rings<S: Scalar, C: Cut<S>, L: Clone>(
+    rings: Vec<Vec<(Point<S>, L)>>,
+    cut: &C,
+    label: &L,
+) -> Vec<Vec<(Point<S>, L)>> {
+    let kept = |p: &(Point<S>, L)| cut.kept(&p.0);
+    let crossing = |a: &(Point<S>, L), b: &(Point<S>, L)| cut.crossing(&a.0, &b.0);
+
+    let mut res = vec![];
+    let mut chains = vec![];
+    for ring in rings {
+        // the points split at only serve to find the crossings
+        let len = ring.len();
+        let (ring, split): (Vec<_>, Vec<_>) = (0..len)
+            .flat_map(|idx| {
+                let ((a, edge), (b, _)) = (&ring[idx], &ring[(idx + 1) % len]);
+                let splits = cut
+                    .splits(a, b)
+                    .into_iter()
+                    .map(|p| ((p, edge.clone()), true));
+                std::iter::once(((a.clone(), edge.clone()), false)).chain(splits)
+            })
+            .unzip();
+        let flags = ring.iter().map(kept).collect::<Vec<_>>();
+        // start outside, so that no chain wraps around the end
+        let Some(start) = flags.iter().position(|&flag| !flag) else {
+            let original = ring.into_iter().zip(&split).filter(|(_, &split)| !split);
+            res.push(original.map(|(p, _)| p).collect());
+            continue;
+        };
+        let n = ring.len();
+        let mut chain = vec![];
+        for k in 0..n {
+            let (i, j) = ((start + k) % n, (start + k + 1) % n);
+            if !flags[i] && flags[j] {
+                chain = vec![(crossing(&ring[i], &ring[j]), ring[i].1.clone())];
+            }
+            if flags[j] && !split[j] {
+                chain.push(ring[j].clone());
+            }
+            if flags[i] && !flags[j] {
+                chain.push((crossing(&ring[i], &ring[j]), label.clone()));
+                chains.push(std::mem::take(&mut chain));
+            }
+        }
+    }
+
+    // ends of the chains along the line, entries before exits at the same
+    // spot, so that a ring only touching the line is closed up right there
+    let along = |p: &(Point<S>, L)| cut.along(&p.0);
+    let mut ends = chains
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, chain)| {
+            [
+                (along(&chain[0]), false, idx),
+                (along(chain.last().unwrap()), true, idx),
+            ]
+        })
+        .collect::<Vec<_>>();
+    ends.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut next = vec![None; chains.len()];
+    for pair in ends.chunks(2) {
+        if let [(_, a_exit, a), (_, b_exit, b)] = pair {
+            match (a_exit, b_exit) {
+                (true, false) => next[*a] = Some(*b),
+                (false, true) => next[*b] = Some(*a),
+                _ => {}
+            }
+        }
+    }
+
+    let mut done = vec![false; chains.len()];
+    for first in 0..chains.len() {
+        let mut ring: Vec<(Point<S>, L)> = vec![];
+        let mut curr = Some(first);
+        while let Some(idx) = curr.filter(|&idx| !done[idx]) {
+            done[idx] = true;
+            for (p, edge) in &chains[idx] {
+                // a point met again leaves by the later edge
+                match ring.last_mut() {
+                    Some(last) if last.0 == *p => last.1 = edge.clone(),
+                    _ => ring.push((p.clone(), edge.clone())),
+                }
+            }
+            curr = next[idx];
+            if let Some(following) = curr {
+                let exit = along(chains[idx].last().unwrap());
+                let corners = cut.corners(&exit, &along(&chains[following][0]));
+                ring.extend(corners.into_iter().map(|p| (p, label.clone())));
+            }
+        }
+        if ring.len() > 1 && ring.first().map(|p| &p.0) == ring.last().map(|p| &p.0) {
+            ring.pop();
+        }
+        if ring.len() >= 3 {
+            res.push(ring);
+        }
+    }
+    res
+}
+
+/// What is left of the cell `ring` on the kept side of `cut`.
+pub(crate) fn clip_cell<S: Scalar, C: Cut<S>>(ring: Vec<Point<S>>, cut: &C) -> Vec<Point<S>> {
+    let ring = ring.into_iter().map(|p| (p, ())).collect();
+    clip_labelled_cell(ring, cut, &())
+        .into_iter()
+        .map(|(p, _)| p)
+        .collect()
+}
+
+/// [`clip_cell`] with labels on the edges, as for [`clip_labelled_rings`].
+pub(crate) fn clip_labelled_cell<S: Scalar, C: Cut<S>, L: Clone>(
+    ring: Vec<(Point<S>, L)>,
+    cut: &C,
+    label: &L,
+) -> Vec<(Point<S>, L)> {
+    // the cell is star-shaped around its site, so only slivers broken off
+    // by rounding can remain
+    clip_labelled_rings(vec![ring], cut, label)
+        .into_iter()
+        .max_by_key(|ring| polygon_area(&ring.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(points: &[(f64, f64)]) -> Vec<Point> {
+        points
+            .iter()
+            .map(|&(x, y)| Point::new(OrderedFloat(x), OrderedFloat(y)))
+            .collect()
+    }
+
+    fn area(polygon: &PolygonWithHoles) -> f64 {
+        std::iter::once(polygon.outer())
+            .chain(polygon.holes().iter().map(|hole| &hole[..]))
+            .map(|ring| twice_area(ring).0 / 2.0)
+            .sum()
+    }
+
+    fn courtyard() -> PolygonWithHoles {
+        PolygonWithHoles::new(
+            ring(&[(0.0, 0.0), (0.0, 4.0), (4.0, 4.0), (4.0, 0.0)]),
+            vec![ring(&[(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)])],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn normalizes_orientation() {
+        let polygon = courtyard();
+        assert!(twice_area(polygon.outer()) > OrderedFloat(0.0));
+        assert!(twice_area(&polygon.holes()[0]) < OrderedFloat(0.0));
+        assert_eq!(area(&polygon), 12.0);
+
+        let flat = ring(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]);
+        assert!(PolygonWithHoles::new(flat, vec![]).is_err());
+    }
+
+    #[test]
+    fn keeps_holes_inside() {
+        let polygon = courtyard();
+        let big = ring(&[(-1.0, -1.0), (5.0, -1.0), (5.0, 5.0), (-1.0, 5.0)]);
+        assert_eq!(polygon.intersect_convex(&big), vec![polygon.clone()]);
+
+        let in_hole = ring(&[(1.5, 1.5), (2.5, 1.5), (2.5, 2.5), (1.5, 2.5)]);
+        assert!(polygon.intersect_convex(&in_hole).is_empty());
+
+        let outside = ring(&[(5.0, 5.0), (6.0, 5.0), (6.0, 6.0)]);
+        assert!(polygon.intersect_convex(&outside).is_empty());
+    }
+
+    #[test]
+    fn cuts_holes_open() {
+        let polygon = courtyard();
+        let left = ring(&[(-1.0, -1.0), (2.0, -1.0), (2.0, 5.0), (-1.0, 5.0)]);
+        let parts = polygon.intersect_convex(&left);
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].holes().is_empty());
+        assert_eq!(area(&parts[0]), 6.0);
+    }
+
+    #[test]
+    fn splits_into_parts() {
+        // a U, whose arms a box across the top cuts apart
+        let u = PolygonWithHoles::new(
+            ring(&[
+                (0.0, 0.0),
+                (3.0, 0.0),
+                (3.0, 3.0),
+                (2.0, 3.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (1.0, 3.0),
+                (0.0, 3.0),
+            ]),
+            vec![],
+        )
+        .unwrap();
+        let top = ring(&[(-1.0, 2.0), (4.0, 2.0), (4.0, 4.0), (-1.0, 4.0)]);
+        let parts = u.intersect_convex(&top);
+        assert_eq!(parts.len(), 2);
+        for part in &parts {
+            assert_eq!(area(part), 1.0);
+        }
+
+        // touching the line at the bottom of the U only
+        let below = ring(&[(-1.0, -1.0), (4.0, -1.0), (4.0, 1.0), (-1.0, 1.0)]);
+        let parts = u.intersect_convex(&below);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(area(&parts[0]), 3.0);
+    }
+}
@@ -0,0 +1,669 @@
+//! Robust geometric predicates.
+//!
+//! Every predicate first evaluates its determinant in plain floating point
+//! and only falls back to exact arithmetic when the result lies within the
+//! rounding error bound, as in Shewchuk's "Adaptive Precision Floating-Point
+//! Arithmetic and Fast Robust Geometric Predicates". The exact path works on
+//! expansions: numbers held as a sum of non-overlapping `f64` components,
+//! ordered by increasing magnitude.
+
+use std::cmp::Ordering;
+
+/// Half an ulp of 1.0, the relative rounding error of a single operation.
+const EPSILON: f64 = f64::EPSILON / 2.0;
+const ORIENT_ERRBOUND: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const INCIRCLE_ERRBOUND: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+/// [`INCIRCLE_ERRBOUND`] with two more roundings on every path, for the
+/// weight difference and its subtraction from the lift: at most
+/// `12ε / ((1 - 24ε) (1 - ε))` of the permanent.
+const POWER_ERRBOUND: f64 = (12.0 + 320.0 * EPSILON) * EPSILON;
+/// The difference of the arcs rounds each of its terms at most five times,
+/// three leaf differences and two products, and their sum five times more:
+/// at most `10ε / ((1 - 20ε) (1 - ε))` of the permanent.
+const BREAKPOINT_ERRBOUND: f64 = (10.0 + 224.0 * EPSILON) * EPSILON;
+/// Its slope rounds two leaf differences, a product and the sum: at most
+/// `4ε / ((1 - 8ε) (1 - ε))` of the permanent.
+const BREAKPOINT_SLOPE_ERRBOUND: f64 = (4.0 + 40.0 * EPSILON) * EPSILON;
+
+/// Which way `a`, `b`, `c` turn: `Greater` for counter-clockwise, `Less` for
+/// clockwise and `Equal` when they are collinear.
+pub fn orient2d([ax, ay]: [f64; 2], [bx, by]: [f64; 2], [cx, cy]: [f64; 2]) -> Ordering {
+    let det_left = (ax - cx) * (by - cy);
+    let det_right = (ay - cy) * (bx - cx);
+    let det = det_left - det_right;
+    let bound = ORIENT_ERRBOUND * (det_left.abs() + det_right.abs());
+    if det.abs() > bound {
+        return sign(det);
+    }
+
+    let det_left = product(&diff(ax, cx), &diff(by, cy));
+    let det_right = product(&diff(ay, cy), &diff(bx, cx));
+    sign_of(&sum(&det_left, &negate(&det_right)))
+}
+
+/// Where `d` lies relative to the circle through `a`, `b` and `c`, which
+/// must turn counter-clockwise: `Greater` inside, `Less` outside and `Equal`
+/// on the circle.
+pub fn incircle(
+    [ax, ay]: [f64; 2],
+    [bx, by]: [f64; 2],
+    [cx, cy]: [f64; 2],
+    [dx, dy]: [f64; 2],
+) -> Ordering {
+    let (adx, ady) = (ax - dx, ay - dy);
+    let (bdx, bdy) = (bx - dx, by - dy);
+    let (cdx, cdy) = (cx - dx, cy - dy);
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift
+        + (cdxady.abs() + adxcdy.abs()) * blift
+        + (adxbdy.abs() + bdxady.abs()) * clift;
+    if det.abs() > INCIRCLE_ERRBOUND * permanent {
+        return sign(det);
+    }
+
+    let (adx, ady) = (diff(ax, dx), diff(ay, dy));
+    let (bdx, bdy) = (diff(bx, dx), diff(by, dy));
+    let (cdx, cdy) = (diff(cx, dx), diff(cy, dy));
+    let lift = |x: &Expansion, y: &Expansion| sum(&product(x, x), &product(y, y));
+    let cross = |x1: &Expansion, y1: &Expansion, x2: &Expansion, y2: &Expansion| {
+        sum(&product(x1, y2), &negate(&product(x2, y1)))
+    };
+
+    let a_term = product(&lift(&adx, &ady), &cross(&bdx, &bdy, &cdx, &cdy));
+    let b_term = product(&lift(&bdx, &bdy), &cross(&cdx, &cdy, &adx, &ady));
+    let c_term = product(&lift(&cdx, &cdy), &cross(&adx, &ady, &bdx, &bdy));
+    sign_of(&sum(&sum(&a_term, &b_term), &c_term))
+}
+
+/// Where `d` lies relative to the point with equal power distance to `a`,
+/// `b` and `c`, each given as x, y and weight, which must turn
+/// counter-clockwise: `Greater` when its power distance to that point is
+/// smaller than theirs, `Less` when larger and `Equal` when they agree.
+pub fn power_test(
+    [ax, ay, aw]: [f64; 3],
+    [bx, by, bw]: [f64; 3],
+    [cx, cy, cw]: [f64; 3],
+    [dx, dy, dw]: [f64; 3],
+) -> Ordering {
+    // incircle, with every point lifted by its weight relative to that of d
+    let (adx, ady, adw) = (ax - dx, ay - dy, aw - dw);
+    let (bdx, bdy, bdw) = (bx - dx, by - dy, bw - dw);
+    let (cdx, cdy, cdw) = (cx - dx, cy - dy, cw - dw);
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let (asq, bsq, csq) = (
+        adx * adx + ady * ady,
+        bdx * bdx + bdy * bdy,
+        cdx * cdx + cdy * cdy,
+    );
+
+    let det = (asq - adw) * (bdxcdy - cdxbdy)
+        + (bsq - bdw) * (cdxady - adxcdy)
+        + (csq - cdw) * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * (asq + adw.abs())
+        + (cdxady.abs() + adxcdy.abs()) * (bsq + bdw.abs())
+        + (adxbdy.abs() + bdxady.abs()) * (csq + cdw.abs());
+    if det.abs() > POWER_ERRBOUND * permanent {
+        return sign(det);
+    }
+
+    let (adx, ady, adw) = (diff(ax, dx), diff(ay, dy), diff(aw, dw));
+    let (bdx, bdy, bdw) = (diff(bx, dx), diff(by, dy), diff(bw, dw));
+    let (cdx, cdy, cdw) = (diff(cx, dx), diff(cy, dy), diff(cw, dw));
+    let lift = |x: &Expansion, y: &Expansion, w: &Expansion| {
+        sum(&sum(&product(x, x), &product(y, y)), &negate(w))
+    };
+    let cross = |x1: &Expansion, y1: &Expansion, x2: &Expansion, y2: &Expansion| {
+        sum(&product(x1, y2), &negate(&product(x2, y1)))
+    };
+
+    let a_term = product(&lift(&adx, &ady, &adw), &cross(&bdx, &bdy, &cdx, &cdy));
+    let b_term = product(&lift(&bdx, &bdy, &bdw), &cross(&cdx, &cdy, &adx, &ady));
+    let c_term = product(&lift(&cdx, &cdy, &cdw), &cross(&adx, &ady, &bdx, &bdy));
+    sign_of(&sum(&sum(&a_term, &b_term), &c_term))
+}
+
+/// [`power_test`] for `d` on the line through the distinct `a` and `b`:
+/// `Greater` when the power distance from some point of the line to `d` is
+/// smaller than to both `a` and `b`.
+pub fn power_test_collinear(
+    [ax, ay, aw]: [f64; 3],
+    [bx, by, bw]: [f64; 3],
+    [dx, dy, dw]: [f64; 3],
+) -> Ordering {
+    // With d at s / l of the way from a to b, compare its lifted height to
+    // that of the line through the lifted a and b, scaled by l.
+    let (ux, uy) = (diff(dx, ax), diff(dy, ay));
+    let (vx, vy) = (diff(bx, ax), diff(by, ay));
+    let s = sum(&product(&ux, &vx), &product(&uy, &vy));
+    let l = sum(&product(&vx, &vx), &product(&vy, &vy));
+    let rest = sum(&l, &negate(&s));
+    let terms = [
+        product(&rest, &sum(&s, &negate(&single(aw)))),
+        negate(&scale(&s, bw)),
+        scale(&l, dw),
+    ];
+    sign_of(&sum(&sum(&terms[0], &terms[1]), &terms[2]))
+}
+
+/// Compare the x coordinate of `p` with that of the breakpoint between the
+/// arcs of `l` (on the left) and `r` (on the right), with the sweep line
+/// passing through `p`. Both sites must lie on or above the sweep line.
+pub fn compare_to_breakpoint([x, y]: [f64; 2], [xl, yl]: [f64; 2], [xr, yr]: [f64; 2]) -> Ordering {
+    compare_to_power_breakpoint([x, y], [xl, yl, 0.0], [xr, yr, 0.0], 0.0)
+}
+
+/// [`compare_to_breakpoint`] for the arcs of weighted sites, lifted by
+/// `top` less their weights.
+pub fn compare_to_power_breakpoint(
+    [x, y]: [f64; 2],
+    [xl, yl, wl]: [f64; 3],
+    [xr, yr, wr]: [f64; 3],
+    top: f64,
+) -> Ordering {
+    if yl == yr {
+        // Congruent parabolas only meet once, halfway between their foci
+        if wl == wr {
+            return sign_of(&sum(&diff(x, xl), &diff(x, xr)));
+        }
+        // or where the powers agree, when lifted apart
+        let (ul, ur) = (diff(x, xl), diff(x, xr));
+        let powers = sum(&product(&ul, &ul), &negate(&product(&ur, &ur)));
+        return sign_of(&sum(&powers, &diff(wr, wl)));
+    }
+
+    // With dl and dr the heights of l and r above the sweep line, the
+    // parabolas differ at x by f / (2 dl dr) and their difference has slope
+    // f' / (dl dr). The breakpoint is the root where the difference rises.
+    let (dl, dr) = (yl - y, yr - y);
+    let (ul, ur) = (x - xl, x - xr);
+    let (f, f_bound) = {
+        let terms = [
+            dr * ul * ul,
+            -(dl * ur * ur),
+            dl * dr * (yl - yr),
+            top * (yr - yl),
+            -(dr * wl),
+            dl * wr,
+        ];
+        let permanent: f64 = terms.iter().map(|t| t.abs()).sum();
+        (terms.iter().sum::<f64>(), BREAKPOINT_ERRBOUND * permanent)
+    };
+    let (f_prime, f_prime_bound) = {
+        let terms = [ul * dr, -(ur * dl)];
+        let permanent: f64 = terms.iter().map(|t| t.abs()).sum();
+        (
+            terms.iter().sum::<f64>(),
+            BREAKPOINT_SLOPE_ERRBOUND * permanent,
+        )
+    };
+
+    let f = if f.abs() > f_bound {
+        sign(f)
+    } else {
+        let (dl, dr) = (diff(yl, y), diff(yr, y));
+        let (ul, ur) = (diff(x, xl), diff(x, xr));
+        let terms = [
+            product(&dr, &product(&ul, &ul)),
+            negate(&product(&dl, &product(&ur, &ur))),
+            product(&product(&dl, &dr), &diff(yl, yr)),
+            scale(&diff(yr, yl), top),
+            negate(&scale(&dr, wl)),
+            scale(&dl, wr),
+        ];
+        sign_of(&terms.iter().fold(vec![], |total, term| sum(&total, term)))
+    };
+    let f_prime = if f_prime.abs() > f_prime_bound {
+        sign(f_prime)
+    } else {
+        let (dl, dr) = (diff(yl, y), diff(yr, y));
+        let (ul, ur) = (diff(x, xl), diff(x, xr));
+        sign_of(&sum(&product(&ul, &dr), &negate(&product(&ur, &dl))))
+    };
+
+    breakpoint_order(yl > yr, f, f_prime)
+}
+
+/// Place x relative to the breakpoint, from the signs of the difference `f`
+/// between the arcs at x and of its slope `f_prime`.
+fn breakpoint_order(l_above_r: bool, f: Ordering, f_prime: Ordering) -> Ordering {
+    let past_root = f != Ordering::Less;
+    let rising = f_prime == Ordering::Greater;
+    if l_above_r {
+        // the difference is concave, and rises through its left root
+        if !past_root && rising {
+            Ordering::Less
+        } else if f == Ordering::Equal && rising {
+            Ordering::Equal
+        } else {
+            Ordering::Greater
+        }
+    } else {
+        // convex, rising through its right root
+        if past_root && rising {
+            f
+        } else {
+            Ordering::Less
+        }
+    }
+}
+
+fn sign(x: f64) -> Ordering {
+    x.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+}
+
+/// Non-overlapping components by increasing magnitude, without zeros.
+type Expansion = Vec<f64>;
+
+fn sign_of(e: &Expansion) -> Ordering {
+    e.last().map_or(Ordering::Equal, |&c| sign(c))
+}
+
+/// `a + b` as its rounded value and the rounding error.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    (x, (a - a_virtual) + (b - b_virtual))
+}
+
+/// `a * b` as its rounded value and the rounding error.
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+fn diff(a: f64, b: f64) -> Expansion {
+    let (x, y) = two_sum(a, -b);
+    [y, x].into_iter().filter(|&c| c != 0.0).collect()
+}
+
+fn single(a: f64) -> Expansion {
+    [a].into_iter().filter(|&c| c != 0.0).collect()
+}
+
+fn negate(e: &Expansion) -> Expansion {
+    e.iter().map(|c| -c).collect()
+}
+
+fn grow(e: &Expansion, b: f64) -> Expansion {
+    let mut res = Vec::with_capacity(e.len() + 1);
+    let mut q = b;
+    for &c in e {
+        let (sum, err) = two_sum(q, c);
+        if err != 0.0 {
+            res.push(err);
+        }
+        q = sum;
+    }
+    if q != 0.0 {
+        res.push(q);
+    }
+    res
+}
+
+fn sum(e: &Expansion, f: &Expansion) -> Expansion {
+    f.iter().fold(e.clone(), |acc, &c| grow(&acc, c))
+}
+
+fn scale(e: &Expansion, b: f64) -> Expansion {
+    let mut res = Vec::with_capacity(2 * e.len());
+    let mut q = 0.0;
+    for (idx, &c) in e.iter().enumerate() {
+        let (high, low) = two_product(c, b);
+        if idx == 0 {
+            if low != 0.0 {
+                res.push(low);
+            }
+            q = high;
+            continue;
+        }
+        let (sum, err) = two_sum(q, low);
+        if err != 0.0 {
+            res.push(err);
+        }
+        let (sum, err) = two_sum(high, sum);
+        if err != 0.0 {
+            res.push(err);
+        }
+        q = sum;
+    }
+    if q != 0.0 {
+        res.push(q);
+    }
+    res
+}
+
+fn product(e: &Expansion, f: &Expansion) -> Expansion {
+    f.iter().fold(vec![], |acc, &c| sum(&acc, &scale(e, c)))
+}
+
+/// The same predicates evaluated directly, for scalars with exact
+/// arithmetic.
+#[cfg(feature = "rational")]
+pub mod exact {
+    use std::cmp::Ordering;
+
+    use super::breakpoint_order;
+    use crate::geometry::{Point, Scalar, WeightedSite};
+
+    pub fn orient2d<S: Scalar>(a: &Point<S>, b: &Point<S>, c: &Point<S>) -> Ordering {
+        let a = a.clone() - c.clone();
+        let b = b.clone() - c.clone();
+        (a.x * b.y - a.y * b.x).cmp(&S::zero())
+    }
+
+    pub fn incircle<S: Scalar>(a: &Point<S>, b: &Point<S>, c: &Point<S>, d: &Point<S>) -> Ordering {
+        let lift = |p: &Point<S>| p.x.clone() * p.x.clone() + p.y.clone() * p.y.clone();
+        let cross =
+            |p: &Point<S>, q: &Point<S>| p.x.clone() * q.y.clone() - q.x.clone() * p.y.clone();
+
+        let a = a.clone() - d.clone();
+        let b = b.clone() - d.clone();
+        let c = c.clone() - d.clone();
+        let det = lift(&a) * cross(&b, &c) + lift(&b) * cross(&c, &a) + lift(&c) * cross(&a, &b);
+        det.cmp(&S::zero())
+    }
+
+    pub fn power_test<S: Scalar>(
+        a: &WeightedSite<S>,
+        b: &WeightedSite<S>,
+        c: &WeightedSite<S>,
+        d: &WeightedSite<S>,
+    ) -> Ordering {
+        let lift = |p: &Point<S>, w: S| p.x.clone() * p.x.clone() + p.y.clone() * p.y.clone() - w;
+        let cross =
+            |p: &Point<S>, q: &Point<S>| p.x.clone() * q.y.clone() - q.x.clone() * p.y.clone();
+
+        let relative = |p: &WeightedSite<S>| {
+            (
+                p.point.clone() - d.point.clone(),
+                p.weight.clone() - d.weight.clone(),
+            )
+        };
+        let (a, aw) = relative(a);
+        let (b, bw) = relative(b);
+        let (c, cw) = relative(c);
+        let det = lift(&a, aw) * cross(&b, &c)
+            + lift(&b, bw) * cross(&c, &a)
+            + lift(&c, cw) * cross(&a, &b);
+        det.cmp(&S::zero())
+    }
+
+    pub fn power_test_collinear<S: Scalar>(
+        a: &WeightedSite<S>,
+        b: &WeightedSite<S>,
+        d: &WeightedSite<S>,
+    ) -> Ordering {
+        let u = d.point.clone() - a.point.clone();
+        let v = b.point.clone() - a.point.clone();
+        let s = u.x * v.x.clone() + u.y * v.y.clone();
+        let l = v.x.clone() * v.x + v.y.clone() * v.y;
+        let det = (l.clone() - s.clone()) * (s.clone() - a.weight.clone()) - s * b.weight.clone()
+            + l * d.weight.clone();
+        det.cmp(&S::zero())
+    }
+
+    pub fn compare_to_breakpoint<S: Scalar>(p: &Point<S>, l: &Point<S>, r: &Point<S>) -> Ordering {
+        let site = |point: &Point<S>| WeightedSite::new(point.clone(), S::zero());
+        compare_to_power_breakpoint(p, &site(l), &site(r), &S::zero())
+    }
+
+    pub fn compare_to_power_breakpoint<S: Scalar>(
+        p: &Point<S>,
+        l: &WeightedSite<S>,
+        r: &WeightedSite<S>,
+        top: &S,
+    ) -> Ordering {
+        let (wl, wr) = (l.weight.clone(), r.weight.clone());
+        let (l, r) = (&l.point, &r.point);
+        let ul = p.x.clone() - l.x.clone();
+        let ur = p.x.clone() - r.x.clone();
+        if l.y == r.y {
+            if wl == wr {
+                return (ul + ur).cmp(&S::zero());
+            }
+            return (ul.clone() * ul - ur.clone() * ur + wr - wl).cmp(&S::zero());
+        }
+
+        let dl = l.y.clone() - p.y.clone();
+        let dr = r.y.clone() - p.y.clone();
+        let f = dr.clone() * ul.clone() * ul.clone() - dl.clone() * ur.clone() * ur.clone()
+            + dl.clone() * dr.clone() * (l.y.clone() - r.y.clone())
+            + top.clone() * (r.y.clone() - l.y.clone())
+            - dr.clone() * wl
+            + dl.clone() * wr;
+        let f_prime = ul * dr - ur * dl;
+        breakpoint_order(l.y > r.y, f.cmp(&S::zero()), f_prime.cmp(&S::zero()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> [f64; 2] {
+        [x, y]
+    }
+
+    /// Small LCG, enough to spread test cases around
+    fn lcg(state: &mut u64) -> i64 {
+        *state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (*state >> 33) as i64
+    }
+
+    #[test]
+    fn orient2d_near_collinear() {
+        let mut state = 7;
+        for _ in 0..1000 {
+            // a line through integer points far apart enough for plain f64
+            // products to round
+            let a = (lcg(&mut state) << 19, lcg(&mut state) << 19);
+            let u = (lcg(&mut state) % 1000 + 1, lcg(&mut state) % 1000 + 1);
+            let k = lcg(&mut state) % (1 << 18) + 2;
+            let b = (a.0 + u.0, a.1 + u.1);
+            for off in -1..=1 {
+                let c = (a.0 + k * u.0 + off, a.1 + k * u.1);
+                let exact = (b.0 - a.0) as i128 * (c.1 - a.1) as i128
+                    - (b.1 - a.1) as i128 * (c.0 - a.0) as i128;
+                let res = orient2d(
+                    point(a.0 as f64, a.1 as f64),
+                    point(b.0 as f64, b.1 as f64),
+                    point(c.0 as f64, c.1 as f64),
+                );
+                assert_eq!(res, exact.cmp(&0));
+            }
+        }
+    }
+
+    #[test]
+    fn incircle_near_cocircular() {
+        let mut state = 11;
+        for _ in 0..1000 {
+            // every rectangle is cyclic, so nudging one corner by one unit
+            // decides between inside and outside
+            let a = (lcg(&mut state) >> 7, lcg(&mut state) >> 7);
+            let u = (lcg(&mut state) >> 16, lcg(&mut state) >> 16);
+            let k = lcg(&mut state) % 64 + 1;
+            let w = (-k * u.1, k * u.0);
+            let b = (a.0 + u.0, a.1 + u.1);
+            let c = (b.0 + w.0, b.1 + w.1);
+            for off in -1..=1 {
+                let d = (a.0 + w.0 + off, a.1 + w.1);
+                let [adx, ady, bdx, bdy, cdx, cdy] = [
+                    a.0 - d.0,
+                    a.1 - d.1,
+                    b.0 - d.0,
+                    b.1 - d.1,
+                    c.0 - d.0,
+                    c.1 - d.1,
+                ]
+                .map(|v| v as i128);
+                let exact = (adx * adx + ady * ady) * (bdx * cdy - cdx * bdy)
+                    + (bdx * bdx + bdy * bdy) * (cdx * ady - adx * cdy)
+                    + (cdx * cdx + cdy * cdy) * (adx * bdy - bdx * ady);
+                let res = incircle(
+                    point(a.0 as f64, a.1 as f64),
+                    point(b.0 as f64, b.1 as f64),
+                    point(c.0 as f64, c.1 as f64),
+                    point(d.0 as f64, d.1 as f64),
+                );
+                assert_eq!(res, exact.cmp(&0));
+            }
+        }
+    }
+
+    #[test]
+    fn power_test_near_degenerate() {
+        let mut state = 13;
+        for _ in 0..1000 {
+            // d on the circle through the corners of a rectangle, with
+            // weights too large for the lifts to stay exact
+            let a = (lcg(&mut state) >> 7, lcg(&mut state) >> 7);
+            let u = (lcg(&mut state) >> 16, lcg(&mut state) >> 16);
+            let k = lcg(&mut state) % 64 + 1;
+            let w = (-k * u.1, k * u.0);
+            let b = (a.0 + u.0, a.1 + u.1);
+            let c = (b.0 + w.0, b.1 + w.1);
+            let weight = lcg(&mut state) << 10;
+            for off in -1..=1 {
+                for weight_off in -1..=1 {
+                    let d = (a.0 + w.0 + off, a.1 + w.1);
+                    let [adx, ady, bdx, bdy, cdx, cdy] = [
+                        a.0 - d.0,
+                        a.1 - d.1,
+                        b.0 - d.0,
+                        b.1 - d.1,
+                        c.0 - d.0,
+                        c.1 - d.1,
+                    ]
+                    .map(|v| v as i128);
+                    let dw = -weight_off as i128;
+                    let exact = (adx * adx + ady * ady + dw) * (bdx * cdy - cdx * bdy)
+                        + (bdx * bdx + bdy * bdy + dw) * (cdx * ady - adx * cdy)
+                        + (cdx * cdx + cdy * cdy + dw) * (adx * bdy - bdx * ady);
+                    let res = power_test(
+                        [a.0 as f64, a.1 as f64, weight as f64],
+                        [b.0 as f64, b.1 as f64, weight as f64],
+                        [c.0 as f64, c.1 as f64, weight as f64],
+                        [d.0 as f64, d.1 as f64, (weight - weight_off) as f64],
+                    );
+                    assert_eq!(res, exact.cmp(&0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn power_test_on_a_line() {
+        let mut state = 17;
+        let lift = |p: (i64, i64), w: i64| (p.0 * p.0 + p.1 * p.1 - w) as i128;
+        for _ in 0..1000 {
+            let a = (lcg(&mut state) >> 12, lcg(&mut state) >> 12);
+            let u = (lcg(&mut state) % 100 - 50, lcg(&mut state) % 100 + 1);
+            let (j, k) = (lcg(&mut state) % 200 - 100, lcg(&mut state) % 100 + 1);
+            let b = (a.0 + k * u.0, a.1 + k * u.1);
+            let d = (a.0 + j * u.0, a.1 + j * u.1);
+            let weights = [0, 1, 2].map(|_| lcg(&mut state) >> 8);
+            // d lies below the line through the lifted a and b
+            let (la, lb) = (lift(a, weights[0]), lift(b, weights[1]));
+            let below =
+                (k as i128) * lift(d, weights[2]) < (k as i128) * la + (j as i128) * (lb - la);
+            let res = power_test_collinear(
+                [a.0 as f64, a.1 as f64, weights[0] as f64],
+                [b.0 as f64, b.1 as f64, weights[1] as f64],
+                [d.0 as f64, d.1 as f64, weights[2] as f64],
+            );
+            assert_eq!(res == Ordering::Greater, below);
+        }
+    }
+
+    #[test]
+    fn power_breakpoint_near_ties() {
+        let exact = |x: i64, y: i64, [xl, yl, wl]: [i64; 3], [xr, yr, wr]: [i64; 3], top: i64| {
+            let [x, y, xl, yl, wl, xr, yr, wr, top] =
+                [x, y, xl, yl, wl, xr, yr, wr, top].map(|v| v as i128);
+            let (dl, dr, ul, ur) = (yl - y, yr - y, x - xl, x - xr);
+            let f = dr * ul * ul - dl * ur * ur + dl * dr * (yl - yr) + top * (yr - yl) - dr * wl
+                + dl * wr;
+            breakpoint_order(yl > yr, f.cmp(&0), (ul * dr - ur * dl).cmp(&0))
+        };
+        let compare = |x: i64, y: i64, l: [i64; 3], r: [i64; 3], top: i64| {
+            compare_to_power_breakpoint(
+                point(x as f64, y as f64),
+                l.map(|v| v as f64),
+                r.map(|v| v as f64),
+                top as f64,
+            )
+        };
+
+        let mut state = 19;
+        for _ in 0..1000 {
+            // arcs one unit apart in height, with the weight of r picked to
+            // cancel all but a remainder below dl, which top then offsets
+            let (x, y) = (lcg(&mut state) >> 11, lcg(&mut state) >> 11);
+            let dr = lcg(&mut state) % (1 << 20) + 1;
+            let dl = dr + 1;
+            let (xl, xr) = (x + (lcg(&mut state) >> 11), x - (lcg(&mut state) >> 11));
+            let wl = lcg(&mut state);
+            let (ul, ur) = ((x - xl) as i128, (x - xr) as i128);
+            let rest = dr as i128 * ul * ul - dl as i128 * ur * ur + (dl * dr) as i128
+                - dr as i128 * wl as i128;
+            let wr = -rest.div_euclid(dl as i128) as i64;
+            let remainder = rest.rem_euclid(dl as i128) as i64;
+            for off in -1..=1 {
+                let (l, r) = ([xl, y + dl, wl], [xr, y + dr, wr]);
+                let top = remainder + off;
+                assert_eq!(compare(x, y, l, r, top), exact(x, y, l, r, top));
+                assert_eq!(compare(x, y, r, l, top), exact(x, y, r, l, top));
+            }
+
+            // and x where the slope vanishes, with ul : ur = dl : dr
+            let (dl, dr) = (dr, dr + lcg(&mut state) % 3 + 1);
+            let k = lcg(&mut state) % (1 << 20) + 1;
+            let (l, r) = ([x - k * dl, y + dl, wl], [x - k * dr, y + dr, wl >> 3]);
+            let top = lcg(&mut state);
+            for off in -1..=1 {
+                assert_eq!(compare(x + off, y, l, r, top), exact(x + off, y, l, r, top));
+                assert_eq!(compare(x + off, y, r, l, top), exact(x + off, y, r, l, top));
+            }
+        }
+    }
+
+    #[test]
+    fn breakpoint_comparison() {
+        // with the sweep line at y = 0 these arcs meet at x = 8 -+ sqrt(34);
+        // the higher, wider one is on the left of the lower root only
+        let l = point(0.0, 2.0);
+        let r = point(4.0, 1.0);
+        let lower = 8.0 - 34f64.sqrt();
+        let upper = 8.0 + 34f64.sqrt();
+        let at = |x: f64, l, r| compare_to_breakpoint(point(x, 0.0), l, r);
+
+        assert_eq!(at(1.0, l, r), Ordering::Less);
+        assert_eq!(at(lower - 1e-9, l, r), Ordering::Less);
+        assert_eq!(at(lower + 1e-9, l, r), Ordering::Greater);
+        assert_eq!(at(upper + 1e-9, l, r), Ordering::Greater);
+
+        assert_eq!(at(lower + 1e-9, r, l), Ordering::Less);
+        assert_eq!(at(upper - 1e-9, r, l), Ordering::Less);
+        assert_eq!(at(upper + 1e-9, r, l), Ordering::Greater);
+
+        // exact hits, of congruent arcs and of the roots of 3x^2 - 32x + 52
+        assert_eq!(at(1.0, point(0.0, 1.0), point(2.0, 1.0)), Ordering::Equal);
+        let l = point(0.0, 4.0);
+        let r = point(4.0, 1.0);
+        assert_eq!(at(2.0, l, r), Ordering::Equal);
+        assert_eq!(at(2.0, r, l), Ordering::Less);
+    }
+}
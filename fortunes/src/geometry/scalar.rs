@@ -0,0 +1,204 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::Hash,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use ordered_float::OrderedFloat;
+
+use super::{predicates, Point, WeightedSite};
+
+/// Number type for coordinates.
+///
+/// The predicates deciding the combinatorics of the diagram default to exact
+/// evaluation on the values converted by [`Scalar::to_f64`], which is only
+/// right for types converting to `f64` without rounding. Other types have to
+/// provide their own.
+pub trait Scalar:
+    Clone
+    + Ord
+    + Hash
+    + fmt::Debug
+    + fmt::Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn from_f64(v: f64) -> Self;
+
+    fn to_f64(&self) -> f64;
+
+    /// Square root, rounded for types that cannot represent it. None of the
+    /// predicates take one.
+    fn sqrt(&self) -> Self;
+
+    fn is_finite(&self) -> bool;
+
+    fn zero() -> Self {
+        Self::from_f64(0.0)
+    }
+
+    /// See [`super::orient2d`].
+    fn orient2d(a: &Point<Self>, b: &Point<Self>, c: &Point<Self>) -> Ordering {
+        predicates::orient2d(coords(a), coords(b), coords(c))
+    }
+
+    /// See [`super::incircle`].
+    fn incircle(a: &Point<Self>, b: &Point<Self>, c: &Point<Self>, d: &Point<Self>) -> Ordering {
+        predicates::incircle(coords(a), coords(b), coords(c), coords(d))
+    }
+
+    /// See [`super::power_test`].
+    fn power_test(
+        a: &WeightedSite<Self>,
+        b: &WeightedSite<Self>,
+        c: &WeightedSite<Self>,
+        d: &WeightedSite<Self>,
+    ) -> Ordering {
+        predicates::power_test(
+            weighted_coords(a),
+            weighted_coords(b),
+            weighted_coords(c),
+            weighted_coords(d),
+        )
+    }
+
+    /// See [`super::power_test_collinear`].
+    fn power_test_collinear(
+        a: &WeightedSite<Self>,
+        b: &WeightedSite<Self>,
+        d: &WeightedSite<Self>,
+    ) -> Ordering {
+        predicates::power_test_collinear(weighted_coords(a), weighted_coords(b), weighted_coords(d))
+    }
+
+    /// See [`super::compare_to_breakpoint`].
+    fn compare_to_breakpoint(p: &Point<Self>, l: &Point<Self>, r: &Point<Self>) -> Ordering {
+        predicates::compare_to_breakpoint(coords(p), coords(l), coords(r))
+    }
+
+    /// See [`super::compare_to_power_breakpoint`].
+    fn compare_to_power_breakpoint(
+        p: &Point<Self>,
+        l: &WeightedSite<Self>,
+        r: &WeightedSite<Self>,
+        top: &Self,
+    ) -> Ordering {
+        predicates::compare_to_power_breakpoint(
+            coords(p),
+            weighted_coords(l),
+            weighted_coords(r),
+            top.to_f64(),
+        )
+    }
+}
+
+fn coords<S: Scalar>(p: &Point<S>) -> [f64; 2] {
+    [p.x.to_f64(), p.y.to_f64()]
+}
+
+fn weighted_coords<S: Scalar>(site: &WeightedSite<S>) -> [f64; 3] {
+    let [x, y] = coords(&site.point);
+    [x, y, site.weight.to_f64()]
+}
+
+impl Scalar for OrderedFloat<f64> {
+    fn from_f64(v: f64) -> Self {
+        OrderedFloat(v)
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.0
+    }
+
+    fn sqrt(&self) -> Self {
+        OrderedFloat(self.0.sqrt())
+    }
+
+    fn is_finite(&self) -> bool {
+        self.0.is_finite()
+    }
+}
+
+impl Scalar for OrderedFloat<f32> {
+    fn from_f64(v: f64) -> Self {
+        OrderedFloat(v as f32)
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.0.into()
+    }
+
+    fn sqrt(&self) -> Self {
+        OrderedFloat(self.0.sqrt())
+    }
+
+    fn is_finite(&self) -> bool {
+        self.0.is_finite()
+    }
+}
+
+/// Exact coordinates and exact predicates. Square roots still go through
+/// `f64`, and the sweep orders some of its events by heights that take one,
+/// so two events closer together than that rounding may be handled in the
+/// wrong order. The vertices are exact either way, but the topology is then
+/// no more certain than with `f64`.
+#[cfg(feature = "rational")]
+impl Scalar for num_rational::BigRational {
+    fn from_f64(v: f64) -> Self {
+        Self::from_float(v).expect("rationals only hold finite values")
+    }
+
+    fn to_f64(&self) -> f64 {
+        num_traits::ToPrimitive::to_f64(self).unwrap_or(f64::NAN)
+    }
+
+    fn sqrt(&self) -> Self {
+        Self::from_f64(Scalar::to_f64(self).sqrt())
+    }
+
+    fn is_finite(&self) -> bool {
+        true
+    }
+
+    fn orient2d(a: &Point<Self>, b: &Point<Self>, c: &Point<Self>) -> Ordering {
+        predicates::exact::orient2d(a, b, c)
+    }
+
+    fn incircle(a: &Point<Self>, b: &Point<Self>, c: &Point<Self>, d: &Point<Self>) -> Ordering {
+        predicates::exact::incircle(a, b, c, d)
+    }
+
+    fn power_test(
+        a: &WeightedSite<Self>,
+        b: &WeightedSite<Self>,
+        c: &WeightedSite<Self>,
+        d: &WeightedSite<Self>,
+    ) -> Ordering {
+        predicates::exact::power_test(a, b, c, d)
+    }
+
+    fn power_test_collinear(
+        a: &WeightedSite<Self>,
+        b: &WeightedSite<Self>,
+        d: &WeightedSite<Self>,
+    ) -> Ordering {
+        predicates::exact::power_test_collinear(a, b, d)
+    }
+
+    fn compare_to_breakpoint(p: &Point<Self>, l: &Point<Self>, r: &Point<Self>) -> Ordering {
+        predicates::exact::compare_to_breakpoint(p, l, r)
+    }
+
+    fn compare_to_power_breakpoint(
+        p: &Point<Self>,
+        l: &WeightedSite<Self>,
+        r: &WeightedSite<Self>,
+        top: &Self,
+    ) -> Ordering {
+        predicates::exact::compare_to_power_breakpoint(p, l, r, top)
+    }
+}
@@ -1,153 +1,474 @@
+pub use apollonius::apollonius_diagram;
+use diagram::Diagram;
+pub use error::Error;
+pub use farthest::farthest_point_diagram;
 use geometry::*;
-use ordered_float::OrderedFloat;
+pub use lloyd::lloyd_relax;
+pub use order_k::order_k_diagram;
+pub use power::power_diagram;
 use priority_queue::PriorityQueue;
+use std::cmp::{Ordering, Reverse};
 
-use crate::beachline::{Arc, Beachline, BreakPoint};
+use crate::{
+    beachline::{Arc, Beachline, BreakPoint},
+    delaunay::Triangulation,
+};
 
+pub mod apollonius;
 mod beachline;
+pub mod delaunay;
+pub mod diagram;
+mod error;
+pub mod farthest;
 pub mod geometry;
+pub mod lloyd;
+pub mod locate;
+mod metric;
+pub mod order_k;
+pub mod power;
 #[cfg(test)]
 mod test_utils;
 
 #[derive(Clone, Hash, PartialEq, Eq)]
 enum Event {
-    Site(Point),
-    /// Index of arc associated with circle event
-    Circle(usize),
+    /// Index of the site in the input
+    Site(usize),
+    /// Index of arc associated with circle event, and the generation of its
+    /// beachline slot when the event was queued
+    Circle(usize, u32),
+    /// Index of a site of a power diagram whose arc may come up through the
+    /// beachline, see [`power::power_diagram`]
+    Appear(usize),
 }
 
-type EventQueue = PriorityQueue<Event, OrderedFloat<f64>>;
+/// Events are handled from the highest y down. At the same y sites come
+/// before circle events, whose y is only rounded, and then ties go to the
+/// smallest x, so sites sharing a y coordinate enter the beachline from left
+/// to right.
+type Priority<S> = (S, bool, Reverse<S>);
+
+type EventQueue<S> = PriorityQueue<Event, Priority<S>>;
+
+/// Compute the Voronoi diagram of `sites`, cutting unbounded edges off at
+/// the boundary of `region`, usually a [`BoundingBox`].
+///
+/// Panics on input [`try_fortunes_algorithm`] rejects.
+pub fn fortunes_algorithm<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+) -> Diagram<S> {
+    try_fortunes_algorithm(sites, region).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`fortunes_algorithm`], but reports bad input as an [`Error`].
+///
+/// Sites equal to an earlier site are dropped from the sweep; their faces in
+/// the returned diagram stay empty, see [`Diagram::site_map`].
+pub fn try_fortunes_algorithm<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+) -> Result<Diagram<S>, Error> {
+    try_fortunes_algorithm_with_epsilon(sites, region, S::zero())
+}
+
+/// Like [`try_fortunes_algorithm`], but also merges sites lying within
+/// `epsilon` of an earlier site.
+pub fn try_fortunes_algorithm_with_epsilon<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+    epsilon: S,
+) -> Result<Diagram<S>, Error> {
+    if region.is_empty() || !epsilon.is_finite() {
+        return Err(Error::DegenerateInput);
+    }
+    for (idx, site) in sites.iter().enumerate() {
+        if !site.x.is_finite() || !site.y.is_finite() {
+            return Err(Error::NonFiniteSite(idx));
+        }
+        if !region.contains(site) {
+            return Err(Error::SiteOutsideBoundingBox(idx));
+        }
+    }
+
+    let site_map = dedup_sites(sites, epsilon);
 
-pub fn fortunes_algorithm(sites: &Vec<Point>, bounding_box: &BoundingBox) -> Vec<Segment> {
     let mut eq = EventQueue::new();
-    for site in sites {
-        eq.push(Event::Site(*site), site.y);
+    for (idx, site) in sites.iter().enumerate() {
+        if site_map[idx] == idx {
+            eq.push(
+                Event::Site(idx),
+                (site.y.clone(), true, Reverse(site.x.clone())),
+            );
+        }
     }
 
     let mut beachline = Beachline::new();
-    let mut res = vec![];
+    let mut diagram = Diagram::new(sites, site_map);
 
-    while let Some((e, yl)) = eq.pop() {
+    while let Some((e, (yl, ..))) = eq.pop() {
         match e {
-            Event::Site(site) => add_parabola(site, yl, &mut eq, &mut beachline),
-            Event::Circle(arc_idx) => {
-                remove_parabola(arc_idx, &mut eq, &mut beachline, &mut res, yl)
+            Event::Site(site_idx) => add_parabola(
+                &sites[site_idx],
+                site_idx,
+                yl,
+                &mut eq,
+                &mut beachline,
+                &mut diagram,
+            )?,
+            Event::Circle(arc_idx, generation) => {
+                if beachline.generation(arc_idx) != generation {
+                    return Err(Error::InvariantViolation("stale circle event"));
+                }
+                remove_parabola(arc_idx, &mut eq, &mut beachline, &mut diagram, yl)?
+            }
+            Event::Appear(_) => {
+                return Err(Error::InvariantViolation("appearing arc without weights"))
             }
         }
     }
 
-    beachline.extend_edges_to_bounding_box(bounding_box, &mut res);
+    close_diagram(sites, region, &beachline, &mut diagram);
+    Ok(diagram)
+}
 
-    res
+/// Like [`try_fortunes_algorithm`], but with distances to the sites
+/// measured by `metric`.
+///
+/// Under [`Metric::Manhattan`] and [`Metric::Chebyshev`] edges are bisectors
+/// bending where they change between running along an axis and diagonally,
+/// with a vertex of the diagram at every bend. Two sites can also be equally
+/// far from whole quadrants of the plane, under L1 when they are as far
+/// apart horizontally as vertically, and under L∞ when they lie on a line
+/// along an axis. Such ties go to the site closer in plain distance, which
+/// makes the edge between them the plain bisector. Diagrams under these
+/// metrics cannot be updated in place, see [`Diagram::insert_site`].
+pub fn try_fortunes_algorithm_with_metric<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+    metric: Metric,
+) -> Result<Diagram<S>, Error> {
+    match metric {
+        Metric::Euclidean => try_fortunes_algorithm(sites, region),
+        metric => metric::metric_diagram(sites, region, metric),
+    }
+}
+
+/// Compute the Delaunay triangulation of `sites`, the dual of their Voronoi
+/// diagram, see [`Diagram::delaunay_triangulation`].
+pub fn delaunay_triangulation<S: Scalar>(sites: &[Point<S>]) -> Result<Triangulation, Error> {
+    let Some(bounding_box) = enclosing_box(sites)? else {
+        return Ok(Triangulation::new(vec![], []));
+    };
+    Ok(try_fortunes_algorithm(sites, &bounding_box)?.delaunay_triangulation())
 }
 
-fn add_parabola(
-    site: Point,
-    yl: OrderedFloat<f64>,
-    eq: &mut EventQueue,
-    beachline: &mut Beachline,
+/// A box with room around `sites`, for diagrams only wanted for how their
+/// cells connect. `None` when there are no sites.
+pub(crate) fn enclosing_box<S: Scalar>(
+    sites: &[Point<S>],
+) -> Result<Option<BoundingBox<S>>, Error> {
+    if let Some(idx) = sites
+        .iter()
+        .position(|site| !site.x.is_finite() || !site.y.is_finite())
+    {
+        return Err(Error::NonFiniteSite(idx));
+    }
+    let Some(tight) = polygon_bounding_box(sites) else {
+        return Ok(None);
+    };
+    let [low, _, high, _] = tight.corners();
+    // any box around the sites does, as long as it has some area
+    let margin = high.x.clone() - low.x.clone() + high.y.clone() - low.y.clone() + S::from_f64(1.0);
+    Ok(Some(BoundingBox::new(
+        low.x - margin.clone(),
+        high.x + margin.clone(),
+        low.y - margin.clone(),
+        high.y + margin,
+    )))
+}
+
+/// Finish the diagram once the sweep has passed all events.
+fn close_diagram<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+    beachline: &Beachline<S>,
+    diagram: &mut Diagram<S>,
 ) {
-    if let Some((arc, arc_idx)) = beachline.arc_under_point(&site, yl) {
-        remove_circle_event(arc_idx, eq);
+    // only sites with unbounded cells still have arcs, and every corner of
+    // the hull is one of them
+    let arc_faces = beachline.arc_faces();
+    let arc_sites = arc_faces
+        .iter()
+        .map(|&face| sites[face].clone())
+        .collect::<Vec<_>>();
+    diagram.set_convex_hull(
+        convex_hull(&arc_sites)
+            .into_iter()
+            .map(|idx| arc_faces[idx])
+            .collect(),
+    );
 
-        let a = Arc::new(arc.site);
-        let b = Arc::new(site);
-        let c = Arc::new(arc.site);
+    beachline.extend_edges_to_boundary(region, diagram);
+    extend_open_origins(region, diagram);
+    merge_cocircular_vertices(diagram);
+}
 
-        let edge_origin = point_on_arc_at_x(&arc.site, yl, site.x);
-        let xl = BreakPoint::new(edge_origin, normal_vector(a.site - b.site), a.site, b.site);
-        let xr = BreakPoint::new(edge_origin, normal_vector(b.site - c.site), b.site, c.site);
+fn add_parabola<S: Scalar>(
+    site: &Point<S>,
+    site_idx: usize,
+    yl: S,
+    eq: &mut EventQueue<S>,
+    beachline: &mut Beachline<S>,
+    diagram: &mut Diagram<S>,
+) -> Result<(), Error> {
+    if let Some((arc, arc_idx)) = beachline.arc_under_point(site)? {
+        if arc.site.y == yl {
+            // Still on the first row of sites, and arriving from the left:
+            // the new arc only borders the rightmost one, along a vertical
+            // bisector reaching up to infinity
+            let a = Arc::new(arc.site.clone(), arc.face);
+            let b = Arc::new(site.clone(), site_idx);
+            add_arc_right(arc_idx, a, b, yl, eq, beachline, diagram)?;
+            return Ok(());
+        }
 
-        beachline.replace_arc(arc_idx, a, xl, b, xr, c, eq, yl);
+        split_arc(arc_idx, site, site_idx, yl, eq, beachline, diagram)
     } else {
-        beachline.add_first_parabola(site);
+        beachline.add_first_parabola(Arc::new(site.clone(), site_idx))?;
+        Ok(())
     }
 }
 
-fn remove_parabola(
+/// Put `b` next to the arc `a` at `arc_idx`, along a vertical edge reaching
+/// up to infinity, returning the index of `b`.
+fn add_arc_right<S: Scalar>(
     arc_idx: usize,
-    eq: &mut EventQueue,
-    beachline: &mut Beachline,
-    segments: &mut Vec<Segment>,
-    yl: OrderedFloat<f64>,
-) {
-    let p = beachline.arc(arc_idx);
-    let (l, l_idx) = beachline.left_arc(arc_idx).unwrap();
-    let (r, r_idx) = beachline.right_arc(arc_idx).unwrap();
-    remove_circle_event(l_idx, eq);
-    remove_circle_event(r_idx, eq);
+    a: Arc<S>,
+    b: Arc<S>,
+    yl: S,
+    eq: &mut EventQueue<S>,
+    beachline: &mut Beachline<S>,
+    diagram: &mut Diagram<S>,
+) -> Result<usize, Error> {
+    let (_, b_edge) = diagram.add_edge(a.face, b.face);
+    let x = BreakPoint::new(&a, &b, b_edge);
+    beachline.add_arc_right(arc_idx, x, b, eq, yl)
+}
+
+/// Split the arc at `arc_idx` in two around a new arc of `site`, which
+/// comes up right below it.
+fn split_arc<S: Scalar>(
+    arc_idx: usize,
+    site: &Point<S>,
+    site_idx: usize,
+    yl: S,
+    eq: &mut EventQueue<S>,
+    beachline: &mut Beachline<S>,
+    diagram: &mut Diagram<S>,
+) -> Result<(), Error> {
+    remove_circle_event(arc_idx, beachline, eq);
+
+    let arc = beachline.arc(arc_idx)?;
+    let a = Arc::new(arc.site.clone(), arc.face);
+    let b = Arc::new(site.clone(), site_idx);
+    let c = Arc::new(arc.site.clone(), arc.face);
+
+    // xl traces the edge towards the face of b, xr back towards a
+    let (a_edge, b_edge) = diagram.add_edge(a.face, b.face);
+
+    let xl = BreakPoint::new(&a, &b, b_edge);
+    let xr = BreakPoint::new(&b, &c, a_edge);
+
+    beachline.replace_arc(arc_idx, a, xl, b, xr, c, eq, yl)
+}
+
+fn remove_parabola<S: Scalar>(
+    arc_idx: usize,
+    eq: &mut EventQueue<S>,
+    beachline: &mut Beachline<S>,
+    diagram: &mut Diagram<S>,
+    yl: S,
+) -> Result<(), Error> {
+    let p = beachline.arc(arc_idx)?;
+    let (l, l_idx) = beachline
+        .left_arc(arc_idx)?
+        .ok_or(Error::InvariantViolation("circle event without left arc"))?;
+    let (r, r_idx) = beachline
+        .right_arc(arc_idx)?
+        .ok_or(Error::InvariantViolation("circle event without right arc"))?;
+    remove_circle_event(l_idx, beachline, eq);
+    remove_circle_event(r_idx, beachline, eq);
+
+    let s = beachline.vertex(l, p, r).ok_or(Error::DegenerateInput)?;
+
+    let (xl, xl_idx) = beachline
+        .left_edge(arc_idx)
+        .ok_or(Error::InvariantViolation("circle event without left edge"))?;
+    let (xr, xr_idx) = beachline
+        .right_edge(arc_idx)
+        .ok_or(Error::InvariantViolation("circle event without right edge"))?;
 
-    let s = circumcenter(&l.site, &p.site, &r.site);
+    // Both edges traced by the disappearing arc end at s, and a new edge
+    // between l and r starts there
+    let l_out = diagram.twin(xl.edge);
+    let p_out = diagram.twin(xr.edge);
+    let v = diagram.add_vertex(s, l_out);
+    diagram.set_origin(p_out, v);
 
-    let (xl, xl_idx) = beachline.left_edge(arc_idx).unwrap();
-    let (xr, xr_idx) = beachline.right_edge(arc_idx).unwrap();
+    let (l_in, r_out) = diagram.add_edge(l.face, r.face);
+    diagram.set_origin(r_out, v);
 
-    segments.push([xl.origin, s]);
-    segments.push([xr.origin, s]);
+    diagram.link(xl.edge, p_out);
+    diagram.link(l_in, l_out);
+    diagram.link(xr.edge, r_out);
 
-    let x = BreakPoint::new(s, normal_vector(l.site - r.site), l.site, r.site);
-    beachline.replace_breakpoint(xl_idx, arc_idx, xr_idx, x, eq, yl);
+    let x = BreakPoint::new(l, r, r_out);
+    beachline.replace_breakpoint(xl_idx, arc_idx, xr_idx, x, eq, yl)
 }
 
-fn remove_circle_event(arc_idx: usize, eq: &mut EventQueue) {
-    let circle = Event::Circle(arc_idx);
+/// Give a vertex on the boundary of `region` to every edge still missing its
+/// origin. Only the vertical edges between the first row of sites are left
+/// like this by the sweep.
+fn extend_open_origins<S: Scalar, R: ClipRegion<S>>(region: &R, diagram: &mut Diagram<S>) {
+    for edge in 0..diagram.half_edges().len() {
+        let half_edge = diagram.half_edge(edge);
+        if half_edge.origin.is_some() {
+            continue;
+        }
+        let Some(destination) = diagram.destination(edge) else {
+            continue;
+        };
+
+        let site = diagram.face(half_edge.face).site.clone();
+        let other = diagram
+            .face(diagram.half_edge(half_edge.twin).face)
+            .site
+            .clone();
+        let backwards = normal_vector(site - other);
+
+        let end = region.ray_end(&diagram.vertex(destination).point, &backwards);
+        diagram.add_vertex(end, edge);
+    }
+}
+
+/// Four or more cocircular sites produce one circle event per disappearing
+/// arc, all at the same center. Collapse the zero-length edges between those
+/// vertices so each such center ends up as a single vertex.
+///
+/// An edge between the circle event vertices of sites `a`, `b`, `c` and of
+/// `b`, `a`, `d` has zero length exactly when the four sites are cocircular,
+/// or in a power diagram have a point of equal power distance to all four.
+fn merge_cocircular_vertices<S: Scalar>(diagram: &mut Diagram<S>) {
+    let site = |edge: usize| {
+        let face = diagram.face(diagram.half_edge(edge).face);
+        WeightedSite::new(face.site.clone(), face.weight.clone())
+    };
+    let short_edges = (0..diagram.half_edges().len())
+        .filter(|&edge| edge < diagram.twin(edge))
+        .filter(|&edge| {
+            let half_edge = diagram.half_edge(edge);
+            let (Some(prev), Some(next)) = (half_edge.prev, half_edge.next) else {
+                return false;
+            };
+            // the faces across the edges meeting this one at either end
+            let c = site(diagram.twin(prev));
+            let d = site(diagram.twin(next));
+            let (a, b) = (site(edge), site(diagram.twin(edge)));
+            power_test(&a, &b, &c, &d) == Ordering::Equal
+        })
+        .collect::<Vec<_>>();
+    diagram.collapse_edges(&short_edges);
+    diagram.find_chains();
+}
+
+fn remove_circle_event<S: Scalar>(
+    arc_idx: usize,
+    beachline: &Beachline<S>,
+    eq: &mut EventQueue<S>,
+) {
+    let circle = Event::Circle(arc_idx, beachline.generation(arc_idx));
     eq.remove(&circle);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::test_utils::compare_edges;
+    use crate::test_utils::{
+        bounding_box, check_cells, check_diagram, check_diagram_with_tolerance, compare_edges,
+        compare_points, compare_segments, random_sites,
+    };
+
+    use ordered_float::OrderedFloat;
 
     use super::*;
 
     #[test]
     fn vertical_line() {
-        let bounding_box = BoundingBox::new(0.0.into(), 1000.0.into(), 0.0.into(), 1000.0.into());
+        let bounding_box = bounding_box();
 
         let sites = vec![
-            Point::new(250.0.into(), 500.0.into()),
-            Point::new(750.0.into(), 500.0.into()),
+            Point::new(OrderedFloat(250.0), OrderedFloat(500.0)),
+            Point::new(OrderedFloat(750.0), OrderedFloat(500.0)),
         ];
 
-        let voronoi = fortunes_algorithm(&sites, &bounding_box);
+        let voronoi = fortunes_algorithm(&sites, &bounding_box).segments();
 
-        assert_eq!(voronoi.len(), 2);
+        assert_eq!(voronoi.len(), 1);
 
-        let gold = [
-            [
-                Point::new(500.0.into(), 500.0.into()),
-                Point::new(500.0.into(), 1000.0.into()),
-            ],
-            [
-                Point::new(500.0.into(), 500.0.into()),
-                Point::new(500.0.into(), 0.0.into()),
-            ],
-        ];
+        let gold = [[
+            Point::new(OrderedFloat(500.0), OrderedFloat(0.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(1000.0)),
+        ]];
 
         assert!(compare_edges(&gold, &voronoi));
     }
 
     #[test]
     fn horizontal_line() {
-        let bounding_box = BoundingBox::new(0.0.into(), 1000.0.into(), 0.0.into(), 1000.0.into());
+        let bounding_box = bounding_box();
 
         let sites = vec![
-            Point::new(500.0.into(), 250.0.into()),
-            Point::new(500.0.into(), 750.0.into()),
+            Point::new(OrderedFloat(500.0), OrderedFloat(250.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(750.0)),
         ];
 
-        let voronoi = fortunes_algorithm(&sites, &bounding_box);
+        let voronoi = fortunes_algorithm(&sites, &bounding_box).segments();
+
+        assert_eq!(voronoi.len(), 1);
 
-        assert_eq!(voronoi.len(), 2);
+        let gold = [[
+            Point::new(OrderedFloat(0.0), OrderedFloat(500.0)),
+            Point::new(OrderedFloat(1000.0), OrderedFloat(500.0)),
+        ]];
+
+        assert!(compare_edges(&gold, &voronoi));
+    }
+
+    #[test]
+    fn three_points() {
+        let bounding_box = bounding_box();
+
+        let sites = vec![
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(300.0), OrderedFloat(300.0)),
+        ];
+
+        let voronoi = fortunes_algorithm(&sites, &bounding_box).segments();
+
+        assert_eq!(voronoi.len(), 3);
 
         let gold = [
             [
-                Point::new(500.0.into(), 500.0.into()),
-                Point::new(1000.0.into(), 500.0.into()),
+                Point::new(OrderedFloat(300.0), OrderedFloat(100.0)),
+                Point::new(OrderedFloat(0.0), OrderedFloat(400.0)),
+            ],
+            [
+                Point::new(OrderedFloat(300.0), OrderedFloat(100.0)),
+                Point::new(OrderedFloat(300.0), OrderedFloat(0.0)),
             ],
             [
-                Point::new(500.0.into(), 500.0.into()),
-                Point::new(0.0.into(), 500.0.into()),
+                Point::new(OrderedFloat(300.0), OrderedFloat(100.0)),
+                Point::new(OrderedFloat(1000.0), OrderedFloat(800.0)),
             ],
         ];
 
@@ -155,42 +476,848 @@ mod tests {
     }
 
     #[test]
-    fn three_points() {
-        let bounding_box = BoundingBox::new(0.0.into(), 1000.0.into(), 0.0.into(), 1000.0.into());
+    fn three_points_topology() {
+        let bounding_box = bounding_box();
+
+        let sites = vec![
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(300.0), OrderedFloat(300.0)),
+        ];
+
+        let diagram = fortunes_algorithm(&sites, &bounding_box);
+
+        assert_eq!(diagram.faces().len(), 3);
+        assert_eq!(diagram.half_edges().len(), 6);
+
+        let vertex = Point::new(OrderedFloat(300.0), OrderedFloat(100.0));
+        for (face_idx, face) in diagram.faces().iter().enumerate() {
+            assert_eq!(face.site, sites[face_idx]);
+
+            // every cell is unbounded and bounded by two edges meeting at
+            // the single Voronoi vertex
+            let boundary = diagram.boundary(face_idx);
+            assert_eq!(boundary.len(), 2);
+            let [e1, e2] = [boundary[0], boundary[1]];
+            assert_eq!(diagram.half_edge(e1).next, Some(e2));
+            assert_eq!(diagram.half_edge(e2).prev, Some(e1));
+            assert_eq!(diagram.half_edge(e1).face, face_idx);
+            assert_eq!(diagram.half_edge(e2).face, face_idx);
+
+            let meet = diagram.destination(e1).unwrap();
+            assert_eq!(diagram.half_edge(e2).origin, Some(meet));
+            assert!(compare_points(&diagram.vertex(meet).point, &vertex));
+
+            // neighbors across both edges are the two other sites
+            let mut neighbors = boundary
+                .iter()
+                .map(|&e| diagram.half_edge(diagram.twin(e)).face)
+                .collect::<Vec<_>>();
+            neighbors.sort();
+            let expected = (0..3).filter(|&i| i != face_idx).collect::<Vec<_>>();
+            assert_eq!(neighbors, expected);
+        }
+    }
+
+    #[test]
+    fn random_sites_are_consistent() {
+        let bounding_box = bounding_box();
+
+        for seed in 0..20 {
+            let sites = random_sites(50, 1000.0, seed);
+            let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+            assert!(check_diagram(&sites, &diagram), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bounding_box = bounding_box();
 
         let sites = vec![
-            Point::new(100.0.into(), 100.0.into()),
-            Point::new(500.0.into(), 100.0.into()),
-            Point::new(300.0.into(), 300.0.into()),
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(f64::NAN), OrderedFloat(100.0)),
         ];
+        assert_eq!(
+            try_fortunes_algorithm(&sites, &bounding_box).err(),
+            Some(Error::NonFiniteSite(1))
+        );
 
-        let voronoi = fortunes_algorithm(&sites, &bounding_box);
+        let sites = vec![
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(100.0), OrderedFloat(f64::INFINITY)),
+        ];
+        assert_eq!(
+            try_fortunes_algorithm(&sites, &bounding_box).err(),
+            Some(Error::NonFiniteSite(1))
+        );
 
-        assert_eq!(voronoi.len(), 5);
+        let sites = vec![
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(100.0), OrderedFloat(2000.0)),
+        ];
+        assert_eq!(
+            try_fortunes_algorithm(&sites, &bounding_box).err(),
+            Some(Error::SiteOutsideBoundingBox(1))
+        );
+
+        let empty_box = BoundingBox::new(
+            OrderedFloat(0.0),
+            OrderedFloat(0.0),
+            OrderedFloat(0.0),
+            OrderedFloat(1000.0),
+        );
+        assert_eq!(
+            try_fortunes_algorithm(&[], &empty_box).err(),
+            Some(Error::DegenerateInput)
+        );
+    }
+
+    #[test]
+    fn duplicate_sites() {
+        let bounding_box = bounding_box();
+
+        let sites = vec![
+            Point::new(OrderedFloat(300.0), OrderedFloat(300.0)),
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(300.0), OrderedFloat(300.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(300.0), OrderedFloat(300.0)),
+        ];
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+
+        assert_eq!(diagram.site_map(), &[0, 1, 0, 3, 1, 0]);
+        assert_eq!(diagram.faces().len(), sites.len());
+        for duplicate in [2, 4, 5] {
+            assert!(diagram.boundary(duplicate).is_empty());
+        }
 
         let gold = [
             [
-                Point::new(100.0.into(), 300.0.into()),
-                Point::new(300.0.into(), 100.0.into()),
-            ],
-            [
-                Point::new(500.0.into(), 300.0.into()),
-                Point::new(300.0.into(), 100.0.into()),
+                Point::new(OrderedFloat(300.0), OrderedFloat(100.0)),
+                Point::new(OrderedFloat(0.0), OrderedFloat(400.0)),
             ],
             [
-                Point::new(100.0.into(), 300.0.into()),
-                Point::new(0.0.into(), 400.0.into()),
+                Point::new(OrderedFloat(300.0), OrderedFloat(100.0)),
+                Point::new(OrderedFloat(300.0), OrderedFloat(0.0)),
             ],
             [
-                Point::new(300.0.into(), 100.0.into()),
-                Point::new(300.0.into(), 0.0.into()),
+                Point::new(OrderedFloat(300.0), OrderedFloat(100.0)),
+                Point::new(OrderedFloat(1000.0), OrderedFloat(800.0)),
             ],
+        ];
+        assert!(compare_edges(&gold, &diagram.segments()));
+    }
+
+    #[test]
+    fn near_duplicate_sites() {
+        let bounding_box = bounding_box();
+
+        let sites = vec![
+            Point::new(OrderedFloat(250.0), OrderedFloat(500.0)),
+            Point::new(OrderedFloat(750.0), OrderedFloat(500.0)),
+            Point::new(OrderedFloat(250.001), OrderedFloat(499.999)),
+        ];
+
+        let diagram =
+            try_fortunes_algorithm_with_epsilon(&sites, &bounding_box, OrderedFloat(0.01)).unwrap();
+
+        assert_eq!(diagram.site_map(), &[0, 1, 0]);
+        let gold = [[
+            Point::new(OrderedFloat(500.0), OrderedFloat(0.0)),
+            Point::new(OrderedFloat(500.0), OrderedFloat(1000.0)),
+        ]];
+        assert!(compare_edges(&gold, &diagram.segments()));
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert_eq!(diagram.site_map(), &[0, 1, 2]);
+    }
+
+    fn check_horizontal_row(n: usize) {
+        let bounding_box = bounding_box();
+
+        // shuffle the input order so the sweep has to sort the row itself
+        let spacing = 1000.0 / n as f64;
+        let sites = (0..n)
+            .map(|i| (i * 7) % n)
+            .map(|i| {
+                Point::new(
+                    OrderedFloat((i as f64 + 0.5) * spacing),
+                    OrderedFloat(500.0),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert!(check_diagram(&sites, &diagram));
+
+        let mut voronoi = diagram.segments();
+        assert_eq!(voronoi.len(), n - 1);
+
+        voronoi.sort_by_key(|[a, _]| a.x);
+        for (i, segment) in voronoi.iter().enumerate() {
+            let x = ((i as f64 + 0.5) * spacing + (i as f64 + 1.5) * spacing) / 2.0;
+            let x = OrderedFloat(x);
+            let gold = [
+                Point::new(x, OrderedFloat(0.0)),
+                Point::new(x, OrderedFloat(1000.0)),
+            ];
+            assert!(compare_segments(&gold, segment));
+        }
+    }
+
+    #[test]
+    fn horizontal_row_of_3() {
+        check_horizontal_row(3);
+    }
+
+    #[test]
+    fn horizontal_row_of_10() {
+        check_horizontal_row(10);
+    }
+
+    #[test]
+    fn horizontal_row_of_100() {
+        check_horizontal_row(100);
+    }
+
+    #[test]
+    fn horizontal_row_above_other_sites() {
+        let bounding_box = bounding_box();
+
+        let mut sites = (0..10)
+            .map(|i| Point::new(OrderedFloat(i as f64 * 100.0 + 50.0), OrderedFloat(900.0)))
+            .collect::<Vec<_>>();
+        sites.extend(random_sites(40, 800.0, 7));
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert!(check_diagram(&sites, &diagram));
+    }
+
+    fn square_grid<S: Scalar>(k: usize) -> Vec<Point<S>> {
+        let spacing = 1000.0 / k as f64;
+        (0..k * k)
+            .map(|i| {
+                let x = ((i % k) as f64 + 0.5) * spacing;
+                let y = ((i / k) as f64 + 0.5) * spacing;
+                Point::new(S::from_f64(x), S::from_f64(y))
+            })
+            .collect()
+    }
+
+    fn check_square_grid<S: Scalar>(k: usize) {
+        let bounding_box = bounding_box::<S>();
+        let sites = square_grid::<S>(k);
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert!(check_diagram(&sites, &diagram));
+
+        // (k - 1)^2 inner vertices of degree 4, plus two on the box for
+        // each of the 2 (k - 1) grid lines
+        let inner = (k - 1) * (k - 1);
+        assert_eq!(diagram.vertices().len(), inner + 4 * (k - 1));
+        assert_eq!(diagram.segments().len(), 2 * k * (k - 1));
+
+        let mut degrees = vec![0; diagram.vertices().len()];
+        for edge in diagram.half_edges() {
+            degrees[edge.origin.unwrap()] += 1;
+        }
+        assert_eq!(degrees.iter().filter(|&&d| d == 4).count(), inner);
+
+        // inner cells are closed squares
+        for row in 1..k - 1 {
+            for col in 1..k - 1 {
+                assert_eq!(diagram.boundary(row * k + col).len(), 4);
+            }
+        }
+    }
+
+    #[test]
+    fn square_grid_2() {
+        check_square_grid::<OrderedFloat<f64>>(2);
+    }
+
+    #[test]
+    fn square_grid_3() {
+        check_square_grid::<OrderedFloat<f64>>(3);
+    }
+
+    #[test]
+    fn square_grid_10() {
+        check_square_grid::<OrderedFloat<f64>>(10);
+    }
+
+    #[test]
+    fn f32_square_grid() {
+        check_square_grid::<OrderedFloat<f32>>(10);
+    }
+
+    #[test]
+    fn f32_random_sites() {
+        let bounding_box = bounding_box::<OrderedFloat<f32>>();
+
+        for seed in 0..20 {
+            let sites = random_sites(50, 1000.0, seed)
+                .iter()
+                .map(|p| Point::new(OrderedFloat(p.x.0 as f32), OrderedFloat(p.y.0 as f32)))
+                .collect::<Vec<_>>();
+            let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+            assert!(
+                check_diagram_with_tolerance(&sites, &diagram, 1e-3),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_square_grid() {
+        check_square_grid::<num_rational::BigRational>(5);
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_vertices_are_exact() {
+        use crate::test_utils::squared_distance;
+        use num_rational::BigRational;
+
+        let r = |v: f64| BigRational::from_float(v).unwrap();
+        let bounding_box = bounding_box::<BigRational>();
+
+        for seed in 0..5 {
+            let sites = random_sites(30, 1000.0, seed)
+                .iter()
+                .map(|p| Point::new(r(p.x.0), r(p.y.0)))
+                .collect::<Vec<_>>();
+            let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+            assert!(check_diagram(&sites, &diagram), "seed {}", seed);
+
+            // every vertex lies exactly on the bisectors of its edges
+            for (idx, edge) in diagram.half_edges().iter().enumerate() {
+                let p = &sites[edge.face];
+                let q = &sites[diagram.half_edge(edge.twin).face];
+                for v in [edge.origin, diagram.destination(idx)] {
+                    let v = &diagram.vertex(v.unwrap()).point;
+                    assert_eq!(squared_distance(v, p), squared_distance(v, q));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cocircular_octagon() {
+        let bounding_box = bounding_box();
+
+        // the lattice points at distance 5 from the origin, bar the ones on
+        // the axes
+        let sites = [
+            (3, 4),
+            (4, 3),
+            (4, -3),
+            (3, -4),
+            (-3, -4),
+            (-4, -3),
+            (-4, 3),
+            (-3, 4),
+        ]
+        .iter()
+        .map(|&(x, y)| {
+            let x = 500.0 + 40.0 * x as f64;
+            let y = 500.0 + 40.0 * y as f64;
+            Point::new(OrderedFloat(x), OrderedFloat(y))
+        })
+        .collect::<Vec<_>>();
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert!(check_diagram(&sites, &diagram));
+
+        // one vertex of degree 8 in the middle, each edge running to the box
+        assert_eq!(diagram.vertices().len(), 9);
+        assert_eq!(diagram.segments().len(), 8);
+        let center = Point::new(OrderedFloat(500.0), OrderedFloat(500.0));
+        let inner = diagram
+            .vertices()
+            .iter()
+            .position(|v| compare_points(&v.point, &center))
+            .unwrap();
+        let degree = diagram
+            .half_edges()
+            .iter()
+            .filter(|e| e.origin == Some(inner))
+            .count();
+        assert_eq!(degree, 8);
+    }
+
+    /// Sites on a circle through sin and cos are only nearly cocircular, and
+    /// the plain floating point tests used to disagree on which way round
+    /// they are.
+    fn check_circle_of_sites(n: usize, center: bool) {
+        let bounding_box = bounding_box();
+
+        let mut sites = (0..n)
+            .map(|i| {
+                let angle = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+                let x = 500.0 + 200.0 * angle.cos();
+                let y = 500.0 + 200.0 * angle.sin();
+                Point::new(OrderedFloat(x), OrderedFloat(y))
+            })
+            .collect::<Vec<_>>();
+        if center {
+            sites.push(Point::new(OrderedFloat(500.0), OrderedFloat(500.0)));
+        }
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert!(check_diagram(&sites, &diagram));
+        for face in 0..sites.len() {
+            assert!(!diagram.boundary(face).is_empty());
+        }
+    }
+
+    #[test]
+    fn hexagon_around_center() {
+        check_circle_of_sites(6, true);
+    }
+
+    #[test]
+    fn nearly_cocircular_sites() {
+        for n in [5, 8, 12, 64, 100] {
+            check_circle_of_sites(n, false);
+        }
+    }
+
+    #[test]
+    fn perturbed_square_grid() {
+        let bounding_box = bounding_box();
+
+        // a grid with every site nudged by a few ulps, so that most circle
+        // events come out almost but not quite cocircular
+        let mut sites = vec![];
+        for x in 0..10 {
+            for y in 0..10 {
+                let nudge = |v: f64, k: i32| {
+                    let bits = v.to_bits() as i64 + (k % 3 - 1) as i64;
+                    f64::from_bits(bits as u64)
+                };
+                let px = nudge(100.0 * x as f64 + 50.0, x * 7 + y);
+                let py = nudge(100.0 * y as f64 + 50.0, x + y * 5);
+                sites.push(Point::new(OrderedFloat(px), OrderedFloat(py)));
+            }
+        }
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert!(check_diagram(&sites, &diagram));
+    }
+
+    #[test]
+    fn x_sorted_sites_stay_logarithmic() {
+        let bounding_box = bounding_box();
+
+        // each site lands right of and below all earlier ones, so every
+        // insertion splits the rightmost arc, and on an exact line no arc
+        // ever leaves
+        let n = 100_000;
+        let sites = (0..n)
+            .map(|i| {
+                let t = i as f64 / 128.0;
+                Point::new(OrderedFloat(10.0 + t), OrderedFloat(990.0 - t))
+            })
+            .collect::<Vec<_>>();
+
+        beachline::NODE_VISITS.with(|visits| visits.set(0));
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        let visits = beachline::NODE_VISITS.with(|visits| visits.get());
+
+        assert_eq!(diagram.segments().len(), n - 1);
+        // an unbalanced tree takes about n^2 / 2
+        let log_n = (n as f64).log2().ceil() as u64;
+        assert!(visits <= 8 * n as u64 * log_n, "{} node visits", visits);
+    }
+
+    #[test]
+    fn cells_of_square_grid() {
+        let bounding_box: BoundingBox = bounding_box();
+        let sites = square_grid(2);
+
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        let cells = diagram.cell_polygons(&bounding_box);
+        assert!(check_cells(&diagram, &cells, &bounding_box));
+
+        // every cell is a quarter of the box, one of its corners included
+        let corners = bounding_box.corners();
+        for (cell, corner) in cells.iter().zip([0, 1, 3, 2]) {
+            assert_eq!(cell.len(), 4);
+            assert_eq!(polygon_area(cell), OrderedFloat(250000.0));
+            assert!(cell.contains(&corners[corner]));
+        }
+    }
+
+    #[test]
+    fn cells_of_random_sites() {
+        let bounding_box = bounding_box();
+
+        for seed in 0..10 {
+            let sites = random_sites(100, 1000.0, seed);
+            let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+            let cells = diagram.cell_polygons(&bounding_box);
+            assert_eq!(cells.len(), sites.len());
+            assert!(check_cells(&diagram, &cells, &bounding_box));
+        }
+    }
+
+    #[test]
+    fn cells_of_few_sites() {
+        let bounding_box = BoundingBox::new(
+            OrderedFloat(0.0),
+            OrderedFloat(10.0),
+            OrderedFloat(0.0),
+            OrderedFloat(10.0),
+        );
+
+        let sites = [Point::new(OrderedFloat(3.0), OrderedFloat(4.0))];
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert_eq!(
+            diagram.cell_polygons(&bounding_box),
+            vec![bounding_box.corners().to_vec()]
+        );
+
+        // the duplicate is left without a cell, and its twin gets the half
+        // the two share
+        let sites = [
+            Point::new(OrderedFloat(3.0), OrderedFloat(4.0)),
+            Point::new(OrderedFloat(7.0), OrderedFloat(4.0)),
+            Point::new(OrderedFloat(3.0), OrderedFloat(4.0)),
+        ];
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        let cells = diagram.cell_polygons(&bounding_box);
+        assert!(check_cells(&diagram, &cells, &bounding_box));
+        assert!(cells[2].is_empty());
+        assert_eq!(polygon_area(&cells[0]), OrderedFloat(50.0));
+    }
+
+    fn hexagon() -> ConvexPolygon {
+        let corners = (0..6)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::PI / 3.0;
+                Point::new(
+                    OrderedFloat(500.0 + 500.0 * angle.cos()),
+                    OrderedFloat(500.0 + 500.0 * angle.sin()),
+                )
+            })
+            .collect();
+        ConvexPolygon::new(corners).unwrap()
+    }
+
+    #[test]
+    fn cells_of_hexagon() {
+        let region = hexagon();
+        for seed in 0..10 {
+            let sites = random_sites(100, 1000.0, seed)
+                .into_iter()
+                .filter(|site| region.contains(site))
+                .collect::<Vec<_>>();
+            let diagram = try_fortunes_algorithm(&sites, &region).unwrap();
+            assert!(check_diagram(&sites, &diagram));
+            assert!(check_cells(
+                &diagram,
+                &diagram.cell_polygons(&region),
+                &region
+            ));
+        }
+
+        let outside = [Point::new(OrderedFloat(10.0), OrderedFloat(10.0))];
+        assert_eq!(
+            try_fortunes_algorithm(&outside, &region).err(),
+            Some(Error::SiteOutsideBoundingBox(0))
+        );
+    }
+
+    #[test]
+    fn clip_segments_to_rotated_square() {
+        let corner = |x: f64, y: f64| Point::new(OrderedFloat(x), OrderedFloat(y));
+        let region = ConvexPolygon::new(vec![
+            corner(500.0, 0.0),
+            corner(1000.0, 500.0),
+            corner(500.0, 1000.0),
+            corner(0.0, 500.0),
+        ])
+        .unwrap();
+        // sites near the corners, whose cells meet far outside the square
+        let sites = [
+            corner(500.0, 10.0),
+            corner(990.0, 500.0),
+            corner(500.0, 990.0),
+            corner(10.0, 500.0),
+            corner(480.0, 520.0),
+        ];
+
+        let diagram = try_fortunes_algorithm(&sites, &region).unwrap();
+        assert!(check_cells(
+            &diagram,
+            &diagram.cell_polygons(&region),
+            &region
+        ));
+
+        let segments = diagram.clipped_segments(&region);
+        assert!(diagram
+            .segments()
+            .iter()
+            .any(|[a, b]| !region.contains(a) || !region.contains(b)));
+        for [a, b] in &segments {
+            for p in [a, b] {
+                // a rounded hair off the boundary at most
+                let dist = (p.x.0 - 500.0).abs() + (p.y.0 - 500.0).abs();
+                assert!(dist <= 500.0 + 1e-9);
+            }
+        }
+        // the edge between the two left sites runs outside the square
+        // entirely
+        assert_eq!(diagram.segments().len(), 8);
+        assert_eq!(segments.len(), 7);
+    }
+
+    #[test]
+    fn cells_in_courtyard_building() {
+        let bounding_box = bounding_box();
+        let ring = |points: &[(f64, f64)]| {
+            points
+                .iter()
+                .map(|&(x, y)| Point::new(OrderedFloat(x), OrderedFloat(y)))
+                .collect::<Vec<_>>()
+        };
+        // an L-shaped floor plan with a courtyard
+        let building = PolygonWithHoles::new(
+            ring(&[
+                (100.0, 100.0),
+                (900.0, 100.0),
+                (900.0, 500.0),
+                (500.0, 500.0),
+                (500.0, 900.0),
+                (100.0, 900.0),
+            ]),
+            vec![ring(&[
+                (200.0, 200.0),
+                (400.0, 200.0),
+                (400.0, 400.0),
+                (200.0, 400.0),
+            ])],
+        )
+        .unwrap();
+        let area = |polygon: &PolygonWithHoles| {
+            polygon_area(polygon.outer()).0
+                + polygon
+                    .holes()
+                    .iter()
+                    .map(|hole| polygon_area(hole).0)
+                    .sum::<f64>()
+        };
+
+        for seed in 0..10 {
+            let mut sites = random_sites(50, 1000.0, seed);
+            // one site in the courtyard, and one outside the building
+            sites.push(Point::new(OrderedFloat(300.0), OrderedFloat(300.0)));
+            sites.push(Point::new(OrderedFloat(950.0), OrderedFloat(950.0)));
+
+            let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+            let cells = diagram.cells_in_polygon(&building);
+            assert_eq!(cells.parts.len(), sites.len());
+            let total = cells.parts.iter().flatten().map(area).sum::<f64>();
+            assert!((total - area(&building)).abs() < 1e-6);
+            for (face, parts) in cells.parts.iter().enumerate() {
+                assert_eq!(parts.is_empty(), cells.empty.contains(&face));
+            }
+            assert!(cells.empty.contains(&(sites.len() - 1)));
+        }
+    }
+
+    #[test]
+    fn delaunay_of_random_sites() {
+        for seed in 0..10 {
+            let sites = random_sites(100, 1000.0, seed);
+            let triangulation = delaunay_triangulation(&sites).unwrap();
+
+            for &[a, b, c] in triangulation.triangles() {
+                let (a, b, c) = (&sites[a], &sites[b], &sites[c]);
+                assert_eq!(orient2d(a, b, c), Ordering::Greater);
+                assert!(sites
+                    .iter()
+                    .all(|d| incircle(a, b, c, d) != Ordering::Greater));
+            }
+            // Euler's formula, with no three sites on a line
+            let hull = triangulation.hull_edges().len();
+            assert_eq!(triangulation.triangles().len(), 2 * sites.len() - 2 - hull);
+        }
+    }
+
+    #[test]
+    fn delaunay_of_square_grid() {
+        let sites = square_grid::<OrderedFloat<f64>>(3);
+        let triangulation = delaunay_triangulation(&sites).unwrap();
+
+        // every square of four cocircular sites is split in two
+        assert_eq!(triangulation.triangles().len(), 8);
+        assert_eq!(
+            triangulation.hull_edges(),
             [
-                Point::new(500.0.into(), 300.0.into()),
-                Point::new(1000.0.into(), 800.0.into()),
-            ],
+                [0, 1],
+                [1, 2],
+                [2, 5],
+                [3, 0],
+                [5, 8],
+                [6, 3],
+                [7, 6],
+                [8, 7]
+            ]
+        );
+    }
+
+    #[test]
+    fn delaunay_of_collinear_sites() {
+        let sites = (0..4)
+            .map(|i| Point::new(OrderedFloat(i as f64), OrderedFloat(2.0 * i as f64)))
+            .collect::<Vec<_>>();
+        let triangulation = delaunay_triangulation(&sites).unwrap();
+
+        assert!(triangulation.triangles().is_empty());
+        assert_eq!(
+            triangulation.hull_edges(),
+            [[0, 1], [1, 0], [1, 2], [2, 1], [2, 3], [3, 2]]
+        );
+
+        assert!(delaunay_triangulation::<OrderedFloat<f64>>(&[])
+            .unwrap()
+            .triangles()
+            .is_empty());
+        let bad = [Point::new(OrderedFloat(f64::NAN), OrderedFloat(0.0))];
+        assert_eq!(
+            delaunay_triangulation(&bad).err(),
+            Some(Error::NonFiniteSite(0))
+        );
+    }
+
+    #[test]
+    fn convex_hull_from_beachline() {
+        let bounding_box = bounding_box();
+
+        for seed in 0..20 {
+            let sites = random_sites(100, 1000.0, seed);
+            let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+            assert_eq!(diagram.convex_hull(), convex_hull(&sites));
+        }
+
+        // sites along the sides of the hull, and first rows of several
+        let mut sites = square_grid(10);
+        let shift = Point::new(OrderedFloat(100.0), OrderedFloat(100.0));
+        sites.extend(random_sites(20, 800.0, 3).into_iter().map(|p| p + shift));
+        sites.push(sites[7]);
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert_eq!(diagram.convex_hull(), [0, 9, 99, 90]);
+        assert_eq!(diagram.convex_hull(), convex_hull(&sites));
+
+        let sites = [
+            Point::new(OrderedFloat(500.0), OrderedFloat(900.0)),
+            Point::new(OrderedFloat(100.0), OrderedFloat(100.0)),
+            Point::new(OrderedFloat(900.0), OrderedFloat(100.0)),
         ];
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        assert_eq!(diagram.convex_hull(), [1, 2, 0]);
+    }
 
-        assert!(compare_edges(&gold, &voronoi));
+    #[test]
+    fn adjacency_of_square_grid() {
+        let bounding_box: BoundingBox = bounding_box();
+        let sites = square_grid(3);
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+
+        // diagonal neighbours only meet at a vertex
+        let faces = |face| {
+            let mut faces = diagram
+                .neighbours(face)
+                .iter()
+                .map(|neighbour| neighbour.face)
+                .collect::<Vec<_>>();
+            faces.sort();
+            faces
+        };
+        assert_eq!(faces(4), [1, 3, 5, 7]);
+        assert_eq!(faces(0), [1, 3]);
+        assert_eq!(faces(5), [2, 4, 8]);
+    }
+
+    #[test]
+    fn adjacency_of_collinear_sites() {
+        let bounding_box = bounding_box();
+        let row = |(x, y): (f64, f64), (dx, dy): (f64, f64), n: usize| {
+            (0..n)
+                .map(|idx| {
+                    let idx = idx as f64;
+                    Point::new(OrderedFloat(x + dx * idx), OrderedFloat(y + dy * idx))
+                })
+                .collect::<Vec<_>>()
+        };
+        let check = |sites: &[Point]| {
+            let diagram = try_fortunes_algorithm(sites, &bounding_box).unwrap();
+            let adjacency = diagram.adjacency();
+            for (face, neighbours) in adjacency.iter().enumerate() {
+                // the cells in the middle are strips with an edge on either
+                // side, which do not link up
+                let mut faces = neighbours
+                    .iter()
+                    .map(|neighbour| neighbour.face)
+                    .collect::<Vec<_>>();
+                faces.sort();
+                let expected = (face.saturating_sub(1)..sites.len().min(face + 2))
+                    .filter(|&other| other != face)
+                    .collect::<Vec<_>>();
+                assert_eq!(faces, expected);
+                for neighbour in neighbours {
+                    assert_eq!(diagram.half_edge(neighbour.edge).face, face);
+                }
+                let edges = diagram
+                    .neighbours(face)
+                    .iter()
+                    .map(|neighbour| neighbour.edge)
+                    .collect::<Vec<_>>();
+                assert_eq!(edges, neighbours.iter().map(|n| n.edge).collect::<Vec<_>>());
+            }
+        };
+
+        check(&row((100.0, 500.0), (400.0, 0.0), 3));
+        check(&row((100.0, 500.0), (200.0, 0.0), 5));
+        check(&row((100.0, 150.0), (150.0, 120.0), 6));
+    }
+
+    #[test]
+    fn adjacency_matches_delaunay() {
+        let bounding_box = bounding_box();
+        let mut sites = random_sites(100, 1000.0, 5);
+        sites.push(sites[3]);
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        let adjacency = diagram.adjacency();
+        assert_eq!(adjacency.len(), sites.len());
+        assert!(adjacency[100].is_empty());
+
+        let mut pairs = vec![];
+        for (face, neighbours) in adjacency.iter().enumerate() {
+            for neighbour in neighbours {
+                let edge = diagram.half_edge(neighbour.edge);
+                assert_eq!(edge.face, face);
+                assert_eq!(diagram.half_edge(edge.twin).face, neighbour.face);
+                pairs.push([face, neighbour.face]);
+            }
+        }
+        // with no four sites on a circle, the edges of the triangulation
+        pairs.sort();
+        let mut edges = diagram
+            .delaunay_triangulation()
+            .triangles()
+            .iter()
+            .flat_map(|&[a, b, c]| [[a, b], [b, a], [b, c], [c, b], [c, a], [a, c]])
+            .collect::<Vec<_>>();
+        edges.sort();
+        edges.dedup();
+        assert_eq!(pairs, edges);
     }
 }
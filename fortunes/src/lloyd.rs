@@ -0,0 +1,129 @@
+use crate::{
+    geometry::{distance, polygon_centroid, polygon_second_moment, ClipRegion, Point, Scalar},
+    try_fortunes_algorithm, Error,
+};
+
+/// Outcome of [`lloyd_relax`].
+pub struct Relaxation<S> {
+    /// The sites after the last iteration, in the order they were passed in
+    pub sites: Vec<Point<S>>,
+    /// For every iteration, the sum over all cells of the squared distance
+    /// to their site, integrated over the cell, before the sites moved
+    pub energies: Vec<S>,
+    /// Whether the sites stopped moving before running out of iterations
+    pub converged: bool,
+}
+
+/// Move every site to the centroid of its cell, cut off at `region`, up to
+/// `iterations` times, stopping early once no site moves further than
+/// `tolerance`. This approaches a centroidal Voronoi tessellation.
+///
+/// Duplicate sites have no cell of their own, so they stay where they are
+/// until the site they duplicate has moved away, and then spread out.
+pub fn lloyd_relax<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+    iterations: usize,
+    tolerance: S,
+) -> Result<Relaxation<S>, Error> {
+    let mut sites = sites.to_vec();
+    let mut energies = vec![];
+    for _ in 0..iterations {
+        let diagram = try_fortunes_algorithm(&sites, region)?;
+        let cells = diagram.cell_polygons(region);
+
+        let mut energy = S::zero();
+        let mut moved = S::zero();
+        for (site, cell) in sites.iter_mut().zip(&cells) {
+            energy = energy + polygon_second_moment(cell, site);
+            if let Some(centroid) = polygon_centroid(cell) {
+                moved = moved.max(distance(site, &centroid));
+                *site = centroid;
+            }
+        }
+        energies.push(energy);
+
+        if moved <= tolerance {
+            return Ok(Relaxation {
+                sites,
+                energies,
+                converged: true,
+            });
+        }
+    }
+
+    Ok(Relaxation {
+        sites,
+        energies,
+        converged: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ordered_float::OrderedFloat;
+
+    use crate::test_utils::{bounding_box, random_sites};
+
+    use super::*;
+
+    #[test]
+    fn converges_to_square_grid() {
+        let sites = [
+            (240.0, 260.0),
+            (700.0, 200.0),
+            (300.0, 800.0),
+            (760.0, 740.0),
+        ]
+        .map(|(x, y)| Point::new(OrderedFloat(x), OrderedFloat(y)));
+
+        let relaxation = lloyd_relax(&sites, &bounding_box(), 100, OrderedFloat(1e-9)).unwrap();
+        assert!(relaxation.converged);
+        assert!(relaxation.energies.len() < 100);
+
+        let expected = [
+            (250.0, 250.0),
+            (750.0, 250.0),
+            (250.0, 750.0),
+            (750.0, 750.0),
+        ];
+        for (site, (x, y)) in relaxation.sites.iter().zip(expected) {
+            assert_relative_eq!(site.x.0, x, epsilon = 1e-6);
+            assert_relative_eq!(site.y.0, y, epsilon = 1e-6);
+        }
+        // four squares of side 500, each side^4 / 6 about its centre
+        let energy = relaxation.energies.last().unwrap().0;
+        assert_relative_eq!(energy, 4.0 * 500f64.powi(4) / 6.0, max_relative = 1e-9);
+    }
+
+    #[test]
+    fn energy_decreases() {
+        let sites = random_sites(50, 1000.0, 11);
+
+        let relaxation = lloyd_relax(&sites, &bounding_box(), 20, OrderedFloat(0.0)).unwrap();
+        assert!(!relaxation.converged);
+        assert_eq!(relaxation.energies.len(), 20);
+        for pair in relaxation.energies.windows(2) {
+            assert!(pair[1] <= pair[0] * OrderedFloat(1.0 + 1e-12));
+        }
+        assert!(relaxation
+            .sites
+            .iter()
+            .all(|site| bounding_box().contains(site)));
+    }
+
+    #[test]
+    fn duplicates_stay_put() {
+        let mut sites = random_sites(10, 1000.0, 2);
+        sites.push(sites[4]);
+
+        let relaxation = lloyd_relax(&sites, &bounding_box(), 1, OrderedFloat(0.0)).unwrap();
+        assert_eq!(relaxation.sites[10], sites[4]);
+        assert_ne!(relaxation.sites[4], sites[4]);
+
+        let relaxation = lloyd_relax(&sites, &bounding_box(), 3, OrderedFloat(0.0)).unwrap();
+        assert_ne!(relaxation.sites[10], sites[4]);
+        assert_ne!(relaxation.sites[10], relaxation.sites[4]);
+    }
+}
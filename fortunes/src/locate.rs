@@ -0,0 +1,325 @@
+use ordered_float::OrderedFloat;
+
+use crate::{
+    diagram::Diagram,
+    enclosing_box,
+    geometry::{Metric, Point, Scalar},
+    try_fortunes_algorithm, Error,
+};
+
+/// Each level of the hierarchy keeps about one in this many sites of the
+/// level below.
+const SAMPLE_RATIO: u64 = 16;
+
+/// Finds the cell containing a point, i.e. the nearest site.
+///
+/// Built as a hierarchy of Delaunay graphs over ever sparser random samples
+/// of the sites. A query walks greedily towards the point on the sparsest
+/// level, then continues from where it got to on the level below, which
+/// takes an expected `O(log n)` steps.
+pub struct PointLocator<S = OrderedFloat<f64>> {
+    sites: Vec<Point<S>>,
+    /// Densest level first
+    levels: Vec<Level>,
+}
+
+struct Level {
+    /// Face of the diagram of every site on this level
+    faces: Vec<usize>,
+    /// Neighbours of every site, by position on this level
+    neighbours: Vec<Vec<usize>>,
+    /// Position of every site on the level below, empty for the densest one
+    below: Vec<usize>,
+}
+
+impl<S: Scalar> PointLocator<S> {
+    /// Index the cells of `diagram`. Fails with [`Error::DegenerateInput`]
+    /// on a diagram without sites, and with [`Error::Unsupported`] on one
+    /// under another [`Metric`] than the plain one the walks rely on, with
+    /// weighted sites of either kind, or where the farthest site wins.
+    pub fn new(diagram: &Diagram<S>) -> Result<Self, Error> {
+        if diagram.metric() != Metric::Euclidean {
+            return Err(Error::Unsupported(
+                "locating points in a diagram under another metric",
+            ));
+        }
+        if diagram.is_weighted() {
+            return Err(Error::Unsupported("locating points in a power diagram"));
+        }
+        if diagram.is_farthest_point() {
+            return Err(Error::Unsupported(
+                "locating points in a farthest-point diagram",
+            ));
+        }
+        if diagram.is_additively_weighted() {
+            return Err(Error::Unsupported(
+                "locating points in an additively weighted diagram",
+            ));
+        }
+        let sites = diagram
+            .faces()
+            .iter()
+            .map(|face| face.site.clone())
+            .collect::<Vec<_>>();
+        let faces = (0..sites.len())
+            .filter(|&face| diagram.site_map()[face] == face)
+            .collect::<Vec<_>>();
+        if faces.is_empty() {
+            return Err(Error::DegenerateInput);
+        }
+
+        let mut position = vec![usize::MAX; sites.len()];
+        for (idx, &face) in faces.iter().enumerate() {
+            position[face] = idx;
+        }
+        let adjacency = diagram.adjacency();
+        let neighbours = faces
+            .iter()
+            .map(|&face| {
+                adjacency[face]
+                    .iter()
+                    .map(|neighbour| position[neighbour.face])
+                    .collect()
+            })
+            .collect();
+        let mut levels = vec![Level {
+            faces,
+            neighbours,
+            below: vec![],
+        }];
+
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        while levels.last().unwrap().faces.len() as u64 > 2 * SAMPLE_RATIO {
+            let level = levels.last().unwrap();
+            let mut below = (0..level.faces.len())
+                .filter(|_| {
+                    seed = splitmix(seed);
+                    seed % SAMPLE_RATIO == 0
+                })
+                .collect::<Vec<_>>();
+            if below.is_empty() {
+                below.push(0);
+            }
+            let faces = below
+                .iter()
+                .map(|&idx| level.faces[idx])
+                .collect::<Vec<_>>();
+
+            let sample = faces
+                .iter()
+                .map(|&face| sites[face].clone())
+                .collect::<Vec<_>>();
+            let bounding_box = enclosing_box(&sample)?.ok_or(Error::DegenerateInput)?;
+            let diagram = try_fortunes_algorithm(&sample, &bounding_box)?;
+            let neighbours = diagram
+                .adjacency()
+                .into_iter()
+                .map(|neighbours| neighbours.iter().map(|neighbour| neighbour.face).collect())
+                .collect();
+
+            levels.push(Level {
+                faces,
+                neighbours,
+                below,
+            });
+        }
+
+        Ok(Self { sites, levels })
+    }
+
+    /// The face whose cell contains `p`, which is that of the site nearest
+    /// to it. Points on the boundary between cells go to either of them.
+    pub fn locate(&self, p: &Point<S>) -> usize {
+        let mut idx = 0;
+        for (depth, level) in self.levels.iter().enumerate().rev() {
+            idx = self.walk(level, idx, p, usize::MAX).0;
+            if depth > 0 {
+                idx = level.below[idx];
+            }
+        }
+        self.levels[0].faces[idx]
+    }
+
+    /// [`Self::locate`] for every point in `points`. Visits the points in an
+    /// order keeping consecutive ones close, and searches for each starting
+    /// out from the cell of the one before.
+    pub fn locate_all(&self, points: &[Point<S>]) -> Vec<usize> {
+        let densest = &self.levels[0];
+        // a walk this long suggests the point is far away, and better left
+        // to the hierarchy
+        let max_steps = 4 * self.levels.len() + 8;
+
+        let mut res = vec![0; points.len()];
+        let mut idx = None;
+        for query in morton_order(points) {
+            let p = &points[query];
+            let hint = idx.map(|idx| self.walk(densest, idx, p, max_steps));
+            idx = Some(match hint {
+                Some((idx, true)) => idx,
+                _ => {
+                    let face = self.locate(p);
+                    densest.faces.binary_search(&face).unwrap()
+                }
+            });
+            res[query] = densest.faces[idx.unwrap()];
+        }
+        res
+    }
+
+    /// Move from the site at `idx` on `level` to the neighbour closest to
+    /// `p`, for as long as that gets closer to it. In a Delaunay graph this
+    /// only stops at the nearest site. Gives up after `max_steps`, telling
+    /// whether it got there.
+    fn walk(&self, level: &Level, mut idx: usize, p: &Point<S>, max_steps: usize) -> (usize, bool) {
+        let dist = |idx: usize| {
+            let site = &self.sites[level.faces[idx]];
+            let dx = site.x.clone() - p.x.clone();
+            let dy = site.y.clone() - p.y.clone();
+            dx.clone() * dx + dy.clone() * dy
+        };
+        let mut best = dist(idx);
+        for _ in 0..max_steps {
+            let closer = level.neighbours[idx]
+                .iter()
+                .map(|&other| (dist(other), other))
+                .filter(|(d, _)| *d < best)
+                .min();
+            let Some((d, other)) = closer else {
+                return (idx, true);
+            };
+            best = d;
+            idx = other;
+        }
+        (idx, false)
+    }
+}
+
+/// Indices of `points` sorted along a Z-order curve over their bounding
+/// box.
+pub(crate) fn morton_order<S: Scalar>(points: &[Point<S>]) -> Vec<usize> {
+    let coords = points
+        .iter()
+        .map(|p| (p.x.to_f64(), p.y.to_f64()))
+        .collect::<Vec<_>>();
+    let (x_min, x_max, y_min, y_max) = coords.iter().fold(
+        (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+        |(x_min, x_max, y_min, y_max), &(x, y)| {
+            (x_min.min(x), x_max.max(x), y_min.min(y), y_max.max(y))
+        },
+    );
+    let quantize = |v: f64, min: f64, max: f64| {
+        let t = if max > min {
+            (v - min) / (max - min)
+        } else {
+            0.0
+        };
+        (t * u16::MAX as f64) as u16
+    };
+    let key = |&(x, y): &(f64, f64)| {
+        let (x, y) = (quantize(x, x_min, x_max), quantize(y, y_min, y_max));
+        (0..16).fold(0u32, |key, bit| {
+            key | (((x as u32 >> bit) & 1) << (2 * bit))
+                | (((y as u32 >> bit) & 1) << (2 * bit + 1))
+        })
+    };
+
+    let mut order = (0..points.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&idx| key(&coords[idx]));
+    order
+}
+
+pub(crate) fn splitmix(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        geometry::{distance, BoundingBox},
+        test_utils::{bounding_box, point, random_sites},
+        try_fortunes_algorithm,
+    };
+
+    use super::*;
+
+    fn nearest(sites: &[Point], p: &Point) -> OrderedFloat<f64> {
+        sites.iter().map(|site| distance(site, p)).min().unwrap()
+    }
+
+    #[test]
+    fn finds_nearest_sites() {
+        let bounding_box = bounding_box();
+        let mut sites = random_sites(2000, 1000.0, 4);
+        sites.push(sites[17]);
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        let locator = PointLocator::new(&diagram).unwrap();
+        assert!(locator.levels.len() >= 3);
+
+        // including points far outside the box
+        let queries = random_sites(1000, 3000.0, 5)
+            .into_iter()
+            .map(|p| Point::new(p.x - 1000.0, p.y - 1000.0))
+            .collect::<Vec<_>>();
+        let found = locator.locate_all(&queries);
+        for (p, &face) in queries.iter().zip(&found) {
+            assert_eq!(locator.locate(p), face);
+            assert_eq!(distance(&sites[face], p), nearest(&sites, p));
+        }
+
+        // the duplicate never comes up
+        assert_eq!(locator.locate(&sites[2000]), 17);
+    }
+
+    #[test]
+    fn small_diagrams() {
+        let bounding_box = BoundingBox::new(
+            OrderedFloat(0.0),
+            OrderedFloat(10.0),
+            OrderedFloat(0.0),
+            OrderedFloat(10.0),
+        );
+        let sites = [Point::new(OrderedFloat(3.0), OrderedFloat(3.0))];
+        let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+        let locator = PointLocator::new(&diagram).unwrap();
+        assert_eq!(
+            locator.locate(&Point::new(OrderedFloat(9.0), OrderedFloat(1.0))),
+            0
+        );
+        assert!(locator.locate_all(&[]).is_empty());
+
+        let diagram = try_fortunes_algorithm::<OrderedFloat<f64>, _>(&[], &bounding_box).unwrap();
+        assert!(PointLocator::new(&diagram).is_err());
+    }
+
+    #[test]
+    fn collinear_sites() {
+        let bounding_box = bounding_box();
+        // the cells in the middle are strips, each with its neighbours on
+        // two separate chains of edges
+        let row = (0..5)
+            .map(|idx| point(100.0 + 200.0 * idx as f64, 500.0))
+            .collect::<Vec<_>>();
+        let diagram = try_fortunes_algorithm(&row, &bounding_box).unwrap();
+        let locator = PointLocator::new(&diagram).unwrap();
+        assert_eq!(locator.locate(&point(500.0, 400.0)), 2);
+        assert_eq!(locator.locate(&point(900.0, 400.0)), 4);
+
+        // enough for a hierarchy, one diagonal line on every level
+        let diagonal = (0..200)
+            .map(|idx| point(2.0 + 4.0 * idx as f64, 5.0 + 3.0 * idx as f64))
+            .collect::<Vec<_>>();
+        for sites in [row, diagonal] {
+            let diagram = try_fortunes_algorithm(&sites, &bounding_box).unwrap();
+            let locator = PointLocator::new(&diagram).unwrap();
+            let queries = random_sites(500, 1000.0, 6);
+            let found = locator.locate_all(&queries);
+            for (p, &face) in queries.iter().zip(&found) {
+                assert_eq!(distance(&sites[locator.locate(p)], p), nearest(&sites, p));
+                assert_eq!(distance(&sites[face], p), nearest(&sites, p));
+            }
+        }
+    }
+}
@@ -0,0 +1,823 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    diagram::Diagram,
+    geometry::{
+        clip_labelled_cell, convex_hull, dedup_sites, nearest_first, orient2d, Bisector,
+        ClipRegion, Metric, Point, Scalar,
+    },
+    Error,
+};
+
+/// Compute the Voronoi diagram of `sites` under the L1 or L∞ `metric`, see
+/// [`crate::try_fortunes_algorithm_with_metric`].
+///
+/// Bisectors under these metrics bend, so the sweep has no circle events to
+/// go by. Instead each cell is cut out of a frame around everything by the
+/// bisectors with the sites nearest to its own, until the rest are too far
+/// away to reach it, and the cells are then sewn together along the edges
+/// they share.
+pub(crate) fn metric_diagram<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    region: &R,
+    metric: Metric,
+) -> Result<Diagram<S>, Error> {
+    if region.is_empty() {
+        return Err(Error::DegenerateInput);
+    }
+    for (idx, site) in sites.iter().enumerate() {
+        if !site.x.is_finite() || !site.y.is_finite() {
+            return Err(Error::NonFiniteSite(idx));
+        }
+        if !region.contains(site) {
+            return Err(Error::SiteOutsideBoundingBox(idx));
+        }
+    }
+
+    let site_map = dedup_sites(sites, S::zero());
+    let distinct = (0..sites.len())
+        .filter(|&idx| site_map[idx] == idx)
+        .collect::<Vec<_>>();
+    let mut diagram = Diagram::new(sites, site_map);
+    diagram.set_metric(metric);
+    diagram.set_convex_hull(convex_hull(sites));
+    if distinct.len() < 2 {
+        return Ok(diagram);
+    }
+
+    let frame = Frame::new(sites, region);
+    let cells = distinct
+        .iter()
+        .map(|&s| (s, trace_cell(s, sites, &distinct, &frame, metric)))
+        .collect::<Vec<_>>();
+    let cells = settle(&cells, sites, metric);
+    sew(&mut diagram, &cells, region)?;
+    diagram.find_chains();
+    Ok(diagram)
+}
+
+/// A box around the sites and the region, far enough out that every vertex
+/// of the diagram lies inside it. Bisectors under L1 and L∞ only run
+/// straight, diagonally or at right angles to the axes, and bend close to
+/// their sites, so they cannot meet far away.
+struct Frame<S> {
+    corners: [Point<S>; 4],
+}
+
+impl<S: Scalar> Frame<S> {
+    fn new<R: ClipRegion<S>>(sites: &[Point<S>], region: &R) -> Self {
+        let points = sites.iter().cloned().chain(region.vertices());
+        let (mut min, mut max) = (sites[0].clone(), sites[0].clone());
+        for p in points {
+            min = Point::new(min.x.min(p.x.clone()), min.y.min(p.y.clone()));
+            max = Point::new(max.x.max(p.x.clone()), max.y.max(p.y));
+        }
+        let extent = (max.x.clone() - min.x.clone()) + (max.y.clone() - min.y.clone());
+        let pad = extent * S::from_f64(4.0);
+        let (lo_x, lo_y) = (min.x - pad.clone(), min.y - pad.clone());
+        let (hi_x, hi_y) = (max.x + pad.clone(), max.y + pad);
+        Self {
+            corners: [
+                Point::new(lo_x.clone(), lo_y.clone()),
+                Point::new(hi_x.clone(), lo_y),
+                Point::new(hi_x, hi_y.clone()),
+                Point::new(lo_x, hi_y),
+            ],
+        }
+    }
+}
+
+/// The points of a cell, counter-clockwise, each with the site across the
+/// edge leaving it, or none along the frame.
+pub(crate) type Ring<S> = Vec<(Point<S>, Option<usize>)>;
+
+/// The cell of `s` within the frame.
+fn trace_cell<S: Scalar>(
+    s: usize,
+    sites: &[Point<S>],
+    distinct: &[usize],
+    frame: &Frame<S>,
+    metric: Metric,
+) -> Ring<S> {
+    let site = &sites[s];
+    let mut ring = frame
+        .corners
+        .iter()
+        .map(|corner| (corner.clone(), None))
+        .collect::<Vec<_>>();
+
+    let mut candidates = distinct
+        .iter()
+        .filter(|&&t| t != s)
+        .map(|&t| (metric.distance(site, &sites[t]), t))
+        .collect::<Vec<_>>();
+    nearest_first(&mut candidates, |distance, t| {
+        // a site more than twice as far as any point of the cell is farther
+        // from all of them than s is
+        let reach = ring
+            .iter()
+            .map(|(p, _)| metric.distance(site, p))
+            .max()
+            .unwrap_or_else(S::zero);
+        if *distance > reach * S::from_f64(2.0) {
+            return false;
+        }
+        ring = clip_labelled_cell(
+            std::mem::take(&mut ring),
+            &Bisector::new(site, &sites[t], metric),
+            &Some(t),
+        );
+        true
+    });
+
+    ring
+}
+
+/// A stretch of the boundary of a cell along a single other cell, or along
+/// the frame, through its bends from the corner it starts at to the one it
+/// ends at.
+struct Run<S> {
+    across: Option<usize>,
+    points: Vec<Point<S>>,
+}
+
+/// `ring` cut into runs, with the corner each starts at taken to be the
+/// end of the one before.
+fn runs<S: Scalar>(ring: &[(Point<S>, Option<usize>)]) -> Result<Vec<Run<S>>, Error> {
+    let len = ring.len();
+    let start = (0..len)
+        .find(|&idx| ring[idx].1 != ring[(idx + len - 1) % len].1)
+        .ok_or(Error::DegenerateInput)?;
+    let mut res: Vec<Run<S>> = vec![];
+    for k in 0..len {
+        let (p, across) = &ring[(start + k) % len];
+        match res.last_mut() {
+            Some(run) if run.across == *across => run.points.push(p.clone()),
+            last => {
+                if let Some(run) = last {
+                    run.points.push(p.clone());
+                }
+                res.push(Run {
+                    across: *across,
+                    points: vec![p.clone()],
+                });
+            }
+        }
+    }
+    if let Some(run) = res.last_mut() {
+        run.points.push(ring[start].0.clone());
+    }
+    Ok(res)
+}
+
+fn find(rep: &mut [usize], mut idx: usize) -> usize {
+    while rep[idx] != idx {
+        rep[idx] = rep[rep[idx]];
+        idx = rep[idx];
+    }
+    idx
+}
+
+fn squared_distance<S: Scalar>(a: &Point<S>, b: &Point<S>) -> f64 {
+    let (dx, dy) = (
+        (a.x.clone() - b.x.clone()).to_f64(),
+        (a.y.clone() - b.y.clone()).to_f64(),
+    );
+    dx * dx + dy * dy
+}
+
+/// Whether `p`, on the line through `a` and `b`, lies between them.
+fn between<S: Scalar>(a: &Point<S>, b: &Point<S>, p: &Point<S>) -> bool {
+    let within = |v: &S, s: &S, t: &S| (s <= v && v <= t) || (t <= v && v <= s);
+    within(&p.x, &a.x, &b.x) && within(&p.y, &a.y, &b.y)
+}
+
+/// Where the bisectors of several sites run together, a cell can go
+/// straight past where the cells across change, and put the whole stretch
+/// along the last site it was cut by. Split the edges of `cells` where the
+/// corners of the cells nearby lie on them, and hand each piece to the cell
+/// that runs back along it, the nearest one if several do.
+fn settle<S: Scalar>(
+    cells: &[(usize, Ring<S>)],
+    sites: &[Point<S>],
+    metric: Metric,
+) -> Vec<(usize, Ring<S>)> {
+    let position = cells
+        .iter()
+        .enumerate()
+        .map(|(pos, (s, _))| (*s, pos))
+        .collect::<HashMap<_, _>>();
+    let mut around = vec![vec![]; cells.len()];
+    for (pos, (s, ring)) in cells.iter().enumerate() {
+        for t in ring.iter().filter_map(|(_, across)| *across) {
+            around[pos].push(t);
+            around[position[&t]].push(*s);
+        }
+    }
+    for others in &mut around {
+        others.sort_unstable();
+        others.dedup();
+    }
+
+    // the cells across a cell's edges need not be those its edges were
+    // cut by, but border one of those
+    let nearby = around
+        .iter()
+        .map(|others| {
+            let mut nearby = others
+                .iter()
+                .flat_map(|t| around[position[t]].iter().chain([t]))
+                .copied()
+                .collect::<Vec<_>>();
+            nearby.sort_unstable();
+            nearby.dedup();
+            nearby
+        })
+        .collect::<Vec<_>>();
+
+    let mut res = vec![];
+    for (pos, (s, ring)) in cells.iter().enumerate() {
+        let nearby = nearby[pos].iter().copied().filter(|t| t != s);
+        let corners = nearby
+            .clone()
+            .flat_map(|t| &cells[position[&t]].1)
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>();
+        let mut settled = vec![];
+        for (idx, (a, across)) in ring.iter().enumerate() {
+            let b = &ring[(idx + 1) % ring.len()].0;
+            if across.is_none() {
+                settled.push((a.clone(), None));
+                continue;
+            }
+            let along = |p: &Point<S>| {
+                (p.x.clone() - a.x.clone()) * (b.x.clone() - a.x.clone())
+                    + (p.y.clone() - a.y.clone()) * (b.y.clone() - a.y.clone())
+            };
+            let (lo, hi) = (along(a), along(b));
+            let mut inside = corners
+                .iter()
+                .filter(|p| orient2d(a, b, p) == Ordering::Equal)
+                .map(|p| (along(p), *p))
+                .filter(|(t, _)| lo < *t && *t < hi)
+                .collect::<Vec<_>>();
+            inside.sort_by(|x, y| x.0.cmp(&y.0));
+            inside.dedup_by(|later, earlier| later.0 == earlier.0);
+
+            let mut from = a.clone();
+            for to in inside
+                .into_iter()
+                .map(|(_, p)| p.clone())
+                .chain([b.clone()])
+            {
+                let mid = (from.clone() + to.clone()) * S::from_f64(0.5);
+                // the cells whose edges run back along this piece
+                let claims = |t: usize| {
+                    let ring = &cells[position[&t]].1;
+                    (0..ring.len()).any(|idx| {
+                        let (c, d) = (&ring[idx].0, &ring[(idx + 1) % ring.len()].0);
+                        ring[idx].1.is_some()
+                            && along(d) < along(c)
+                            && orient2d(c, d, &mid) == Ordering::Equal
+                            && between(c, d, &mid)
+                    })
+                };
+                let tied = nearby.clone().filter(|&t| claims(t));
+                let nearest = tied.reduce(|best, t| {
+                    match Bisector::new(&sites[t], &sites[best], metric).side(&mid) {
+                        Ordering::Less => t,
+                        _ => best,
+                    }
+                });
+                let keep = across.filter(|&t| claims(t));
+                settled.push((from, keep.or(nearest).or(*across)));
+                from = to;
+            }
+        }
+        res.push((*s, settled));
+    }
+    res
+}
+
+/// Join the traced `cells` into `diagram`, with edges reaching the frame
+/// cut off at the boundary of `region` instead. The frame may be the region
+/// itself, as for [`crate::apollonius_diagram`].
+///
+/// Each boundary between two cells is taken from the one with the lower
+/// site, and the corners where it meets others are matched up through the
+/// runs they end, not by where the cells put them, so that the cells fit
+/// together however the tracing rounded.
+pub(crate) fn sew<S: Scalar, R: ClipRegion<S>>(
+    diagram: &mut Diagram<S>,
+    cells: &[(usize, Ring<S>)],
+    region: &R,
+) -> Result<(), Error> {
+    let position = cells
+        .iter()
+        .enumerate()
+        .map(|(pos, (s, _))| (*s, pos))
+        .collect::<HashMap<_, _>>();
+
+    let runs = cells
+        .iter()
+        .map(|(_, ring)| runs(ring))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // the corners of each cell, where its runs start
+    let mut first_corner = vec![0];
+    for cell_runs in &runs {
+        first_corner.push(first_corner.last().unwrap() + cell_runs.len());
+    }
+    let start = |pos: usize, k: usize| first_corner[pos] + k;
+    let end = |pos: usize, k: usize| first_corner[pos] + (k + 1) % runs[pos].len();
+
+    let mut across: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (pos, cell_runs) in runs.iter().enumerate() {
+        for (k, run) in cell_runs.iter().enumerate() {
+            if let Some(t) = run.across {
+                across.entry((pos, position[&t])).or_default().push(k);
+            }
+        }
+    }
+
+    // a run and the one across from it, in the cell of the higher site,
+    // go opposite ways between the same corners. Runs the cell across does
+    // not run back along are slivers left by rounding, and shrink to their
+    // corners.
+    let mut rep = (0..*first_corner.last().unwrap()).collect::<Vec<_>>();
+    let mut union = |a: usize, b: usize| {
+        let (a, b) = (find(&mut rep, a), find(&mut rep, b));
+        rep[b] = a;
+    };
+    let mut partner = HashMap::new();
+    for (&(pos, other), ks) in &across {
+        let others = match across.get(&(other, pos)) {
+            Some(_) if pos > other => continue,
+            Some(others) => others.as_slice(),
+            None => &[],
+        };
+        let gap = |k: usize, l: usize| {
+            let (ours, theirs) = (&runs[pos][k].points, &runs[other][l].points);
+            squared_distance(&ours[0], theirs.last().unwrap())
+                + squared_distance(ours.last().unwrap(), &theirs[0])
+        };
+        let mut pairs = ks
+            .iter()
+            .flat_map(|&k| others.iter().map(move |&l| (k, l)))
+            .collect::<Vec<_>>();
+        pairs.sort_by(|&(a, b), &(c, d)| gap(a, b).total_cmp(&gap(c, d)));
+        let mut matched = HashMap::new();
+        for (k, l) in pairs {
+            if !matched.contains_key(&(pos, k)) && !matched.contains_key(&(other, l)) {
+                matched.insert((pos, k), l);
+                matched.insert((other, l), k);
+                union(start(pos, k), end(other, l));
+                union(end(pos, k), start(other, l));
+                partner.insert((pos, k), l);
+            }
+        }
+        let lone = ks.iter().map(|&k| (pos, k));
+        for (at, k) in lone.chain(others.iter().map(|&l| (other, l))) {
+            if !matched.contains_key(&(at, k)) {
+                union(start(at, k), end(at, k));
+            }
+        }
+    }
+
+    // corners go where the cell of the lowest site put them, and are ends
+    // of rays where they touch the frame
+    let mut point = vec![None; rep.len()];
+    let mut on_frame = vec![false; rep.len()];
+    for (pos, cell_runs) in runs.iter().enumerate() {
+        for (k, run) in cell_runs.iter().enumerate() {
+            let corner = find(&mut rep, start(pos, k));
+            point[corner].get_or_insert_with(|| run.points[0].clone());
+            if run.across.is_none() {
+                on_frame[corner] = true;
+                on_frame[find(&mut rep, end(pos, k))] = true;
+            }
+        }
+    }
+
+    let mut vertex = vec![None; rep.len()];
+    let mut edges = runs
+        .iter()
+        .map(|cell_runs| {
+            let edges = |run: &Run<S>| run.across.map(|_| vec![]);
+            cell_runs.iter().map(edges).collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for (pos, cell_runs) in runs.iter().enumerate() {
+        for (k, run) in cell_runs.iter().enumerate() {
+            let Some(t) = run.across else {
+                continue;
+            };
+            let other = position[&t];
+            if other < pos {
+                continue;
+            }
+            let Some(&l) = partner.get(&(pos, k)) else {
+                continue;
+            };
+            let corners = [find(&mut rep, start(pos, k)), find(&mut rep, end(pos, k))];
+            let count = run.points.len() - 1;
+            let mut points = run.points.clone();
+            points[0] = point[corners[0]].clone().unwrap();
+            points[count] = point[corners[1]].clone().unwrap();
+
+            let halves = (0..count)
+                .map(|_| diagram.add_edge(cells[pos].0, t))
+                .collect::<Vec<_>>();
+            for idx in 0..=count {
+                let outgoing = halves.get(idx).map(|half| half.0);
+                let incoming = idx.checked_sub(1).map(|prev| halves[prev].1);
+                let corner = match idx {
+                    0 => Some(corners[0]),
+                    _ if idx == count => Some(corners[1]),
+                    _ => None,
+                };
+                let first = outgoing.or(incoming).unwrap();
+                let v = match corner.and_then(|corner| vertex[corner]) {
+                    Some(v) => v,
+                    None => {
+                        let at = match corner {
+                            Some(corner) if on_frame[corner] => {
+                                // the ray towards the frame, cut off at the
+                                // region instead
+                                let from = &points[idx];
+                                let neighbour = if idx == 0 { 1 } else { count - 1 };
+                                let to = &points[neighbour];
+                                let inner = if count == 1 && on_frame[corners[1 - idx / count]] {
+                                    (from.clone() + to.clone()) * S::from_f64(0.5)
+                                } else {
+                                    to.clone()
+                                };
+                                region.ray_end(&inner, &(from.clone() - inner.clone()))
+                            }
+                            _ => points[idx].clone(),
+                        };
+                        let v = diagram.add_vertex(at, first);
+                        if let Some(corner) = corner {
+                            vertex[corner] = Some(v);
+                        }
+                        v
+                    }
+                };
+                for half in [outgoing, incoming].into_iter().flatten() {
+                    diagram.set_origin(half, v);
+                }
+            }
+            edges[pos][k] = Some(halves.iter().map(|half| half.0).collect());
+            edges[other][l] = Some(halves.iter().rev().map(|half| half.1).collect());
+        }
+    }
+
+    // link up the edges around each cell, which break off at the frame
+    for cell_edges in &edges {
+        let around = cell_edges
+            .iter()
+            .flat_map(|run_edges| match run_edges {
+                Some(run_edges) => run_edges.iter().copied().map(Some).collect(),
+                None => vec![None],
+            })
+            .collect::<Vec<_>>();
+        for (idx, entry) in around.iter().enumerate() {
+            if let (Some(prev), Some(next)) = (entry, around[(idx + 1) % around.len()]) {
+                diagram.link(*prev, next);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fortunes_algorithm,
+        geometry::{polygon_area, PolygonWithHoles},
+        locate::PointLocator,
+        test_utils::{
+            bounding_box, check_diagram_by, compare_edges, point, random_sites, same_diagram,
+        },
+        try_fortunes_algorithm_with_metric,
+    };
+
+    use super::*;
+
+    const METRICS: [Metric; 2] = [Metric::Manhattan, Metric::Chebyshev];
+
+    #[test]
+    fn random_sites_under_both_metrics() {
+        let bounding_box = bounding_box();
+        for metric in METRICS {
+            for seed in 0..5 {
+                let mut sites = random_sites(300, 1000.0, seed);
+                sites.push(sites[17]);
+                let diagram =
+                    try_fortunes_algorithm_with_metric(&sites, &bounding_box, metric).unwrap();
+                assert_eq!(diagram.metric(), metric);
+                assert!(check_diagram_by(&sites, &diagram, 1e-9, |a, b| metric
+                    .distance(a, b)
+                    .to_f64()));
+                assert_eq!(diagram.site_map()[300], 17);
+
+                // the cells cover the region once
+                let cells = diagram.cell_polygons(&bounding_box);
+                assert!(cells[300].is_empty());
+                let total = cells.iter().map(|cell| polygon_area(cell).0).sum::<f64>();
+                assert!((total - 1e6).abs() < 1e-3);
+                assert!(cells[..300].iter().all(|cell| !cell.is_empty()));
+                diagram.delaunay_triangulation();
+            }
+        }
+    }
+
+    #[test]
+    fn sites_with_many_ties() {
+        let bounding_box = bounding_box();
+        for metric in METRICS {
+            for seed in 0..10 {
+                // on a coarse grid, where bisectors often run together
+                let sites = random_sites(100, 1000.0, seed)
+                    .iter()
+                    .map(|p| point((p.x.0 / 50.0).round() * 50.0, (p.y.0 / 50.0).round() * 50.0))
+                    .collect::<Vec<_>>();
+                let diagram =
+                    try_fortunes_algorithm_with_metric(&sites, &bounding_box, metric).unwrap();
+                assert!(check_diagram_by(&sites, &diagram, 1e-9, |a, b| metric
+                    .distance(a, b)
+                    .to_f64()));
+                let cells = diagram.cell_polygons(&bounding_box);
+                let total = cells.iter().map(|cell| polygon_area(cell).0).sum::<f64>();
+                assert!((total - 1e6).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn collinear_and_close_sites() {
+        let bounding_box = bounding_box();
+        let cases: Vec<Vec<Point>> = vec![
+            vec![
+                point(100.0, 500.0),
+                point(500.0, 500.0),
+                point(900.0, 500.0),
+            ],
+            (0..50)
+                .map(|i| point(10.0 + 19.0 * i as f64, 500.0))
+                .collect(),
+            (0..50)
+                .map(|i| point(10.0 + 19.0 * i as f64, 10.0 + 19.0 * i as f64))
+                .collect(),
+            (0..50)
+                .map(|i| point(500.0, 10.0 + 1e-6 * i as f64))
+                .collect(),
+            vec![
+                point(500.0, 100.0),
+                point(500.0, 500.0),
+                point(500.0, 500.0),
+            ],
+            vec![
+                point(1.0, 1.0),
+                point(1.0 + 1e-13, 1.0),
+                point(1.0, 1.0 + 1e-13),
+            ],
+        ];
+        for metric in METRICS {
+            for sites in &cases {
+                // either a proper diagram or an error, never a panic
+                match try_fortunes_algorithm_with_metric(sites, &bounding_box, metric) {
+                    Ok(diagram) => {
+                        assert!(check_diagram_by(sites, &diagram, 1e-9, |a, b| metric
+                            .distance(a, b)
+                            .to_f64()));
+                        let cells = diagram.cell_polygons(&bounding_box);
+                        let total = cells.iter().map(|cell| polygon_area(cell).0).sum::<f64>();
+                        assert!((total - 1e6).abs() < 1e-3);
+                    }
+                    Err(err) => assert_eq!(err, Error::DegenerateInput),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_gives_square_cells() {
+        let bounding_box = bounding_box();
+        let sites = (0..25)
+            .map(|idx| {
+                point(
+                    100.0 + 200.0 * (idx % 5) as f64,
+                    100.0 + 200.0 * (idx / 5) as f64,
+                )
+            })
+            .collect::<Vec<_>>();
+        for metric in METRICS {
+            let diagram =
+                try_fortunes_algorithm_with_metric(&sites, &bounding_box, metric).unwrap();
+            assert!(check_diagram_by(&sites, &diagram, 1e-12, |a, b| metric
+                .distance(a, b)
+                .to_f64()));
+            for (site, cell) in sites.iter().zip(diagram.cell_polygons(&bounding_box)) {
+                assert_eq!(polygon_area(&cell).0, 40000.0);
+                assert!(cell
+                    .iter()
+                    .all(|p| (p.x - site.x).abs() == 100.0 && (p.y - site.y).abs() == 100.0));
+            }
+            // cells meeting at a corner are not neighbours
+            let mut neighbours = diagram
+                .neighbours(12)
+                .iter()
+                .map(|n| n.face)
+                .collect::<Vec<_>>();
+            neighbours.sort();
+            assert_eq!(neighbours, [7, 11, 13, 17]);
+        }
+    }
+
+    #[test]
+    fn bent_and_tied_bisectors() {
+        let bounding_box = bounding_box();
+        // rounded, as ends on the frame are found by interpolating along it
+        let round = |p: &Point| point((p.x.0 * 1e6).round() / 1e6, (p.y.0 * 1e6).round() / 1e6);
+        let segments = |sites: &[Point], metric: Metric| {
+            try_fortunes_algorithm_with_metric(sites, &bounding_box, metric)
+                .unwrap()
+                .segments()
+                .iter()
+                .map(|[a, b]| [round(a), round(b)])
+                .collect::<Vec<_>>()
+        };
+
+        // along an axis beyond the sites, and diagonal between them
+        let bent = segments(
+            &[point(200.0, 400.0), point(600.0, 500.0)],
+            Metric::Manhattan,
+        );
+        assert!(compare_edges(
+            &bent,
+            &[
+                [point(450.0, 0.0), point(450.0, 400.0)],
+                [point(450.0, 400.0), point(350.0, 500.0)],
+                [point(350.0, 500.0), point(350.0, 1000.0)],
+            ]
+        ));
+
+        // equally far from whole quadrants, split along the plain bisector
+        let tied = segments(
+            &[point(400.0, 400.0), point(600.0, 600.0)],
+            Metric::Manhattan,
+        );
+        assert!(compare_edges(
+            &tied,
+            &[[point(0.0, 1000.0), point(1000.0, 0.0)]]
+        ));
+        let tied = segments(
+            &[point(500.0, 300.0), point(500.0, 700.0)],
+            Metric::Chebyshev,
+        );
+        assert!(compare_edges(
+            &tied,
+            &[[point(0.0, 500.0), point(1000.0, 500.0)]]
+        ));
+
+        // three sites on a line give the middle one two separate edges
+        let sites = [
+            point(100.0, 500.0),
+            point(500.0, 500.0),
+            point(900.0, 500.0),
+        ];
+        assert!(compare_edges(
+            &segments(&sites, Metric::Manhattan),
+            &[
+                [point(300.0, 0.0), point(300.0, 1000.0)],
+                [point(700.0, 0.0), point(700.0, 1000.0)],
+            ]
+        ));
+        let diagram =
+            try_fortunes_algorithm_with_metric(&sites, &bounding_box, Metric::Manhattan).unwrap();
+        let cells = diagram.cell_polygons(&bounding_box);
+        assert_eq!(polygon_area(&cells[1]).0, 400000.0);
+    }
+
+    #[test]
+    fn cells_in_polygon_with_a_hole() {
+        let bounding_box = bounding_box();
+        let building = PolygonWithHoles::new(
+            vec![
+                point(100.0, 100.0),
+                point(900.0, 100.0),
+                point(900.0, 500.0),
+                point(500.0, 500.0),
+                point(500.0, 900.0),
+                point(100.0, 900.0),
+            ],
+            vec![vec![
+                point(200.0, 200.0),
+                point(400.0, 200.0),
+                point(400.0, 400.0),
+                point(200.0, 400.0),
+            ]],
+        )
+        .unwrap();
+        let area = |polygon: &PolygonWithHoles| {
+            polygon_area(polygon.outer()).0
+                + polygon
+                    .holes()
+                    .iter()
+                    .map(|hole| polygon_area(hole).0)
+                    .sum::<f64>()
+        };
+
+        for metric in METRICS {
+            let mut sites = random_sites(50, 1000.0, 3);
+            sites.push(point(950.0, 950.0));
+            let diagram =
+                try_fortunes_algorithm_with_metric(&sites, &bounding_box, metric).unwrap();
+            let cells = diagram.cells_in_polygon(&building);
+            let total = cells.parts.iter().flatten().map(area).sum::<f64>();
+            assert!((total - area(&building)).abs() < 1e-6);
+            assert!(cells.empty.contains(&50));
+        }
+    }
+
+    #[test]
+    fn euclidean_metric_is_the_sweep() {
+        let bounding_box = bounding_box();
+        let sites = random_sites(200, 1000.0, 8);
+        let diagram =
+            try_fortunes_algorithm_with_metric(&sites, &bounding_box, Metric::Euclidean).unwrap();
+        assert!(same_diagram(
+            &diagram,
+            &fortunes_algorithm(&sites, &bounding_box),
+            0.0
+        ));
+        assert!(PointLocator::new(&diagram).is_ok());
+    }
+
+    #[test]
+    fn no_updates_or_point_location() {
+        let bounding_box = bounding_box();
+        let sites = random_sites(20, 1000.0, 2);
+        let mut diagram =
+            try_fortunes_algorithm_with_metric(&sites, &bounding_box, Metric::Chebyshev).unwrap();
+        assert!(matches!(
+            diagram.insert_site(point(500.0, 500.0), &bounding_box),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            diagram.remove_site(3, &bounding_box),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            PointLocator::new(&diagram),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bounding_box = bounding_box();
+        let diagram = |sites: &[Point]| {
+            try_fortunes_algorithm_with_metric(sites, &bounding_box, Metric::Manhattan)
+        };
+        assert_eq!(
+            diagram(&[point(1.0, 1.0), point(f64::NAN, 1.0)]).err(),
+            Some(Error::NonFiniteSite(1))
+        );
+        assert_eq!(
+            diagram(&[point(1.0, 1.0), point(2000.0, 1.0)]).err(),
+            Some(Error::SiteOutsideBoundingBox(1))
+        );
+        let single = diagram(&[point(1.0, 1.0), point(1.0, 1.0)]).unwrap();
+        assert_eq!(single.site_map(), [0, 0]);
+        assert_eq!(single.cell_polygons(&bounding_box)[0].len(), 4);
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_vertices_are_exact() {
+        use num_rational::BigRational;
+
+        let sites = random_sites(60, 1000.0, 5)
+            .iter()
+            .map(|p| {
+                let round = |v: f64| BigRational::from_f64(v.round());
+                Point::new(round(p.x.into_inner()), round(p.y.into_inner()))
+            })
+            .collect::<Vec<_>>();
+        for metric in METRICS {
+            let diagram =
+                try_fortunes_algorithm_with_metric(&sites, &bounding_box::<BigRational>(), metric)
+                    .unwrap();
+            for v in 0..diagram.vertices().len() {
+                let p = &diagram.vertex(v).point;
+                let nearest = sites.iter().map(|site| metric.distance(site, p)).min();
+                assert!(diagram.outgoing(v).into_iter().all(|edge| {
+                    let site = &sites[diagram.half_edge(edge).face];
+                    Some(metric.distance(site, p)) == nearest
+                }));
+            }
+        }
+    }
+}
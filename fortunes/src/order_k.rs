@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use ordered_float::OrderedFloat;
+
+use crate::{
+    geometry::{clip_to_bisector, convex_hull, polygon_area, ClipRegion, Point, Scalar},
+    try_fortunes_algorithm, Error,
+};
+
+/// A region of an order-k Voronoi diagram, see [`order_k_diagram`].
+pub struct OrderKRegion<S = OrderedFloat<f64>> {
+    /// Faces of the `k` sites nearest to every point of the region, in
+    /// increasing order
+    pub sites: Vec<usize>,
+    /// The region cut off at the clip region, a convex polygon listing its
+    /// vertices counter-clockwise
+    pub polygon: Vec<Point<S>>,
+}
+
+/// Compute the order-`k` Voronoi diagram of `sites` within `region`, whose
+/// regions each have the same `k` sites nearest to all their points. The
+/// regions come ordered by their sites, and only those reaching into the
+/// region are listed. Sites equal to an earlier one are merged into it as in
+/// [`crate::diagram::Diagram::site_map`]. Fails with
+/// [`Error::DegenerateInput`] unless `k` is at least one and at most the
+/// number of distinct sites.
+///
+/// Starting from the cells of the Voronoi diagram, the regions of each order
+/// are split up by the site coming next, and the pieces with the same sites
+/// joined again. The site coming next is always a neighbour in the Voronoi
+/// diagram of one of those nearer, so only these are tried.
+pub fn order_k_diagram<S: Scalar, R: ClipRegion<S>>(
+    sites: &[Point<S>],
+    k: usize,
+    region: &R,
+) -> Result<Vec<OrderKRegion<S>>, Error> {
+    let diagram = try_fortunes_algorithm(sites, region)?;
+    let distinct = (0..sites.len())
+        .filter(|&idx| diagram.site_map()[idx] == idx)
+        .count();
+    if k == 0 || k > distinct {
+        return Err(Error::DegenerateInput);
+    }
+
+    let mut neighbours = vec![vec![]; sites.len()];
+    for edge in diagram.half_edges() {
+        neighbours[edge.face].push(diagram.half_edge(edge.twin).face);
+    }
+    for list in &mut neighbours {
+        list.sort_unstable();
+        list.dedup();
+    }
+
+    let mut regions = diagram
+        .cell_polygons(region)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, cell)| !cell.is_empty())
+        .map(|(face, cell)| (vec![face], cell))
+        .collect::<Vec<_>>();
+    for _ in 1..k {
+        let mut pieces: HashMap<Vec<usize>, Vec<Point<S>>> = HashMap::new();
+        for (nearest, polygon) in &regions {
+            let mut candidates = nearest
+                .iter()
+                .flat_map(|&face| &neighbours[face])
+                .copied()
+                .filter(|face| !nearest.contains(face))
+                .collect::<Vec<_>>();
+            candidates.sort_unstable();
+            candidates.dedup();
+
+            for &next in &candidates {
+                let piece = candidates
+                    .iter()
+                    .filter(|&&other| other != next)
+                    .fold(polygon.clone(), |piece, &other| {
+                        clip_to_bisector(&piece, &sites[next], &sites[other])
+                    });
+                if polygon_area(&piece) <= S::zero() {
+                    continue;
+                }
+                let mut key = nearest.clone();
+                key.push(next);
+                key.sort_unstable();
+                pieces.entry(key).or_default().extend(piece);
+            }
+        }
+
+        // the pieces with the same sites make up a convex region together
+        regions = pieces
+            .into_iter()
+            .map(|(key, points)| {
+                let corners = convex_hull(&points);
+                (
+                    key,
+                    corners.into_iter().map(|idx| points[idx].clone()).collect(),
+                )
+            })
+            .collect();
+        regions.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    Ok(regions
+        .into_iter()
+        .map(|(sites, polygon)| OrderKRegion { sites, polygon })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fortunes_algorithm,
+        geometry::ConvexPolygon,
+        test_utils::{bounding_box, point, random_sites, squared_distance},
+    };
+
+    use super::*;
+
+    #[test]
+    fn regions_hold_the_nearest_sites() {
+        let bounding_box = bounding_box();
+        let sites = random_sites(150, 1000.0, 31);
+        for k in 1..=4 {
+            let regions = order_k_diagram(&sites, k, &bounding_box).unwrap();
+            let area = regions
+                .iter()
+                .map(|region| polygon_area(&region.polygon).0)
+                .sum::<f64>();
+            assert!((area - 1e6).abs() < 1e-6);
+            assert!(regions.windows(2).all(|pair| pair[0].sites < pair[1].sites));
+            assert!(regions.iter().all(|region| region.sites.len() == k));
+
+            for p in random_sites(300, 1000.0, 32) {
+                let mut order = (0..sites.len()).collect::<Vec<_>>();
+                order.sort_by_key(|&idx| squared_distance(&sites[idx], &p));
+                let mut nearest = order[..k].to_vec();
+                nearest.sort_unstable();
+                let region = regions
+                    .iter()
+                    .find(|region| region.sites == nearest)
+                    .unwrap();
+                let polygon = ConvexPolygon::new(region.polygon.clone()).unwrap();
+                let dist = |q: &Point| (q.x - p.x).abs() + (q.y - p.y).abs();
+                assert!(polygon.contains(&p) || region.polygon.iter().any(|q| dist(q) < 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn first_order_is_the_voronoi_diagram() {
+        let bounding_box = bounding_box();
+        let mut sites = random_sites(100, 1000.0, 33);
+        sites.push(sites[4]);
+        let cells = fortunes_algorithm(&sites, &bounding_box).cell_polygons(&bounding_box);
+        let regions = order_k_diagram(&sites, 1, &bounding_box).unwrap();
+        assert_eq!(regions.len(), 100);
+        for region in &regions {
+            let [face] = region.sites[..] else {
+                panic!("one site per region");
+            };
+            assert_eq!(region.polygon, cells[face]);
+        }
+
+        // the duplicate counts once, so that all distinct sites are nearest
+        // everywhere
+        let mut sites = sites[..10].to_vec();
+        sites.push(sites[4]);
+        let regions = order_k_diagram(&sites, 10, &bounding_box).unwrap();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].sites, (0..10).collect::<Vec<_>>());
+        assert_eq!(polygon_area(&regions[0].polygon).0, 1e6);
+        assert_eq!(
+            order_k_diagram(&sites, 11, &bounding_box).err(),
+            Some(Error::DegenerateInput)
+        );
+    }
+
+    #[test]
+    fn grid_of_sites() {
+        let bounding_box = bounding_box();
+        // cocircular everywhere, so that many pieces meet at single points
+        let sites = (0..16)
+            .map(|idx| {
+                point(
+                    125.0 + 250.0 * (idx % 4) as f64,
+                    125.0 + 250.0 * (idx / 4) as f64,
+                )
+            })
+            .collect::<Vec<_>>();
+        let regions = order_k_diagram(&sites, 2, &bounding_box).unwrap();
+        // a region for every pair of sites next to each other
+        assert_eq!(regions.len(), 24);
+        for region in &regions {
+            let [a, b] = region.sites[..] else {
+                panic!("two sites per region");
+            };
+            let d = sites[a] - sites[b];
+            assert_eq!(d.x.0.abs() + d.y.0.abs(), 250.0);
+        }
+        // between two of them, a square standing on one corner
+        let middle = regions
+            .iter()
+            .find(|region| region.sites == [5, 6])
+            .unwrap();
+        assert_eq!(polygon_area(&middle.polygon).0, 250.0 * 250.0 / 2.0);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bounding_box = bounding_box();
+        let sites = [point(100.0, 100.0), point(200.0, 200.0)];
+        assert_eq!(
+            order_k_diagram(&sites, 0, &bounding_box).err(),
+            Some(Error::DegenerateInput)
+        );
+        assert_eq!(
+            order_k_diagram(&[point(100.0, 100.0), point(-1.0, 0.0)], 1, &bounding_box).err(),
+            Some(Error::SiteOutsideBoundingBox(1))
+        );
+        assert_eq!(
+            order_k_diagram(&[point(f64::NAN, 0.0)], 1, &bounding_box).err(),
+            Some(Error::NonFiniteSite(0))
+        );
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_regions_are_exact() {
+        use num_rational::BigRational;
+
+        let sites = random_sites(40, 1000.0, 34)
+            .iter()
+            .map(|p| {
+                let round = |v: f64| BigRational::from_f64(v.round());
+                Point::new(round(p.x.0), round(p.y.0))
+            })
+            .collect::<Vec<_>>();
+        let bounding_box = bounding_box::<BigRational>();
+        for k in 1..=3 {
+            let regions = order_k_diagram(&sites, k, &bounding_box).unwrap();
+            let area = regions
+                .iter()
+                .map(|region| polygon_area(&region.polygon))
+                .fold(BigRational::zero(), |sum, area| sum + area);
+            assert_eq!(area, BigRational::from_f64(1e6));
+        }
+    }
+}
@@ -0,0 +1,739 @@
+use std::{
+    cmp::{min, Ordering, Reverse},
+    collections::HashMap,
+};
+
+use crate::{
+    add_arc_right, add_parabola,
+    beachline::{Arc, Beachline},
+    close_diagram,
+    diagram::Diagram,
+    geometry::{
+        bisector_point, normal_vector, orient2d, power_circle_bottom, power_test,
+        power_test_collinear, ClipRegion, Point, Scalar, WeightedSite,
+    },
+    remove_parabola, split_arc, Error, Event, EventQueue,
+};
+
+/// Compute the power diagram of `sites` within `region`, in which a point
+/// belongs to the site it has the smallest power distance
+/// `|p - site|^2 - weight` to. With equal weights this is the Voronoi
+/// diagram.
+///
+/// A site outweighed everywhere by the sites around it, like one sharing
+/// its position with a heavier one, gets a face without a cell, as does one
+/// whose cell is no more than a point or a line. Sites equal
+/// to an earlier one in position and weight are merged into it, see
+/// [`Diagram::site_map`].
+///
+/// The sweep lifts every site by the largest weight less its own, which
+/// keeps the arcs parabolas. A cell need not contain its site, so the arc
+/// of a lifted site comes up through the beachline wherever its cell is
+/// highest: from its own site event on, it is queued at the highest point of
+/// its cell as far as the arcs found above that point so far go, until the
+/// arc found there is one of them.
+pub fn power_diagram<S: Scalar, R: ClipRegion<S>>(
+    sites: &[WeightedSite<S>],
+    region: &R,
+) -> Result<Diagram<S>, Error> {
+    if region.is_empty() {
+        return Err(Error::DegenerateInput);
+    }
+    for (idx, site) in sites.iter().enumerate() {
+        if !site.point.x.is_finite() || !site.point.y.is_finite() || !site.weight.is_finite() {
+            return Err(Error::NonFiniteSite(idx));
+        }
+        if !region.contains(&site.point) {
+            return Err(Error::SiteOutsideBoundingBox(idx));
+        }
+    }
+
+    let mut first = HashMap::new();
+    let site_map = sites
+        .iter()
+        .enumerate()
+        .map(|(idx, site)| *first.entry(site).or_insert(idx))
+        .collect::<Vec<_>>();
+
+    // of sites sharing a position only the heaviest can have a cell
+    let mut heaviest: HashMap<&Point<S>, usize> = HashMap::new();
+    for idx in (0..sites.len()).filter(|&idx| site_map[idx] == idx) {
+        let other = heaviest.entry(&sites[idx].point).or_insert(idx);
+        if sites[idx].weight > sites[*other].weight {
+            *other = idx;
+        }
+    }
+    let mut candidates = heaviest.into_values().collect::<Vec<_>>();
+    candidates.sort_unstable();
+
+    let points = sites
+        .iter()
+        .map(|site| site.point.clone())
+        .collect::<Vec<_>>();
+    let mut diagram = Diagram::new(&points, site_map);
+    diagram.set_weights(sites);
+
+    let top = candidates
+        .iter()
+        .map(|&idx| sites[idx].weight.clone())
+        .max()
+        .unwrap_or_else(S::zero);
+    let mut beachline = Beachline::with_weights(sites.to_vec(), top.clone());
+    let mut eq = EventQueue::new();
+
+    if let Some(y_max) = candidates.iter().map(|&idx| &points[idx].y).max() {
+        let mut row = candidates
+            .iter()
+            .copied()
+            .filter(|&idx| points[idx].y == *y_max)
+            .collect::<Vec<_>>();
+        row.sort_by(|&a, &b| points[a].x.cmp(&points[b].x));
+        add_first_row(sites, &row, &mut eq, &mut beachline, &mut diagram)?;
+
+        for &idx in &candidates {
+            let site = &points[idx];
+            if site.y != *y_max {
+                eq.push(
+                    Event::Site(idx),
+                    (site.y.clone(), true, Reverse(site.x.clone())),
+                );
+            }
+        }
+    }
+
+    let mut waiting = Waiting::default();
+    while let Some((e, (yl, ..))) = eq.pop() {
+        match e {
+            Event::Site(site_idx) if sites[site_idx].weight == top => add_parabola(
+                &points[site_idx],
+                site_idx,
+                yl,
+                &mut eq,
+                &mut beachline,
+                &mut diagram,
+            )?,
+            Event::Site(site_idx) => {
+                let (arc, _) = beachline
+                    .arc_under_point(&points[site_idx])?
+                    .ok_or(Error::InvariantViolation("site below an empty beachline"))?;
+                waiting.schedule(site_idx, vec![arc.face], sites, &top, yl, &mut eq);
+            }
+            Event::Appear(site_idx) => {
+                let (cell, others) = waiting.take(site_idx)?;
+                let (_, arc_idx) = beachline
+                    .arc_under_point(&Point::new(cell.point.x.clone(), yl.clone()))?
+                    .ok_or(Error::InvariantViolation("site below an empty beachline"))?;
+                let meets = |other: usize| cell.meets(sites, site_idx, other);
+                let arcs = arcs_at(&beachline, arc_idx, meets)?;
+                if let Some(&(face, _)) = arcs.iter().find(|(face, _)| !cell.around.contains(face))
+                {
+                    let mut around = cell.around;
+                    around.push(face);
+                    waiting.schedule(site_idx, around, sites, &top, yl, &mut eq);
+                    continue;
+                }
+                // besides the arcs there, the sites around that meet at the
+                // point with their arcs gone and those about to come up there
+                let meeting = cell
+                    .around
+                    .iter()
+                    .chain(&others)
+                    .copied()
+                    .filter(|&other| meets(other))
+                    .chain(arcs.iter().map(|&(face, _)| face))
+                    .collect::<Vec<_>>();
+                if !has_room(sites, site_idx, &meeting) {
+                    continue;
+                }
+                // From left to right the arcs meeting at the point follow the
+                // convex hull of their sites counter-clockwise, so the new arc
+                // goes in after the first of them whose hull edge it sees.
+                // The arcs it passes over end there with circle events.
+                let arc_idx = arcs
+                    .windows(2)
+                    .find(|pair| {
+                        orient2d(&points[pair[0].0], &points[pair[1].0], &points[site_idx])
+                            == Ordering::Less
+                    })
+                    .map_or(arc_idx, |pair| pair[0].1);
+                split_arc(
+                    arc_idx,
+                    &points[site_idx],
+                    site_idx,
+                    yl,
+                    &mut eq,
+                    &mut beachline,
+                    &mut diagram,
+                )?;
+            }
+            Event::Circle(arc_idx, generation) => {
+                if beachline.generation(arc_idx) != generation {
+                    return Err(Error::InvariantViolation("stale circle event"));
+                }
+                remove_parabola(arc_idx, &mut eq, &mut beachline, &mut diagram, yl)?
+            }
+        }
+    }
+
+    close_diagram(&points, region, &beachline, &mut diagram);
+    Ok(diagram)
+}
+
+/// Start the beachline with the arcs of the sites on the first row, in
+/// `row` from left to right, all reaching up to infinity.
+fn add_first_row<S: Scalar>(
+    sites: &[WeightedSite<S>],
+    row: &[usize],
+    eq: &mut EventQueue<S>,
+    beachline: &mut Beachline<S>,
+    diagram: &mut Diagram<S>,
+) -> Result<(), Error> {
+    // a site keeps its cell while it reaches below the line between the
+    // lifted sites on either side of it
+    let mut cells: Vec<usize> = vec![];
+    for &idx in row {
+        while let [.., a, b] = cells[..] {
+            if power_test_collinear(&sites[a], &sites[idx], &sites[b]) == Ordering::Greater {
+                break;
+            }
+            cells.pop();
+        }
+        cells.push(idx);
+    }
+
+    let Some((&first, rest)) = cells.split_first() else {
+        return Ok(());
+    };
+    let arc = |idx: usize| Arc::new(sites[idx].point.clone(), idx);
+    let yl = sites[first].point.y.clone();
+    let mut arc_idx = beachline.add_first_parabola(arc(first))?;
+    let mut left = first;
+    for &idx in rest {
+        arc_idx = add_arc_right(
+            arc_idx,
+            arc(left),
+            arc(idx),
+            yl.clone(),
+            eq,
+            beachline,
+            diagram,
+        )?;
+        left = idx;
+    }
+    Ok(())
+}
+
+/// A site whose arc has yet to come up through the beachline.
+struct Hidden<S> {
+    /// Sites whose arcs have been found above the cell of this one
+    around: Vec<usize>,
+    /// The highest point of the cell as far as those sites go
+    point: Point<S>,
+    /// The site besides the last one around that bounds the cell at that
+    /// point, if it is a corner of the cell
+    corner: Option<usize>,
+}
+
+impl<S: Scalar> Hidden<S> {
+    /// Whether `other` has the same power distance at the point as `site`.
+    /// At a corner this is decided from the sites it comes from rather than
+    /// from its rounded coordinates.
+    fn meets(&self, sites: &[WeightedSite<S>], site: usize, other: usize) -> bool {
+        let last = self.around[self.around.len() - 1];
+        match self.corner {
+            Some(corner) => {
+                power_test(&sites[site], &sites[last], &sites[corner], &sites[other])
+                    == Ordering::Equal
+            }
+            None => {
+                other == last
+                    || power(&sites[other], &self.point) == power(&sites[site], &self.point)
+            }
+        }
+    }
+}
+
+/// The sites whose arcs have yet to come up through the beachline, by index
+/// and by the point they are queued to come up at.
+struct Waiting<S> {
+    cells: HashMap<usize, Hidden<S>>,
+    at: HashMap<Point<S>, Vec<usize>>,
+}
+
+impl<S> Default for Waiting<S> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+            at: HashMap::new(),
+        }
+    }
+}
+
+impl<S: Scalar> Waiting<S> {
+    /// Queue the arc of `site` to come up at the highest point of its cell
+    /// as far as the sites `around` it go, unless they leave it no cell.
+    fn schedule(
+        &mut self,
+        site: usize,
+        around: Vec<usize>,
+        sites: &[WeightedSite<S>],
+        top: &S,
+        yl: S,
+        eq: &mut EventQueue<S>,
+    ) {
+        let Some((point, corner)) = highest_point(sites, site, &around, top) else {
+            return;
+        };
+        let height = power_circle_bottom(&point, &sites[site], top);
+        eq.push(
+            Event::Appear(site),
+            (min(height, yl), false, Reverse(point.x.clone())),
+        );
+        self.at.entry(point.clone()).or_default().push(site);
+        self.cells.insert(
+            site,
+            Hidden {
+                around,
+                point,
+                corner,
+            },
+        );
+    }
+
+    /// Stop waiting for `site`, returning where it was queued to come up
+    /// and the other sites queued to the same point.
+    fn take(&mut self, site: usize) -> Result<(Hidden<S>, Vec<usize>), Error> {
+        let cell = self
+            .cells
+            .remove(&site)
+            .ok_or(Error::InvariantViolation("stale appearance"))?;
+        let others = self.at.get_mut(&cell.point).map_or(vec![], |others| {
+            others.retain(|&other| other != site);
+            others.clone()
+        });
+        Ok((cell, others))
+    }
+}
+
+/// The point where the arc of `site` comes up first, if only the sites in
+/// `around` were in its way, along with the site bounding it there if any,
+/// or `None` if they leave it no cell. Whenever a site is added to `around`
+/// the point so far is in its way, so the new one lies on the edge with the
+/// last site, as in Seidel's linear programming.
+fn highest_point<S: Scalar>(
+    sites: &[WeightedSite<S>],
+    site: usize,
+    around: &[usize],
+    top: &S,
+) -> Option<(Point<S>, Option<usize>)> {
+    let s = &sites[site];
+    let (&last, rest) = around.split_last()?;
+    let base = bisector_point(s, &sites[last]);
+    let along = normal_vector(sites[last].point.clone() - s.point.clone());
+
+    // the part of the edge, from base + lo * along to base + hi * along,
+    // where s has a smaller power distance than the rest of them, along with
+    // the sites bounding it
+    let mut lo: Option<(S, usize)> = None;
+    let mut hi: Option<(S, usize)> = None;
+    let dot = |p: &Point<S>, q: &Point<S>| p.x.clone() * q.x.clone() + p.y.clone() * q.y.clone();
+    let towards_last = sites[last].point.clone() - s.point.clone();
+    for &idx in rest {
+        let a = &sites[idx];
+        let slack = power(a, &base) - power(s, &base);
+        let d = a.point.clone() - s.point.clone();
+        let slope = dot(&d, &along) * S::from_f64(2.0);
+        match slope.cmp(&S::zero()) {
+            Ordering::Greater => {
+                let bound = slack / slope;
+                if hi.as_ref().map_or(true, |(hi, _)| bound < *hi) {
+                    hi = Some((bound, idx));
+                }
+            }
+            Ordering::Less => {
+                let bound = slack / slope;
+                if lo.as_ref().map_or(true, |(lo, _)| bound > *lo) {
+                    lo = Some((bound, idx));
+                }
+            }
+            // a bisector along the edge, with the site on the other side,
+            // leaves s no more than the edge itself
+            Ordering::Equal
+                if slack < S::zero()
+                    || (slack == S::zero() && dot(&d, &towards_last) < S::zero()) =>
+            {
+                return None
+            }
+            Ordering::Equal => {}
+        }
+    }
+    if let (Some((lo, _)), Some((hi, _))) = (&lo, &hi) {
+        if lo > hi {
+            return None;
+        }
+    }
+
+    // The arc of s rises along the edge up to a peak, or without end when
+    // the edge is vertical
+    let (t, corner) = if along.x == S::zero() {
+        if along.y > S::zero() { hi } else { lo }.map(|(t, idx)| (t, Some(idx)))?
+    } else {
+        let length = along.x.clone() * along.x.clone() + along.y.clone() * along.y.clone();
+        let foot = ((s.point.x.clone() - base.x.clone()) * along.x.clone()
+            + (s.point.y.clone() - base.y.clone()) * along.y.clone())
+            / length.clone();
+        let lift = power(s, &(base.clone() + &(along.clone() * foot.clone()))) + top.clone();
+        let peak =
+            foot + along.y.clone() * (lift / (length * along.x.clone() * along.x.clone())).sqrt();
+        match (lo, hi) {
+            (Some((lo, idx)), _) if peak <= lo => (lo, Some(idx)),
+            (_, Some((hi, idx))) if peak >= hi => (hi, Some(idx)),
+            _ => (peak, None),
+        }
+    };
+    Some((base + &(along * t), corner))
+}
+
+/// The faces and indices of the arc at `arc_idx` and of the arcs next to it
+/// whose sites `meet` it, from left to right.
+fn arcs_at<S: Scalar>(
+    beachline: &Beachline<S>,
+    arc_idx: usize,
+    meets: impl Fn(usize) -> bool,
+) -> Result<Vec<(usize, usize)>, Error> {
+    let mut res = vec![(beachline.arc(arc_idx)?.face, arc_idx)];
+    while let Some((arc, idx)) = beachline.left_arc(res[0].1)? {
+        if !meets(arc.face) {
+            break;
+        }
+        res.insert(0, (arc.face, idx));
+    }
+    while let Some((arc, idx)) = beachline.right_arc(res[res.len() - 1].1)? {
+        if !meets(arc.face) {
+            break;
+        }
+        res.push((arc.face, idx));
+    }
+    Ok(res)
+}
+
+/// Whether `site` has a cell of some area right around a point where it has
+/// the same power distance as `faces`, rather than just a point or a line.
+/// That takes a direction in which it is closer than all of them, so they
+/// have to lie within less than half a turn around it.
+fn has_room<S: Scalar>(sites: &[WeightedSite<S>], site: usize, faces: &[usize]) -> bool {
+    let s = &sites[site].point;
+    let side = |p: &Point<S>| (p.x.cmp(&s.x), p.y.cmp(&s.y));
+    faces.iter().any(|&first| {
+        let first = &sites[first].point;
+        faces.iter().all(|&other| {
+            let other = &sites[other].point;
+            match orient2d(s, first, other) {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                Ordering::Equal => side(first) == side(other),
+            }
+        })
+    })
+}
+
+/// Power distance from `p` to `site`.
+fn power<S: Scalar>(site: &WeightedSite<S>, p: &Point<S>) -> S {
+    let dx = p.x.clone() - site.point.x.clone();
+    let dy = p.y.clone() - site.point.y.clone();
+    dx.clone() * dx + dy.clone() * dy - site.weight.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use ordered_float::OrderedFloat;
+
+    use crate::{
+        fortunes_algorithm,
+        geometry::{polygon_area, BoundingBox, ConvexPolygon, Point},
+        locate::PointLocator,
+        test_utils::{bounding_box, check_diagram_by, random_sites, same_diagram},
+    };
+
+    use super::*;
+
+    fn weighted(points: &[Point], weights: impl Fn(usize) -> f64) -> Vec<WeightedSite> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(idx, &point)| WeightedSite::new(point, OrderedFloat(weights(idx))))
+            .collect()
+    }
+
+    /// The distance [`check_diagram_by`] checks power diagrams with.
+    fn power_distance<S: Scalar>(p: &Point<S>, site: &WeightedSite<S>) -> f64 {
+        power(site, p).to_f64()
+    }
+
+    #[test]
+    fn equal_weights_give_voronoi_diagram() {
+        let bounding_box = bounding_box();
+        let mut points = random_sites(300, 1000.0, 21);
+        // a grid full of cocircular sites, with a duplicate
+        for i in 0..5 {
+            for j in 0..5 {
+                points.push(Point::new(
+                    OrderedFloat(400.0 + 50.0 * i as f64),
+                    OrderedFloat(400.0 + 50.0 * j as f64),
+                ));
+            }
+        }
+        points.push(points[310]);
+        let voronoi = fortunes_algorithm(&points, &bounding_box);
+
+        for weight in [0.0, 7.5] {
+            let diagram = power_diagram(&weighted(&points, |_| weight), &bounding_box).unwrap();
+            assert!(same_diagram(&diagram, &voronoi, 1e-6));
+        }
+
+        let line =
+            [1.0, 4.0, 9.0].map(|x| Point::new(OrderedFloat(x * 100.0), OrderedFloat(300.0)));
+        let diagram = power_diagram(&weighted(&line, |_| 0.0), &bounding_box).unwrap();
+        assert!(same_diagram(
+            &diagram,
+            &fortunes_algorithm(&line, &bounding_box),
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn random_weights() {
+        let bounding_box = bounding_box();
+        let points = random_sites(400, 1000.0, 22);
+        let weights = random_sites(400, 5000.0, 23);
+        let sites = weighted(&points, |idx| weights[idx].x.into_inner());
+        let diagram = power_diagram(&sites, &bounding_box).unwrap();
+        assert!(check_diagram_by(&sites, &diagram, 1e-9, power_distance));
+
+        let cells = diagram.cell_polygons(&bounding_box);
+        let outweighed = (0..sites.len())
+            .filter(|&face| cells[face].is_empty())
+            .count();
+        assert!(outweighed > 0);
+        let area = cells
+            .iter()
+            .map(|cell| polygon_area(cell))
+            .sum::<OrderedFloat<f64>>();
+        assert!((area.into_inner() - 1e6).abs() < 1e-6);
+
+        // every point lies in the cell of the site it is closest to
+        for p in random_sites(500, 1000.0, 24) {
+            let nearest = (0..sites.len())
+                .min_by_key(|&idx| power(&sites[idx], &p))
+                .unwrap();
+            let cell = ConvexPolygon::new(cells[nearest].clone()).unwrap();
+            let dist = |q: &Point| (q.x - p.x).abs() + (q.y - p.y).abs();
+            assert!(cell.contains(&p) || cells[nearest].iter().any(|q| dist(q) < 1e-6));
+        }
+    }
+
+    #[test]
+    fn outweighed_sites() {
+        let bounding_box = bounding_box();
+        let site = |x: f64, y: f64, weight: f64| {
+            WeightedSite::new(
+                Point::new(OrderedFloat(x), OrderedFloat(y)),
+                OrderedFloat(weight),
+            )
+        };
+        let sites = [
+            site(200.0, 200.0, 2e5),
+            site(800.0, 200.0, 2e5),
+            site(500.0, 800.0, 2e5),
+            // above the triangle of the lifted sites around it
+            site(500.0, 400.0, 0.0),
+            // on top of heavier ones
+            site(200.0, 200.0, 1e5),
+            site(200.0, 200.0, 2e5),
+            site(800.0, 200.0, 4e5),
+        ];
+        let mut diagram = power_diagram(&sites, &bounding_box).unwrap();
+        assert!(check_diagram_by(&sites, &diagram, 1e-9, power_distance));
+        assert_eq!(diagram.site_map(), [0, 1, 2, 3, 4, 0, 6]);
+        assert_eq!(diagram.convex_hull(), [0, 6, 2]);
+        assert_eq!(diagram.vertices().len(), 4);
+        assert_eq!(diagram.half_edges().len(), 6);
+
+        let cells = diagram.cell_polygons(&bounding_box);
+        for (face, cell) in cells.iter().enumerate() {
+            assert_eq!(cell.is_empty(), ![0, 2, 6].contains(&face));
+        }
+        assert!(diagram.is_weighted());
+        assert!(matches!(
+            diagram.insert_site(sites[3].point, &bounding_box),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn cells_shrunk_to_a_point_or_line() {
+        let bounding_box = bounding_box();
+        let site = |x: f64, y: f64, weight: f64| {
+            WeightedSite::new(
+                Point::new(OrderedFloat(x), OrderedFloat(y)),
+                OrderedFloat(weight),
+            )
+        };
+        let cases = [
+            // the second one only has the point where it meets the others
+            (
+                vec![
+                    site(700.0, 900.0, 3e4),
+                    site(800.0, 800.0, 1e4),
+                    site(700.0, 700.0, 3e4),
+                    site(900.0, 800.0, 2e4),
+                ],
+                1,
+            ),
+            // the first one only has a line, where it comes up at the same
+            // point as the last one
+            (
+                vec![
+                    site(200.0, 800.0, 0.0),
+                    site(100.0, 900.0, 3e4),
+                    site(300.0, 700.0, 1e4),
+                ],
+                0,
+            ),
+        ];
+        for (sites, shrunk) in cases {
+            let diagram = power_diagram(&sites, &bounding_box).unwrap();
+            assert!(check_diagram_by(&sites, &diagram, 1e-9, power_distance));
+            let cells = diagram.cell_polygons(&bounding_box);
+            assert!(cells[shrunk].is_empty());
+            let area = cells
+                .iter()
+                .map(|cell| polygon_area(cell))
+                .sum::<OrderedFloat<f64>>();
+            assert!((area.into_inner() - 1e6).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn cannot_locate_points() {
+        // the nearest site is not the one of least power distance
+        let sites = [
+            WeightedSite::new(
+                Point::new(OrderedFloat(200.0), OrderedFloat(500.0)),
+                OrderedFloat(0.0),
+            ),
+            WeightedSite::new(
+                Point::new(OrderedFloat(800.0), OrderedFloat(500.0)),
+                OrderedFloat(3e5),
+            ),
+            WeightedSite::new(
+                Point::new(OrderedFloat(500.0), OrderedFloat(900.0)),
+                OrderedFloat(0.0),
+            ),
+        ];
+        let diagram = power_diagram(&sites, &bounding_box()).unwrap();
+        assert!(matches!(
+            PointLocator::new(&diagram),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn sites_on_a_line() {
+        let bounding_box = BoundingBox::new(
+            OrderedFloat(0.0),
+            OrderedFloat(10.0),
+            OrderedFloat(0.0),
+            OrderedFloat(10.0),
+        );
+        let site = |x: f64, weight: f64| {
+            WeightedSite::new(
+                Point::new(OrderedFloat(x), OrderedFloat(5.0)),
+                OrderedFloat(weight),
+            )
+        };
+        let sites = [
+            site(7.0, 0.0),
+            site(4.0, -10.0),
+            site(1.0, 0.0),
+            site(5.0, 3.0),
+            site(3.0, 0.0),
+        ];
+        let diagram = power_diagram(&sites, &bounding_box).unwrap();
+        assert!(check_diagram_by(&sites, &diagram, 1e-9, power_distance));
+        assert_eq!(diagram.convex_hull(), [2, 0]);
+        assert!(diagram.neighbours(1).is_empty());
+
+        let mut lines = diagram
+            .segments()
+            .iter()
+            .map(|[a, b]| {
+                assert_eq!(a.x, b.x);
+                assert_eq!([a.y, b.y].iter().min().unwrap().into_inner(), 0.0);
+                assert_eq!([a.y, b.y].iter().max().unwrap().into_inner(), 10.0);
+                a.x.into_inner()
+            })
+            .collect::<Vec<_>>();
+        lines.sort_by(f64::total_cmp);
+        assert_eq!(lines, [2.0, 3.25, 6.75]);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bounding_box = bounding_box();
+        let site = |x: f64, weight: f64| {
+            WeightedSite::new(
+                Point::new(OrderedFloat(x), OrderedFloat(5.0)),
+                OrderedFloat(weight),
+            )
+        };
+        assert_eq!(
+            power_diagram(&[site(1.0, 0.0), site(2.0, f64::NAN)], &bounding_box).err(),
+            Some(Error::NonFiniteSite(1))
+        );
+        assert_eq!(
+            power_diagram(&[site(-1.0, 0.0)], &bounding_box).err(),
+            Some(Error::SiteOutsideBoundingBox(0))
+        );
+
+        let diagram = power_diagram(&[site(1.0, 0.0), site(1.0, 3.0)], &bounding_box).unwrap();
+        assert_eq!(diagram.convex_hull(), [1]);
+        assert_eq!(diagram.cell_polygons(&bounding_box)[1].len(), 4);
+        assert!(power_diagram::<OrderedFloat<f64>, _>(&[], &bounding_box)
+            .unwrap()
+            .faces()
+            .is_empty());
+    }
+
+    #[cfg(feature = "rational")]
+    #[test]
+    fn rational_vertices_are_exact() {
+        use num_rational::BigRational;
+
+        let sites = random_sites(60, 1000.0, 25)
+            .iter()
+            .zip(random_sites(60, 3000.0, 26))
+            .map(|(p, w)| {
+                let round = |v: f64| BigRational::from_f64(v.round());
+                WeightedSite::new(
+                    Point::new(round(p.x.into_inner()), round(p.y.into_inner())),
+                    round(w.x.into_inner()),
+                )
+            })
+            .collect::<Vec<_>>();
+        let diagram = power_diagram(&sites, &bounding_box::<BigRational>()).unwrap();
+        for v in 0..diagram.vertices().len() {
+            if diagram.is_ray_end(v) {
+                continue;
+            }
+            let p = &diagram.vertex(v).point;
+            let faces = diagram
+                .outgoing(v)
+                .into_iter()
+                .map(|edge| diagram.half_edge(edge).face)
+                .collect::<Vec<_>>();
+            let nearest = sites.iter().map(|site| power(site, p)).min().unwrap();
+            assert!(faces.iter().all(|&face| power(&sites[face], p) == nearest));
+        }
+    }
+}
@@ -1,7 +1,32 @@
 use approx::relative_eq;
 use itertools::Itertools;
 
-use crate::{Point, Segment};
+use ordered_float::OrderedFloat;
+
+use crate::{
+    diagram::Diagram,
+    geometry::{distance, polygon_area, BoundingBox, ClipRegion, Point, Scalar, Segment},
+};
+
+/// The square from 0 to 1000 on both axes.
+pub fn bounding_box<S: Scalar>() -> BoundingBox<S> {
+    BoundingBox::new(
+        S::from_f64(0.0),
+        S::from_f64(1000.0),
+        S::from_f64(0.0),
+        S::from_f64(1000.0),
+    )
+}
+
+/// The point at `x`, `y`.
+pub fn point(x: f64, y: f64) -> Point {
+    Point::new(OrderedFloat(x), OrderedFloat(y))
+}
+
+pub fn squared_distance<S: Scalar>(a: &Point<S>, b: &Point<S>) -> S {
+    let d = a.clone() - b.clone();
+    d.x.clone() * d.x + d.y.clone() * d.y
+}
 
 pub fn compare_points(a: &Point, b: &Point) -> bool {
     relative_eq!(a.x.into_inner(), b.x.into_inner())
@@ -33,3 +58,158 @@ pub fn compare_edges(a: &[Segment], b: &[Segment]) -> bool {
 
     false
 }
+
+/// Whether `a` and `b` only differ in the order of their vertices and
+/// edges: every face has the same neighbours, with the edges between them
+/// ending at points no further than `eps` apart.
+pub fn same_diagram<S: Scalar>(a: &Diagram<S>, b: &Diagram<S>, eps: f64) -> bool {
+    if a.site_map() != b.site_map()
+        || a.convex_hull() != b.convex_hull()
+        || a.vertices().len() != b.vertices().len()
+        || a.half_edges().len() != b.half_edges().len()
+    {
+        return false;
+    }
+    let edges = |diagram: &Diagram<S>, face: usize| {
+        let point = |v: Option<usize>| {
+            let p = &diagram.vertex(v.unwrap()).point;
+            (p.x.to_f64(), p.y.to_f64())
+        };
+        let mut res = diagram
+            .neighbours(face)
+            .into_iter()
+            .map(|neighbour| {
+                let edge = neighbour.edge;
+                let ends = (
+                    point(diagram.half_edge(edge).origin),
+                    point(diagram.destination(edge)),
+                );
+                (neighbour.face, ends)
+            })
+            .collect::<Vec<_>>();
+        res.sort_by_key(|&(face, _)| face);
+        res
+    };
+    let close = |p: (f64, f64), q: (f64, f64)| (p.0 - q.0).abs() <= eps && (p.1 - q.1).abs() <= eps;
+    (0..a.faces().len()).all(|face| {
+        let (ea, eb) = (edges(a, face), edges(b, face));
+        ea.len() == eb.len()
+            && ea.iter().zip(&eb).all(|((fa, (a0, a1)), (fb, (b0, b1)))| {
+                fa == fb && close(*a0, *b0) && close(*a1, *b1)
+            })
+    })
+}
+
+/// Deterministic pseudo-random sites inside `[0, size)^2`.
+pub fn random_sites(n: usize, size: f64, seed: u64) -> Vec<Point> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 11) as f64 / (1u64 << 53) as f64 * size
+    };
+    (0..n)
+        .map(|_| {
+            let x = next();
+            let y = next();
+            Point::new(x.into(), y.into())
+        })
+        .collect()
+}
+
+/// Check that every edge of `diagram` lies on the bisector of the two sites
+/// it separates and that no other site is closer to its endpoints, and that
+/// the half-edges are linked up consistently.
+pub fn check_diagram<S: Scalar>(sites: &[Point<S>], diagram: &Diagram<S>) -> bool {
+    check_diagram_with_tolerance(sites, diagram, 1e-6)
+}
+
+/// Like [`check_diagram`], with distances allowed to be off by `eps` relative
+/// to their size.
+pub fn check_diagram_with_tolerance<S: Scalar>(
+    sites: &[Point<S>],
+    diagram: &Diagram<S>,
+    eps: f64,
+) -> bool {
+    let to_f64 = |p: &Point<S>| Point::new(OrderedFloat(p.x.to_f64()), OrderedFloat(p.y.to_f64()));
+    check_diagram_by(sites, diagram, eps, |a, b| {
+        distance(&to_f64(a), &to_f64(b)).into_inner()
+    })
+}
+
+/// Like [`check_diagram_with_tolerance`], with the sites nearest under
+/// `distance` instead, which may also weigh them.
+pub fn check_diagram_by<S: Scalar, T>(
+    sites: &[T],
+    diagram: &Diagram<S>,
+    eps: f64,
+    distance: impl Fn(&Point<S>, &T) -> f64,
+) -> bool {
+    for (idx, edge) in diagram.half_edges().iter().enumerate() {
+        let (Some(a), Some(b)) = (edge.origin, diagram.destination(idx)) else {
+            return false;
+        };
+        if diagram.twin(edge.twin) != idx || diagram.half_edge(edge.twin).face == edge.face {
+            return false;
+        }
+        if let Some(next) = edge.next {
+            let next = diagram.half_edge(next);
+            if next.prev != Some(idx) || next.origin != Some(b) || next.face != edge.face {
+                return false;
+            }
+        }
+        let p = &sites[edge.face];
+        let q = &sites[diagram.half_edge(edge.twin).face];
+        for v in [a, b] {
+            let v = &diagram.vertex(v).point;
+            let d = distance(v, p);
+            let tolerance = eps * d.abs().max(1.0);
+            if (d - distance(v, q)).abs() > tolerance {
+                return false;
+            }
+            if sites.iter().any(|s| distance(v, s) < d - tolerance) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Check that `cells` are convex counter-clockwise polygons inside `region`,
+/// each holding the site of its face, and that together they cover the
+/// region.
+pub fn check_cells<S: Scalar, R: ClipRegion<S>>(
+    diagram: &Diagram<S>,
+    cells: &[Vec<Point<S>>],
+    region: &R,
+) -> bool {
+    let area = |polygon: &[Point<S>]| polygon_area(polygon).to_f64();
+    let region_area = area(&region.vertices());
+    let inside = |p: &Point<S>| {
+        let vertices = region.vertices();
+        (0..vertices.len()).all(|i| {
+            let edge = [
+                vertices[i].clone(),
+                vertices[(i + 1) % vertices.len()].clone(),
+            ];
+            area(&[edge[0].clone(), edge[1].clone(), p.clone()]) >= -1e-9 * region_area
+        })
+    };
+
+    let mut total = 0.0;
+    for (face, cell) in cells.iter().enumerate() {
+        if cell.is_empty() {
+            continue;
+        }
+        let site = &diagram.face(face).site;
+        for (idx, a) in cell.iter().enumerate() {
+            let b = &cell[(idx + 1) % cell.len()];
+            if !inside(a) || area(&[a.clone(), b.clone(), site.clone()]) < 0.0 {
+                return false;
+            }
+        }
+        total += area(cell);
+    }
+    (total - region_area).abs() <= 1e-9 * region_area
+}
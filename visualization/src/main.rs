@@ -72,7 +72,8 @@ fn draw_solution(context: &CanvasRenderingContext2d, sites: &Vec<Point>, edges:
 
 #[component]
 fn App() -> impl IntoView {
-    let bounding_box = BoundingBox::new(0.0.into(), WIDTH.into(), 0.0.into(), HEIGHT.into());
+    let bounding_box: BoundingBox =
+        BoundingBox::new(0.0.into(), WIDTH.into(), 0.0.into(), HEIGHT.into());
 
     let canvas_ref: NodeRef<html::Canvas> = create_node_ref::<html::Canvas>();
     create_effect(move |_| {
@@ -111,7 +112,7 @@ fn App() -> impl IntoView {
                 <button
                     class="rounded bg-sky-300 px-4 py-2 font-bold text-slate-900 hover:bg-sky-500"
                     on:click=move |_| {
-                        let edges = fortunes_algorithm(&sites(), &bounding_box);
+                        let edges = fortunes_algorithm(&sites(), &bounding_box).segments();
                         for edge in &edges {
                             log!("{:?}", edge);
                         }